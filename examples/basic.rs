@@ -5,9 +5,9 @@ use vm6502::prelude::*;
 
 fn main() {
     let mut vm = VirtualMachine::new();
-    vm.set_program(0x0000, "6901690100");
+    vm.set_program(0x0000, "6901690100").unwrap();
 
-    vm.run(Duration::from_millis(10));
+    vm.run(Duration::from_millis(10)).unwrap();
 
     assert_eq!(vm.registers.ac, 0x02);
 }
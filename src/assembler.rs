@@ -1,6 +1,759 @@
 //! High performance 6502 assembler.
 //!
-// TODO Interpretation
-//use crate::prelude::*;
+//! A two-pass assembler for the standard 6502 mnemonics. The first pass lays out
+//! every statement and collects labels, the second pass resolves operands and
+//! emits the final bytes. Opcodes are looked up from [COMPLETE_OPCODE_TABLE] and
+//! [OP_MODES], so the assembler always agrees with the virtual machine.
+//!
+//! # Syntax
+//! - `LDA #$BB` immediate, `LDA $LL` zero page, `LDA $HHLL` absolute
+//! - `LDA $LL,X` / `LDX $LL,Y` indexed, `LDA ($LL,X)` / `LDA ($LL),Y` indirect indexed
+//! - `JMP ($HHLL)` indirect, `ASL A` (or just `ASL`) accumulator
+//! - `BNE loop` relative branches to labels or addresses
+//! - `label:` defines a label, `name = $10` defines a constant
+//! - `.org $0600` (or `*= $0600`), `.byte $01, "text"`, `.word label`
+//! - Numbers are `$hex`, `%binary`, decimal or `'c'`. `<expr` and `>expr` select the low and high byte.
+//! - `;` starts a comment.
+//!
+//! # Example
+//! ```
+//! use vm6502::prelude::*;
+//!
+//! let bytes = assemble("loop: ADC #$01\n BNE loop\n BRK").unwrap();
+//! assert_eq!(bytes, vec![0x69, 0x01, 0xD0, 0xFC, 0x00]);
+//!
+//! let mut vm = VirtualMachine::new();
+//! vm.insert_bytes(0x0000, bytes);
+//! ```
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::assembler::{assemble, Assembler, AssemblerError, AssemblerErrorKind};
+}
+
+/// The reason an [AssemblerError] was raised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblerErrorKind {
+    /// The line could not be parsed.
+    Syntax(String),
+    /// The mnemonic is not a documented 6502 instruction.
+    UnknownMnemonic(String),
+    /// The instruction does not support the requested addressing mode.
+    InvalidMode(String, Mode),
+    /// A label was referenced but never defined.
+    UndefinedLabel(String),
+    /// A label was defined more than once.
+    DuplicateLabel(String),
+    /// A value does not fit in its operand.
+    ValueOutOfRange(i64),
+    /// A relative branch target is further than -128..=127 bytes away.
+    BranchOutOfRange(i64),
+    /// `.org` attempted to move the output backwards.
+    OriginBackwards(u16),
+}
+
+/// An assembly error and the (1-based) source line it occurred on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerError {
+    pub line: usize,
+    pub kind: AssemblerErrorKind,
+}
+
+impl AssemblerError {
+    fn new(line: usize, kind: AssemblerErrorKind) -> Self {
+        AssemblerError { line, kind }
+    }
+}
+
+impl Display for AssemblerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AssemblerErrorKind::Syntax(msg) => write!(f, "syntax error: {}", msg),
+            AssemblerErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic {}", m),
+            AssemblerErrorKind::InvalidMode(m, mode) => {
+                write!(f, "{} does not support {:?} addressing", m, mode)
+            }
+            AssemblerErrorKind::UndefinedLabel(l) => write!(f, "undefined label {}", l),
+            AssemblerErrorKind::DuplicateLabel(l) => write!(f, "duplicate label {}", l),
+            AssemblerErrorKind::ValueOutOfRange(v) => write!(f, "value {} out of range", v),
+            AssemblerErrorKind::BranchOutOfRange(o) => {
+                write!(f, "branch offset {} out of range", o)
+            }
+            AssemblerErrorKind::OriginBackwards(o) => {
+                write!(f, "origin ${:04X} is behind the current address", o)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+type AsmResult<T> = std::result::Result<T, AssemblerError>;
+
+/// Byte selection prefix of an expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Select {
+    Word,
+    Low,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Number(i64),
+    Label(String),
+}
+
+/// A sum of terms, optionally narrowed to the low or high byte.
+#[derive(Debug, Clone, PartialEq)]
+struct Expr {
+    select: Select,
+    terms: Vec<(i64, Term)>,
+}
+
+/// The addressing syntax of an operand, before the final mode is chosen.
+enum Operand {
+    None,
+    Accumulator,
+    Immediate(Expr),
+    Direct(Expr),
+    IndexedX(Expr),
+    IndexedY(Expr),
+    Indirect(Expr),
+    IndirectX(Expr),
+    IndirectY(Expr),
+}
+
+/// A laid out statement from the first pass.
+enum Statement {
+    Instruction {
+        line: usize,
+        address: u16,
+        opcode: u8,
+        mode: Mode,
+        operand: Option<Expr>,
+    },
+    Bytes {
+        line: usize,
+        values: Vec<Expr>,
+    },
+    Words {
+        line: usize,
+        values: Vec<Expr>,
+    },
+    Org(u16),
+}
+
+/// Two-pass 6502 assembler.
+///
+/// The assembler keeps the labels of the last assembled source around so that
+/// hosts can look up entry points after assembling.
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    origin: u16,
+    labels: HashMap<String, u16>,
+    encodings: HashMap<(&'static str, Mode), u8>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Assembler {
+    /// Create an assembler that places code at address 0x0000.
+    pub fn new() -> Self {
+        Self::with_origin(0x0000)
+    }
+
+    /// Create an assembler that places code at `origin`.
+    pub fn with_origin(origin: u16) -> Self {
+        let encodings = VALID_OPCODES
+            .iter()
+            .zip(OP_MODES.iter())
+            .map(|(op, mode)| ((COMPLETE_OPCODE_TABLE[*op as usize].0, *mode), *op))
+            .collect();
+
+        Assembler {
+            origin,
+            labels: HashMap::new(),
+            encodings,
+        }
+    }
+
+    /// The address of the first emitted byte. A leading `.org` replaces the initial origin.
+    pub fn origin(&self) -> u16 {
+        self.origin
+    }
+
+    /// All labels and constants defined by the last assembled source.
+    pub fn labels(&self) -> &HashMap<String, u16> {
+        &self.labels
+    }
+
+    /// Look up a single label.
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// Look up the opcode for `mnemonic` in `mode`.
+    pub fn opcode(&self, mnemonic: &str, mode: Mode) -> Option<u8> {
+        let mnemonic = mnemonic.to_uppercase();
+        self.encodings.get(&(mnemonic.as_str(), mode)).copied()
+    }
+
+    fn has_mode(&self, mnemonic: &str, mode: Mode) -> bool {
+        self.encodings.contains_key(&(mnemonic, mode))
+    }
+
+    /// Assemble `source` into a flat series of bytes starting at [origin](Assembler::origin).
+    pub fn assemble(&mut self, source: &str) -> AsmResult<Vec<u8>> {
+        self.labels.clear();
+        let statements = self.first_pass(source)?;
+        self.second_pass(&statements)
+    }
+
+    /// Lay out every statement, recording label addresses and instruction sizes.
+    fn first_pass(&mut self, source: &str) -> AsmResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+        let mut pc = self.origin as i64;
+        let mut emitted = false;
+
+        for (i, raw) in source.lines().enumerate() {
+            let line = i + 1;
+            let mut text = strip_comment(raw).trim();
+
+            // Labels, possibly followed by a statement on the same line.
+            while let Some(colon) = label_end(text) {
+                let name = &text[..colon];
+                self.define(line, name, pc)?;
+                text = text[colon + 1..].trim();
+            }
+
+            if text.is_empty() {
+                continue;
+            }
+
+            // Constant definitions and origin changes.
+            if let Some((name, value)) = split_assignment(text) {
+                let expr = parse_expr(line, value, pc)?;
+                let value = self.eval(line, &expr, true)?.unwrap();
+
+                if name == "*" {
+                    pc = self.set_origin(line, &mut statements, pc, value, emitted)?;
+                } else {
+                    self.define(line, name, value)?;
+                }
+                continue;
+            }
+
+            let (head, rest) = match text.find(char::is_whitespace) {
+                Some(idx) => (&text[..idx], text[idx..].trim()),
+                None => (text, ""),
+            };
+
+            if let Some(directive) = head.strip_prefix('.') {
+                match directive.to_lowercase().as_str() {
+                    "org" => {
+                        let expr = parse_expr(line, rest, pc)?;
+                        let value = self.eval(line, &expr, true)?.unwrap();
+                        pc = self.set_origin(line, &mut statements, pc, value, emitted)?;
+                    }
+                    "byte" | "db" => {
+                        let values = parse_data(line, rest, pc)?;
+                        pc += values.len() as i64;
+                        statements.push(Statement::Bytes { line, values });
+                        emitted = true;
+                    }
+                    "word" | "dw" => {
+                        let values = parse_data(line, rest, pc)?;
+                        pc += 2 * values.len() as i64;
+                        statements.push(Statement::Words { line, values });
+                        emitted = true;
+                    }
+                    _ => {
+                        return Err(AssemblerError::new(
+                            line,
+                            AssemblerErrorKind::Syntax(format!("unknown directive .{}", directive)),
+                        ))
+                    }
+                }
+                continue;
+            }
+
+            let mnemonic = head.to_uppercase();
+            let operand = parse_operand(line, rest, pc)?;
+            let (mode, operand) = self.select_mode(line, &mnemonic, operand)?;
+            let opcode = self.opcode(&mnemonic, mode).unwrap();
+
+            statements.push(Statement::Instruction {
+                line,
+                address: pc as u16,
+                opcode,
+                mode,
+                operand,
+            });
+            pc += 1 + mode.operand_len() as i64;
+            emitted = true;
+
+            if pc > 0x10000 {
+                return Err(AssemblerError::new(
+                    line,
+                    AssemblerErrorKind::ValueOutOfRange(pc),
+                ));
+            }
+        }
+
+        Ok(statements)
+    }
+
+    /// Resolve all operands and emit the program bytes.
+    fn second_pass(&self, statements: &[Statement]) -> AsmResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        for statement in statements {
+            match statement {
+                Statement::Org(address) => {
+                    let fill = *address as usize - self.origin as usize;
+                    bytes.resize(fill, 0x00);
+                }
+                Statement::Bytes { line, values } => {
+                    for value in values {
+                        let v = self.eval(*line, value, true)?.unwrap();
+                        bytes.push(to_byte(*line, v)?);
+                    }
+                }
+                Statement::Words { line, values } => {
+                    for value in values {
+                        let v = self.eval(*line, value, true)?.unwrap();
+                        bytes.extend_from_slice(&to_word(*line, v)?.to_le_bytes());
+                    }
+                }
+                Statement::Instruction {
+                    line,
+                    address,
+                    opcode,
+                    mode,
+                    operand,
+                } => {
+                    bytes.push(*opcode);
+
+                    let value = match operand {
+                        Some(expr) => self.eval(*line, expr, true)?.unwrap(),
+                        None => continue,
+                    };
+
+                    match mode {
+                        Mode::Relative => {
+                            let offset = value - (*address as i64 + 2);
+                            if !(-128..=127).contains(&offset) {
+                                return Err(AssemblerError::new(
+                                    *line,
+                                    AssemblerErrorKind::BranchOutOfRange(offset),
+                                ));
+                            }
+                            bytes.push(offset as u8);
+                        }
+                        _ if mode.operand_len() == 1 => bytes.push(to_byte(*line, value)?),
+                        _ => bytes.extend_from_slice(&to_word(*line, value)?.to_le_bytes()),
+                    }
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn define(&mut self, line: usize, name: &str, value: i64) -> AsmResult<()> {
+        if !is_identifier(name) {
+            return Err(AssemblerError::new(
+                line,
+                AssemblerErrorKind::Syntax(format!("invalid label {}", name)),
+            ));
+        }
+
+        let value = to_word(line, value)?;
+        if self.labels.insert(name.to_string(), value).is_some() {
+            return Err(AssemblerError::new(
+                line,
+                AssemblerErrorKind::DuplicateLabel(name.to_string()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Move the output address. Before any output this rebases the origin, afterwards it pads.
+    fn set_origin(
+        &mut self,
+        line: usize,
+        statements: &mut Vec<Statement>,
+        pc: i64,
+        value: i64,
+        emitted: bool,
+    ) -> AsmResult<i64> {
+        let value = to_word(line, value)?;
+
+        if !emitted {
+            self.origin = value;
+        } else if (value as i64) < pc {
+            return Err(AssemblerError::new(
+                line,
+                AssemblerErrorKind::OriginBackwards(value),
+            ));
+        } else {
+            statements.push(Statement::Org(value));
+        }
+
+        Ok(value as i64)
+    }
+
+    /// Evaluate an expression. Unknown labels are an error when `strict`, otherwise `None`.
+    fn eval(&self, line: usize, expr: &Expr, strict: bool) -> AsmResult<Option<i64>> {
+        let mut total = 0;
+
+        for (sign, term) in &expr.terms {
+            let value = match term {
+                Term::Number(n) => *n,
+                Term::Label(name) => match self.labels.get(name) {
+                    Some(v) => *v as i64,
+                    None if strict => {
+                        return Err(AssemblerError::new(
+                            line,
+                            AssemblerErrorKind::UndefinedLabel(name.clone()),
+                        ))
+                    }
+                    None => return Ok(None),
+                },
+            };
+            total += sign * value;
+        }
+
+        Ok(Some(match expr.select {
+            Select::Word => total,
+            Select::Low => total & 0xFF,
+            Select::High => (total >> 8) & 0xFF,
+        }))
+    }
+
+    /// Pick the final addressing mode for an operand.
+    ///
+    /// Operands that are unresolved during the first pass are assumed to be absolute,
+    /// keeping the layout identical between both passes.
+    fn select_mode(
+        &self,
+        line: usize,
+        mnemonic: &str,
+        operand: Operand,
+    ) -> AsmResult<(Mode, Option<Expr>)> {
+        if !self.encodings.keys().any(|(m, _)| *m == mnemonic) {
+            return Err(AssemblerError::new(
+                line,
+                AssemblerErrorKind::UnknownMnemonic(mnemonic.to_string()),
+            ));
+        }
+
+        let fits_zero_page = |expr: &Expr| -> AsmResult<bool> {
+            Ok(matches!(self.eval(line, expr, false)?, Some(v) if (0..=0xFF).contains(&v)))
+        };
+        let sized = |expr: Expr, zp: Mode, abs: Mode| -> AsmResult<(Mode, Option<Expr>)> {
+            let mode = if self.has_mode(mnemonic, zp)
+                && (fits_zero_page(&expr)? || !self.has_mode(mnemonic, abs))
+            {
+                zp
+            } else {
+                abs
+            };
+            Ok((mode, Some(expr)))
+        };
+
+        let (mode, expr) = match operand {
+            Operand::None if self.has_mode(mnemonic, Mode::Implied) => (Mode::Implied, None),
+            Operand::None | Operand::Accumulator => (Mode::Accumulator, None),
+            Operand::Immediate(e) => (Mode::Immediate, Some(e)),
+            Operand::Direct(e) if self.has_mode(mnemonic, Mode::Relative) => {
+                (Mode::Relative, Some(e))
+            }
+            Operand::Direct(e) => sized(e, Mode::ZeroPage, Mode::Absolute)?,
+            Operand::IndexedX(e) => sized(e, Mode::ZeroPageX, Mode::AbsoluteX)?,
+            Operand::IndexedY(e) => sized(e, Mode::ZeroPageY, Mode::AbsoluteY)?,
+            Operand::Indirect(e) => (Mode::Indirect, Some(e)),
+            Operand::IndirectX(e) => (Mode::IndirectX, Some(e)),
+            Operand::IndirectY(e) => (Mode::IndirectY, Some(e)),
+        };
+
+        if !self.has_mode(mnemonic, mode) {
+            return Err(AssemblerError::new(
+                line,
+                AssemblerErrorKind::InvalidMode(mnemonic.to_string(), mode),
+            ));
+        }
+
+        Ok((mode, expr))
+    }
+}
+
+/// Assemble `source` with an origin of 0x0000.
+///
+/// The returned bytes can be passed straight to [insert_bytes](ProgramController::insert_bytes).
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssemblerError> {
+    Assembler::new().assemble(source)
+}
+
+fn to_byte(line: usize, value: i64) -> AsmResult<u8> {
+    if (-128..=0xFF).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(AssemblerError::new(
+            line,
+            AssemblerErrorKind::ValueOutOfRange(value),
+        ))
+    }
+}
+
+fn to_word(line: usize, value: i64) -> AsmResult<u16> {
+    if (0..=0xFFFF).contains(&value) {
+        Ok(value as u16)
+    } else {
+        Err(AssemblerError::new(
+            line,
+            AssemblerErrorKind::ValueOutOfRange(value),
+        ))
+    }
+}
+
+fn syntax(line: usize, msg: impl Into<String>) -> AssemblerError {
+    AssemblerError::new(line, AssemblerErrorKind::Syntax(msg.into()))
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Strip a `;` comment, ignoring semicolons inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, ';') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Remove whitespace, keeping it inside quotes such as `' '`.
+fn strip_whitespace(text: &str) -> String {
+    let mut quote = None;
+    text.chars()
+        .filter(|&c| {
+            match (quote, c) {
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                _ => {}
+            }
+            quote.is_some() || !c.is_whitespace()
+        })
+        .collect()
+}
+
+/// Returns the index of the colon terminating a leading label, if any.
+fn label_end(text: &str) -> Option<usize> {
+    let colon = text.find(':')?;
+    is_identifier(&text[..colon]).then_some(colon)
+}
+
+/// Split `name = value` and `*= value` statements.
+fn split_assignment(text: &str) -> Option<(&str, &str)> {
+    let eq = text.find('=')?;
+    let name = text[..eq].trim();
+    (name == "*" || is_identifier(name)).then(|| (name, text[eq + 1..].trim()))
+}
+
+fn parse_number(line: usize, s: &str) -> AsmResult<i64> {
+    let parsed = if let Some(hex) = s.strip_prefix('$') {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix('%') {
+        i64::from_str_radix(bin, 2)
+    } else {
+        s.parse::<i64>()
+    };
+
+    parsed.map_err(|_| syntax(line, format!("invalid number {}", s)))
+}
+
+/// Parse `[<|>] term {(+|-) term}`. A term is a number, label or `'c'` char literal, and `*`
+/// evaluates to the current address `pc`.
+fn parse_expr(line: usize, text: &str, pc: i64) -> AsmResult<Expr> {
+    let text = text.trim();
+    let (select, mut rest) = match text.as_bytes().first() {
+        Some(b'<') => (Select::Low, &text[1..]),
+        Some(b'>') => (Select::High, &text[1..]),
+        _ => (Select::Word, text),
+    };
+
+    if rest.trim().is_empty() {
+        return Err(syntax(line, "missing operand"));
+    }
+
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    loop {
+        rest = rest.trim_start();
+        // A char literal can be a space or an operator itself, so it's taken whole.
+        let quoted = matches!(rest.as_bytes(), [b'\'', c, b'\'', ..] if c.is_ascii());
+        let end = if quoted {
+            3
+        } else {
+            let first = rest.chars().next().map_or(0, char::len_utf8);
+            rest[first..]
+                .find(['+', '-'])
+                .map(|i| i + first)
+                .unwrap_or(rest.len())
+        };
+        let token = rest[..end].trim_end();
+
+        let term = if quoted {
+            Term::Number(token.as_bytes()[1] as i64)
+        } else if token == "*" {
+            Term::Number(pc)
+        } else if is_identifier(token) {
+            Term::Label(token.to_string())
+        } else {
+            Term::Number(parse_number(line, token)?)
+        };
+        terms.push((sign, term));
+
+        rest = rest[end..].trim_start();
+        sign = match rest.as_bytes().first() {
+            None => break,
+            Some(b'+') => 1,
+            Some(b'-') => -1,
+            Some(_) => return Err(syntax(line, format!("expected + or - before `{}`", rest))),
+        };
+        rest = &rest[1..];
+        if rest.trim().is_empty() {
+            return Err(syntax(line, "dangling operator"));
+        }
+    }
+
+    Ok(Expr { select, terms })
+}
+
+/// Parse a `.byte`/`.word` list. Strings expand to one value per character.
+fn parse_data(line: usize, text: &str, pc: i64) -> AsmResult<Vec<Expr>> {
+    let mut values = Vec::new();
+
+    for item in split_list(text) {
+        let item = item.trim();
+        if item.len() >= 2 && item.starts_with('"') && item.ends_with('"') {
+            for b in item[1..item.len() - 1].bytes() {
+                values.push(Expr {
+                    select: Select::Word,
+                    terms: vec![(1, Term::Number(b as i64))],
+                });
+            }
+        } else {
+            values.push(parse_expr(line, item, pc)?);
+        }
+    }
+
+    if values.is_empty() {
+        return Err(syntax(line, "empty data directive"));
+    }
+
+    Ok(values)
+}
+
+/// Split on commas outside of quotes.
+fn split_list(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, ',') => {
+                items.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !text[start..].trim().is_empty() {
+        items.push(&text[start..]);
+    }
+
+    items
+}
+
+fn parse_operand(line: usize, text: &str, pc: i64) -> AsmResult<Operand> {
+    let compact = strip_whitespace(text);
+    let upper = compact.to_uppercase();
+
+    if compact.is_empty() {
+        return Ok(Operand::None);
+    }
+    if upper == "A" {
+        return Ok(Operand::Accumulator);
+    }
+    if let Some(imm) = compact.strip_prefix('#') {
+        return Ok(Operand::Immediate(parse_expr(line, imm, pc)?));
+    }
+
+    if compact.starts_with('(') {
+        let len = compact.len();
+        return if upper.ends_with(",X)") {
+            Ok(Operand::IndirectX(parse_expr(
+                line,
+                &compact[1..len - 3],
+                pc,
+            )?))
+        } else if upper.ends_with("),Y") {
+            Ok(Operand::IndirectY(parse_expr(
+                line,
+                &compact[1..len - 3],
+                pc,
+            )?))
+        } else if upper.ends_with(')') {
+            Ok(Operand::Indirect(parse_expr(
+                line,
+                &compact[1..len - 1],
+                pc,
+            )?))
+        } else {
+            Err(syntax(
+                line,
+                format!("unterminated indirect operand {}", text),
+            ))
+        };
+    }
 
-pub mod prelude {}
+    let len = compact.len();
+    if upper.ends_with(",X") {
+        Ok(Operand::IndexedX(parse_expr(
+            line,
+            &compact[..len - 2],
+            pc,
+        )?))
+    } else if upper.ends_with(",Y") {
+        Ok(Operand::IndexedY(parse_expr(
+            line,
+            &compact[..len - 2],
+            pc,
+        )?))
+    } else {
+        Ok(Operand::Direct(parse_expr(line, &compact, pc)?))
+    }
+}
@@ -0,0 +1,706 @@
+//! Debug Adapter Protocol server.
+//!
+//! Speaks DAP's JSON messages over any reader and writer, such as stdio, so a
+//! [VirtualMachine] can be debugged from DAP capable editors. It provides:
+//!
+//! - a single thread, the CPU;
+//! - a stack trace reconstructed from JSR frames;
+//! - scopes for the registers, flags and zero page;
+//! - function breakpoints by symbol or address (`main`, `$0204` or `0x0204`),
+//!   instruction breakpoints by address, and source breakpoints on lines mapped by the
+//!   [symbol table](VirtualMachine::symbols);
+//! - `next`, `stepIn`, `stepOut`, `continue` and `pause`;
+//! - `readMemory` and `disassemble`.
+//!
+//! Symbols added to the server and those in the machine's symbol table name stack frames and
+//! label disassembly, and source lines from ld65 `.dbg` files locate them in the source.
+//!
+//! The server attaches its own [Observer] to track calls, replacing any other observer.
+//!
+//! # Example
+//! ```no_run
+//! use vm6502::prelude::*;
+//!
+//! let mut vm = VirtualMachine::new();
+//! vm.set_program(0x0000, "A9014C0002").unwrap();
+//!
+//! let mut server = DapServer::new();
+//! server.add_symbol("main", 0x0200);
+//! server.serve_stdio(&mut vm).unwrap();
+//! ```
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::dap::DapServer;
+}
+
+/// The only thread, the CPU.
+const THREAD_ID: u64 = 1;
+
+/// How many instructions a continue runs between checks for a pause request.
+const POLL_INTERVAL: u32 = 1024;
+
+/// Variable references of the scopes.
+const REGISTERS: u64 = 1;
+const FLAGS: u64 = 2;
+const ZERO_PAGE: u64 = 3;
+
+/// Tracks subroutine frames from JSR and RTS.
+#[derive(Debug, Default)]
+struct CallStack {
+    /// The call site and entry point of each active subroutine, innermost last.
+    frames: Vec<(u16, u16)>,
+    /// The site of a JSR whose entry point is the next fetch.
+    pending: Option<u16>,
+}
+
+impl Observer for CallStack {
+    fn on_fetch(&mut self, pc: u16, _opcode: u8) {
+        if let Some(site) = self.pending.take() {
+            self.frames.push((site, pc));
+        }
+    }
+
+    fn on_decode(&mut self, pc: u16, opcode: u8, _mode: Mode) {
+        match opcode {
+            0x20 => self.pending = Some(pc),
+            0x60 => {
+                self.frames.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What to do after handling a message.
+enum Flow {
+    Continue,
+    Exit,
+}
+
+/// A DAP session over a [VirtualMachine].
+#[derive(Debug, Default)]
+pub struct DapServer {
+    /// Symbol names for breakpoints and stack frames.
+    symbols: HashMap<String, u16>,
+    call_stack: Rc<RefCell<CallStack>>,
+    /// Debugger ids of the source breakpoints of each file, and of the function and
+    /// instruction breakpoints.
+    source_breakpoints: HashMap<String, Vec<usize>>,
+    function_breakpoints: Vec<usize>,
+    instruction_breakpoints: Vec<usize>,
+    stop_on_entry: bool,
+    seq: u64,
+    /// Requests received while running, handled once stopped.
+    pending: VecDeque<Value>,
+}
+
+impl DapServer {
+    pub fn new() -> Self {
+        DapServer::default()
+    }
+
+    /// Name `address`, for function breakpoints and stack frames.
+    pub fn add_symbol(&mut self, name: impl Into<String>, address: u16) {
+        self.symbols.insert(name.into(), address);
+    }
+
+    /// Serve `vm` over stdin and stdout.
+    pub fn serve_stdio<B: Bus>(&mut self, vm: &mut VirtualMachine<B>) -> io::Result<()> {
+        self.serve(vm, io::stdin(), io::stdout())
+    }
+
+    /// Serve `vm`, reading requests from `input` and writing to `output` until disconnected.
+    pub fn serve<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        input: impl Read + Send + 'static,
+        mut output: impl Write,
+    ) -> io::Result<()> {
+        vm.set_observer(self.call_stack.clone());
+
+        // Requests are read on their own thread so a running program can be paused.
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(input);
+            while let Ok(Some(message)) = read_message(&mut input) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        loop {
+            let request = match self.pending.pop_front() {
+                Some(request) => request,
+                None => match requests.recv() {
+                    Ok(request) => request,
+                    Err(_) => break,
+                },
+            };
+
+            if let Flow::Exit = self.handle(vm, &request, &requests, &mut output)? {
+                break;
+            }
+        }
+
+        vm.take_observer();
+        Ok(())
+    }
+
+    fn handle<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        request: &Value,
+        requests: &Receiver<Value>,
+        output: &mut impl Write,
+    ) -> io::Result<Flow> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let args = &request["arguments"];
+
+        let body = match command {
+            "initialize" => {
+                self.respond(output, request, true, capabilities())?;
+                self.event(output, "initialized", json!({}))?;
+                return Ok(Flow::Continue);
+            }
+            "launch" | "attach" => {
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                json!({})
+            }
+            "configurationDone" => {
+                self.respond(output, request, true, json!({}))?;
+                if self.stop_on_entry {
+                    self.stopped(output, "entry", None)?;
+                } else {
+                    self.run(vm, requests, output)?;
+                }
+                return Ok(Flow::Continue);
+            }
+            "threads" => json!({ "threads": [{ "id": THREAD_ID, "name": "6502" }] }),
+            "stackTrace" => self.stack_trace(vm),
+            "scopes" => json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                    { "name": "Flags", "variablesReference": FLAGS, "expensive": false },
+                    { "name": "Zero Page", "variablesReference": ZERO_PAGE, "expensive": true },
+                ]
+            }),
+            "variables" => variables(vm, args["variablesReference"].as_u64().unwrap_or(0)),
+            "setBreakpoints" => {
+                let path = args["source"]["path"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let targets = args["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|b| {
+                        let line = b["line"].as_u64().unwrap_or(0) as usize;
+                        vm.symbols.line_address(&path, line)
+                    })
+                    .collect::<Vec<Option<u16>>>();
+                let ids = self.source_breakpoints.remove(&path).unwrap_or_default();
+                let (ids, body) = set_breakpoints(vm, ids, targets, "no code at this line");
+                self.source_breakpoints.insert(path, ids);
+                body
+            }
+            "setFunctionBreakpoints" => {
+                let targets = args["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|b| self.resolve(vm, b["name"].as_str().unwrap_or_default()))
+                    .collect::<Vec<Option<u16>>>();
+                let ids = std::mem::take(&mut self.function_breakpoints);
+                let (ids, body) = set_breakpoints(vm, ids, targets, "unknown symbol or address");
+                self.function_breakpoints = ids;
+                body
+            }
+            "setInstructionBreakpoints" => {
+                let targets = args["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|b| {
+                        let offset = b["offset"].as_i64().unwrap_or(0) as i16;
+                        parse_reference(&b["instructionReference"])
+                            .map(|a| a.wrapping_add_signed(offset))
+                    })
+                    .collect::<Vec<Option<u16>>>();
+                let ids = std::mem::take(&mut self.instruction_breakpoints);
+                let (ids, body) = set_breakpoints(vm, ids, targets, "invalid address");
+                self.instruction_breakpoints = ids;
+                body
+            }
+            "continue" => {
+                self.respond(
+                    output,
+                    request,
+                    true,
+                    json!({ "allThreadsContinued": true }),
+                )?;
+                self.run(vm, requests, output)?;
+                return Ok(Flow::Continue);
+            }
+            "next" | "stepIn" | "stepOut" => {
+                self.respond(output, request, true, json!({}))?;
+                let stop = match command {
+                    "next" => vm.step_over(),
+                    "stepIn" => {
+                        vm.resume_here();
+                        vm.run_for_instructions(1)
+                    }
+                    _ => vm.step_out(),
+                };
+                self.report(output, stop)?;
+                return Ok(Flow::Continue);
+            }
+            // Pausing while running is handled by `run`, so it's already stopped.
+            "pause" => json!({}),
+            "readMemory" => read_memory(vm, args),
+            "disassemble" => disassemble(vm, args),
+            "disconnect" | "terminate" => {
+                self.respond(output, request, true, json!({}))?;
+                return Ok(Flow::Exit);
+            }
+            _ => {
+                let message = format!("unsupported command: {}", command);
+                self.respond(
+                    output,
+                    request,
+                    false,
+                    json!({ "error": { "id": 1, "format": message } }),
+                )?;
+                return Ok(Flow::Continue);
+            }
+        };
+
+        self.respond(output, request, true, body)?;
+        Ok(Flow::Continue)
+    }
+
+    /// Continue until a stop, handling a pause request if one arrives.
+    fn run<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        requests: &Receiver<Value>,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        vm.resume_here();
+
+        let stop = loop {
+            let mut stop = None;
+            for _ in 0..POLL_INTERVAL {
+                match vm.debug_step() {
+                    Ok(None) => {}
+                    Ok(Some(reason)) => {
+                        stop = Some(Ok(reason));
+                        break;
+                    }
+                    Err(e) => {
+                        stop = Some(Err(e));
+                        break;
+                    }
+                }
+            }
+
+            if let Some(stop) = stop {
+                break stop;
+            }
+
+            match requests.try_recv() {
+                Ok(request) if request["command"] == "pause" => {
+                    self.respond(output, &request, true, json!({}))?;
+                    break Ok(StopReason::Interrupted);
+                }
+                Ok(request) => self.pending.push_back(request),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => {}
+            }
+        };
+
+        self.report(output, stop)
+    }
+
+    /// Send the events for why execution stopped.
+    fn report(&mut self, output: &mut impl Write, stop: VmResult<StopReason>) -> io::Result<()> {
+        match stop {
+            Ok(StopReason::Halted) => {
+                self.event(output, "exited", json!({ "exitCode": 0 }))?;
+                self.event(output, "terminated", json!({}))
+            }
+            Ok(StopReason::Breakpoint { id, .. } | StopReason::Condition { id }) => self.stopped(
+                output,
+                "breakpoint",
+                Some(json!({ "hitBreakpointIds": [id] })),
+            ),
+            Ok(StopReason::Watchpoint { id, .. }) => self.stopped(
+                output,
+                "data breakpoint",
+                Some(json!({ "hitBreakpointIds": [id] })),
+            ),
+            Ok(StopReason::Interrupted) => self.stopped(output, "pause", None),
+            Ok(StopReason::Jammed { pc }) => {
+                let description = format!("jammed at ${:04X}", pc);
+                self.stopped(
+                    output,
+                    "exception",
+                    Some(json!({ "description": description, "text": description })),
+                )
+            }
+            Ok(_) => self.stopped(output, "step", None),
+            Err(e) => self.stopped(
+                output,
+                "exception",
+                Some(json!({ "description": e.to_string(), "text": e.to_string() })),
+            ),
+        }
+    }
+
+    fn stopped(
+        &mut self,
+        output: &mut impl Write,
+        reason: &str,
+        extra: Option<Value>,
+    ) -> io::Result<()> {
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(Value::Object(extra)) = extra {
+            body.as_object_mut().unwrap().extend(extra);
+        }
+
+        self.event(output, "stopped", body)
+    }
+
+    fn stack_trace<B: Bus>(&self, vm: &VirtualMachine<B>) -> Value {
+        let call_stack = self.call_stack.borrow();
+
+        // A call whose entry hasn't been fetched yet is stopped on its entry point.
+        let pending = call_stack.pending.map(|site| (site, vm.registers.pc));
+
+        // Each frame is executing in the subroutine entered by the call below it.
+        let mut pc = vm.registers.pc;
+        let mut frames = Vec::new();
+        for (site, entry) in call_stack.frames.iter().chain(&pending).rev() {
+            frames.push((pc, self.name(vm, *entry)));
+            pc = *site;
+        }
+        frames.push((pc, "main".to_string()));
+
+        let frames = frames
+            .into_iter()
+            .enumerate()
+            .map(|(id, (pc, name))| {
+                let mut frame = json!({
+                    "id": id,
+                    "name": name,
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": reference(pc),
+                });
+                if let Some(line) = vm.symbols.source_line(pc) {
+                    frame["source"] = json!({ "name": line.file, "path": line.file });
+                    frame["line"] = json!(line.line);
+                }
+                frame
+            })
+            .collect::<Vec<Value>>();
+
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+
+    /// The symbol naming `address`, or the address itself.
+    fn name<B: Bus>(&self, vm: &VirtualMachine<B>, address: u16) -> String {
+        self.symbols
+            .iter()
+            .filter(|(_, a)| **a == address)
+            .map(|(name, _)| name.clone())
+            .min()
+            .or_else(|| vm.symbols.name(address).map(str::to_string))
+            .unwrap_or_else(|| format!("${:04X}", address))
+    }
+
+    /// Resolve a symbol or an address written as `$0204`, `0x0204` or `0204`.
+    fn resolve<B: Bus>(&self, vm: &VirtualMachine<B>, name: &str) -> Option<u16> {
+        let symbol = self.symbols.get(name).copied();
+
+        symbol.or_else(|| vm.symbols.resolve(name)).or_else(|| {
+            let hex = name
+                .strip_prefix('$')
+                .or_else(|| name.strip_prefix("0x"))
+                .unwrap_or(name);
+            u16::from_str_radix(hex, 16).ok()
+        })
+    }
+
+    fn respond(
+        &mut self,
+        output: &mut impl Write,
+        request: &Value,
+        success: bool,
+        body: Value,
+    ) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": success,
+            "command": request["command"],
+            "body": body,
+        });
+        if !success {
+            response["message"] = response["body"]["error"]["format"].clone();
+        }
+
+        self.send(output, response)
+    }
+
+    fn event(&mut self, output: &mut impl Write, event: &str, body: Value) -> io::Result<()> {
+        self.send(
+            output,
+            json!({ "type": "event", "event": event, "body": body }),
+        )
+    }
+
+    fn send(&mut self, output: &mut impl Write, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let content = message.to_string();
+        write!(
+            output,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        output.flush()
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsFunctionBreakpoints": true,
+        "supportsInstructionBreakpoints": true,
+        "supportsReadMemoryRequest": true,
+        "supportsDisassembleRequest": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+/// Read a single `Content-Length` framed message, returning `None` at the end of input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut content = vec![0; length.unwrap_or(0)];
+    input.read_exact(&mut content)?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Replace the breakpoints `ids` with breakpoints at `targets`, reporting `missing` for
+/// those that couldn't be resolved.
+fn set_breakpoints<B: Bus>(
+    vm: &mut VirtualMachine<B>,
+    ids: Vec<usize>,
+    targets: Vec<Option<u16>>,
+    missing: &str,
+) -> (Vec<usize>, Value) {
+    for id in ids {
+        vm.remove_breakpoint(id);
+    }
+
+    let mut ids = Vec::new();
+    let breakpoints = targets
+        .into_iter()
+        .map(|target| match target {
+            Some(address) => {
+                let id = vm.add_breakpoint(Breakpoint::Pc(address));
+                ids.push(id);
+                json!({ "id": id, "verified": true, "instructionReference": reference(address) })
+            }
+            None => json!({ "verified": false, "message": missing }),
+        })
+        .collect::<Vec<Value>>();
+
+    (ids, json!({ "breakpoints": breakpoints }))
+}
+
+fn variables<B: Bus>(vm: &VirtualMachine<B>, scope: u64) -> Value {
+    let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+    let r = &vm.registers;
+
+    let variables = match scope {
+        REGISTERS => vec![
+            variable("A".into(), format!("${:02X}", r.ac)),
+            variable("X".into(), format!("${:02X}", r.x)),
+            variable("Y".into(), format!("${:02X}", r.y)),
+            variable("SP".into(), format!("${:02X}", r.sp)),
+            variable("PC".into(), format!("${:04X}", r.pc)),
+            variable("P".into(), format!("${:02X}", r.sr)),
+        ],
+        FLAGS => [
+            ("N", Status::Negative),
+            ("V", Status::Overflow),
+            ("D", Status::Decimal),
+            ("I", Status::Interrupt),
+            ("Z", Status::Zero),
+            ("C", Status::Carry),
+        ]
+        .into_iter()
+        .map(|(name, flag)| variable(name.into(), (vm.get_status(flag) as u8).to_string()))
+        .collect(),
+        ZERO_PAGE => (0..=0xFF)
+            .map(|address: u16| {
+                let mut v = variable(
+                    format!("${:02X}", address),
+                    format!("${:02X}", vm.flatmap.peek(address)),
+                );
+                v["memoryReference"] = json!(reference(address));
+                v
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    json!({ "variables": variables })
+}
+
+fn read_memory<B: Bus>(vm: &VirtualMachine<B>, args: &Value) -> Value {
+    let start = parse_reference(&args["memoryReference"]).unwrap_or(0) as i64
+        + args["offset"].as_i64().unwrap_or(0);
+    let start = start.clamp(0, 0x10000) as usize;
+    let count = args["count"].as_u64().unwrap_or(0) as usize;
+    let readable = count.min(0x10000 - start);
+
+    let bytes = (start..start + readable)
+        .map(|address| vm.flatmap.peek(address as u16))
+        .collect::<Vec<u8>>();
+
+    json!({
+        "address": reference(start as u16),
+        "data": base64(&bytes),
+        "unreadableBytes": count - readable,
+    })
+}
+
+/// Disassemble exactly `instructionCount` instructions, padding those before the start of
+/// memory with invalid ones. 64K holds at most 0x10000 instructions, so offsets and counts
+/// are clamped to that.
+fn disassemble<B: Bus>(vm: &VirtualMachine<B>, args: &Value) -> Value {
+    let address = (parse_reference(&args["memoryReference"]).unwrap_or(0) as i64)
+        .saturating_add(args["offset"].as_i64().unwrap_or(0))
+        .clamp(0, 0xFFFF) as u16;
+    let skip = args["instructionOffset"]
+        .as_i64()
+        .unwrap_or(0)
+        .clamp(-0x10000, 0x10000);
+    let count = args["instructionCount"].as_u64().unwrap_or(0).min(0x10000) as usize;
+
+    let (padding, instructions) = if skip < 0 {
+        let before = skip.unsigned_abs() as usize;
+        let after = count.saturating_sub(before);
+        let mut listing = vm.disassemble_around(address, before, after);
+        let padding = (before - (listing.len() - after)).min(count);
+        listing.truncate(count - padding);
+        (padding, listing)
+    } else {
+        let mut listing = vm.disassemble_count(address, skip as usize + count);
+        listing.drain(..skip as usize);
+        (0, listing)
+    };
+
+    let invalid =
+        json!({ "address": reference(0), "instruction": "??", "presentationHint": "invalid" });
+    let instructions = std::iter::repeat_n(invalid, padding)
+        .chain(instructions.iter().map(|i| {
+            let mut instruction = json!({
+                "address": reference(i.address),
+                "instructionBytes": i.bytes().iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>().join(" "),
+                "instruction": i.labelled(&vm.symbols),
+            });
+            if let Some(name) = vm.symbols.name(i.address) {
+                instruction["symbol"] = json!(name);
+            }
+            if let Some(line) = vm.symbols.source_line(i.address) {
+                instruction["location"] = json!({ "name": line.file, "path": line.file });
+                instruction["line"] = json!(line.line);
+            }
+            instruction
+        }))
+        .collect::<Vec<Value>>();
+
+    json!({ "instructions": instructions })
+}
+
+/// A DAP memory reference for `address`.
+fn reference(address: u16) -> String {
+    format!("0x{:04X}", address)
+}
+
+fn parse_reference(reference: &Value) -> Option<u16> {
+    let reference = reference.as_str()?;
+    let hex = reference
+        .strip_prefix("0x")
+        .or_else(|| reference.strip_prefix('$'))
+        .unwrap_or(reference);
+
+    u16::from_str_radix(hex, 16).ok()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let word = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |word, (i, b)| word | (*b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(word >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,284 @@
+//! 6502 disassembler.
+//!
+//! Decodes raw bytes back into [Instruction]s using [COMPLETE_OPCODE_TABLE] and [OP_MODES].
+//! Bytes that are not valid opcodes are decoded as single byte `.byte` data, so a
+//! listing always covers every input byte. A machine's [Disassemble] also decodes the
+//! undocumented opcodes while [undocumented](VirtualMachine::undocumented) is set, marked
+//! with a `*` as in nestest.log, ex. `*LAX $10`.
+//!
+//! # Example
+//! ```
+//! use vm6502::prelude::*;
+//!
+//! let listing = disassemble(&[0xA9, 0x01, 0xD0, 0xFC], 0x0600);
+//!
+//! assert_eq!(listing[0].to_string(), "LDA #$01");
+//! assert_eq!(listing[1].to_string(), "BNE $0600");
+//! ```
+use std::fmt::{Display, Formatter, Result};
+
+use crate::opcode_name;
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::disassembler::{disassemble, disassemble_one, Disassemble, Instruction};
+}
+
+/// A single decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    /// Address of the opcode.
+    pub address: u16,
+    /// The raw opcode byte.
+    pub opcode: u8,
+    /// Mnemonic from [COMPLETE_OPCODE_TABLE].
+    pub mnemonic: &'static str,
+    /// Addressing mode from [OP_MODES].
+    pub mode: Mode,
+    /// The little endian operand, if the mode takes one.
+    pub operand: Option<u16>,
+    /// Total length in bytes, including the opcode.
+    pub len: u16,
+    /// Whether this was decoded as an undocumented NMOS instruction.
+    pub undocumented: bool,
+}
+
+impl Instruction {
+    /// Whether the opcode is an instruction, rather than a data byte.
+    pub fn is_valid(&self) -> bool {
+        valid_op(self.opcode) || self.undocumented
+    }
+
+    /// The raw bytes of this instruction.
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.opcode];
+        if let Some(operand) = self.operand {
+            bytes.extend_from_slice(&operand.to_le_bytes()[..self.len as usize - 1]);
+        }
+        bytes
+    }
+
+    /// Branch destination of a relative instruction.
+    pub fn target(&self) -> Option<u16> {
+        match (self.mode, self.operand) {
+            (Mode::Relative, Some(offset)) => Some(
+                self.address
+                    .wrapping_add(self.len)
+                    .wrapping_add_signed(offset as u8 as i8 as i16),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Address of the instruction following this one.
+    pub fn next(&self) -> u16 {
+        self.address.wrapping_add(self.len)
+    }
+
+    /// The assembly text with an address operand replaced by its label in `symbols`.
+    ///
+    /// Ex. `JSR main` rather than `JSR $0200`.
+    pub fn labelled(&self, symbols: &SymbolTable) -> String {
+        let text = self.to_string();
+        let (address, width) = match self.mode {
+            _ if !self.is_valid() => return text,
+            Mode::Implied | Mode::Accumulator | Mode::Immediate => return text,
+            Mode::Relative => (self.target().unwrap(), 4),
+            Mode::ZeroPage | Mode::ZeroPageX | Mode::ZeroPageY => (self.operand.unwrap(), 2),
+            Mode::IndirectX | Mode::IndirectY => (self.operand.unwrap(), 2),
+            _ => (self.operand.unwrap(), 4),
+        };
+
+        match symbols.name(address) {
+            Some(name) => text.replacen(&format!("${:0width$X}", address, width = width), name, 1),
+            None => text,
+        }
+    }
+
+    /// A listing line: address, raw bytes and assembly text.
+    ///
+    /// Ex. `0600  A9 01     LDA #$01`
+    pub fn listing(&self) -> String {
+        self.listing_of(&self.to_string())
+    }
+
+    /// A listing line with the [labelled](Instruction::labelled) assembly text.
+    pub fn labelled_listing(&self, symbols: &SymbolTable) -> String {
+        self.listing_of(&self.labelled(symbols))
+    }
+
+    fn listing_of(&self, text: &str) -> String {
+        let bytes = self
+            .bytes()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        format!("{:04X}  {:<8}  {}", self.address, bytes, text)
+    }
+}
+
+/// Canonical assembly text, accepted by the [assembler](crate::assembler) unless the
+/// instruction is undocumented.
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if !self.is_valid() {
+            return write!(f, ".byte ${:02X}", self.opcode);
+        } else if self.undocumented {
+            write!(f, "*")?;
+        }
+
+        let operand = self.operand.unwrap_or(0);
+        match self.mode {
+            Mode::Implied => write!(f, "{}", self.mnemonic),
+            Mode::Accumulator => write!(f, "{} A", self.mnemonic),
+            Mode::Immediate => write!(f, "{} #${:02X}", self.mnemonic, operand),
+            Mode::ZeroPage => write!(f, "{} ${:02X}", self.mnemonic, operand),
+            Mode::ZeroPageX => write!(f, "{} ${:02X},X", self.mnemonic, operand),
+            Mode::ZeroPageY => write!(f, "{} ${:02X},Y", self.mnemonic, operand),
+            Mode::Relative => write!(f, "{} ${:04X}", self.mnemonic, self.target().unwrap()),
+            Mode::Absolute => write!(f, "{} ${:04X}", self.mnemonic, operand),
+            Mode::AbsoluteX => write!(f, "{} ${:04X},X", self.mnemonic, operand),
+            Mode::AbsoluteY => write!(f, "{} ${:04X},Y", self.mnemonic, operand),
+            Mode::Indirect => write!(f, "{} (${:04X})", self.mnemonic, operand),
+            Mode::IndirectX => write!(f, "{} (${:02X},X)", self.mnemonic, operand),
+            Mode::IndirectY => write!(f, "{} (${:02X}),Y", self.mnemonic, operand),
+        }
+    }
+}
+
+/// Decode the instruction at the start of `bytes`, which is located at `address`.
+///
+/// Invalid opcodes, and instructions truncated by the end of `bytes`, decode as a one byte `.byte`.
+///
+/// # Panics
+/// Panics if `bytes` is empty.
+pub fn disassemble_one(bytes: &[u8], address: u16) -> Instruction {
+    decode(bytes, address, false)
+}
+
+/// Decode like [disassemble_one], including the stable undocumented opcodes if `undocumented`.
+fn decode(bytes: &[u8], address: u16, undocumented: bool) -> Instruction {
+    let opcode = bytes[0];
+    let mnemonic = opcode_name!(opcode);
+    let mode = match op_mode(opcode) {
+        Some(mode) => Some(mode),
+        None if undocumented => undocumented_mode(opcode),
+        None => None,
+    };
+
+    match mode {
+        Some(mode) if bytes.len() > mode.operand_len() as usize => {
+            let operand = match mode.operand_len() {
+                0 => None,
+                1 => Some(bytes[1] as u16),
+                _ => Some(u16::from_le_bytes([bytes[1], bytes[2]])),
+            };
+
+            Instruction {
+                address,
+                opcode,
+                mnemonic,
+                mode,
+                operand,
+                len: 1 + mode.operand_len(),
+                undocumented: !valid_op(opcode),
+            }
+        }
+        _ => Instruction {
+            address,
+            opcode,
+            mnemonic,
+            mode: Mode::Implied,
+            operand: None,
+            len: 1,
+            undocumented: false,
+        },
+    }
+}
+
+/// Decode every instruction in `bytes`, the first of which is located at `origin`.
+pub fn disassemble(bytes: &[u8], origin: u16) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let instruction = disassemble_one(&bytes[offset..], origin.wrapping_add(offset as u16));
+        offset += instruction.len as usize;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Disassembly of the virtual machine's memory.
+///
+/// Addresses are the same bus addresses used by the [PC](Registers::pc).
+pub trait Disassemble {
+    /// Decode the instructions from `start` up to and including `end`.
+    fn disassemble(&self, start: u16, end: u16) -> Vec<Instruction>;
+    /// Decode `count` instructions starting at `start`.
+    fn disassemble_count(&self, start: u16, count: usize) -> Vec<Instruction>;
+    /// Decode up to `before` instructions leading up to `address`, and `after` instructions from it.
+    ///
+    /// Since 6502 code can't be decoded backwards, the leading instructions are found by
+    /// searching for the furthest start address that decodes exactly onto `address`.
+    fn disassemble_around(&self, address: u16, before: usize, after: usize) -> Vec<Instruction>;
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Decode the instruction at `address`, without side effects.
+    fn peek_instruction(&self, address: u16) -> Instruction {
+        let byte = |i: u16| self.flatmap.peek(address.wrapping_add(i));
+
+        decode(&[byte(0), byte(1), byte(2)], address, self.undocumented)
+    }
+}
+
+impl<B: Bus> Disassemble for VirtualMachine<B> {
+    fn disassemble(&self, start: u16, end: u16) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        let mut address = start as u32;
+
+        while address <= end as u32 {
+            let instruction = self.peek_instruction(address as u16);
+            address += instruction.len as u32;
+            instructions.push(instruction);
+        }
+
+        instructions
+    }
+
+    fn disassemble_count(&self, start: u16, count: usize) -> Vec<Instruction> {
+        let mut instructions = Vec::with_capacity(count);
+        let mut address = start;
+
+        for _ in 0..count {
+            let instruction = self.peek_instruction(address);
+            address = instruction.next();
+            instructions.push(instruction);
+        }
+
+        instructions
+    }
+
+    fn disassemble_around(&self, address: u16, before: usize, after: usize) -> Vec<Instruction> {
+        let mut leading = Vec::new();
+
+        // At most three bytes per instruction, so search back that far.
+        for back in (1..=before.saturating_mul(3).min(address as usize)).rev() {
+            // Every instruction is at least a byte, so no more than `back` of them fit.
+            let decoded = self.disassemble_count(address - back as u16, before.min(back));
+            if let Some(end) = decoded.iter().position(|i| i.next() == address) {
+                leading = decoded[..=end].to_vec();
+                break;
+            }
+        }
+
+        let skip = leading.len().saturating_sub(before);
+        leading.drain(..skip);
+        leading.extend(self.disassemble_count(address, after));
+        leading
+    }
+}
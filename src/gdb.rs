@@ -0,0 +1,420 @@
+//! GDB remote serial protocol stub.
+//!
+//! Serves a [VirtualMachine] to a debugger over TCP, supporting register and memory
+//! access, software breakpoints, watchpoints, single-step and continue.
+//!
+//! There is no upstream GDB architecture for the 6502, so registers use this layout,
+//! each as little endian hex in `g`/`G`/`p`/`P` packets:
+//!
+//! | Number | Register | Size |
+//! |--------|----------|------|
+//! | 0      | A        | 1    |
+//! | 1      | X        | 1    |
+//! | 2      | Y        | 1    |
+//! | 3      | P (SR)   | 1    |
+//! | 4      | SP       | 1    |
+//! | 5      | PC       | 2    |
+//!
+//! Stops are reported as `S05`, or `T05watch:ADDR;` (`rwatch`, `awatch`) for watchpoints.
+//! Faults report `S04` for illegal opcodes and `S0B` otherwise, and an interrupted
+//! continue reports `S02`.
+//!
+//! # Example
+//! ```no_run
+//! use std::net::TcpListener;
+//!
+//! use vm6502::prelude::*;
+//!
+//! let mut vm = VirtualMachine::new();
+//! vm.set_program(0x0000, "A9014C0002").unwrap();
+//!
+//! // Then `target remote localhost:1234` from the debugger.
+//! let listener = TcpListener::bind("127.0.0.1:1234").unwrap();
+//! let (stream, _) = listener.accept().unwrap();
+//! GdbStub::new().serve(&mut vm, stream).unwrap();
+//! ```
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::gdb::GdbStub;
+}
+
+/// How many instructions a continue runs between checks for an interrupt request.
+const POLL_INTERVAL: u32 = 1024;
+
+/// A GDB remote protocol session.
+#[derive(Debug, Default)]
+pub struct GdbStub {
+    /// Debugger ids of the breakpoints set with `Z` packets, by type and address.
+    breakpoints: HashMap<(u8, u16), usize>,
+    /// Whether `QStartNoAckMode` was negotiated.
+    no_ack: bool,
+}
+
+impl GdbStub {
+    pub fn new() -> Self {
+        GdbStub::default()
+    }
+
+    /// Serve `vm` over `stream` until the debugger detaches, kills or disconnects.
+    pub fn serve<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        stream: TcpStream,
+    ) -> io::Result<()> {
+        let mut stream = stream;
+
+        while let Some(packet) = self.read_packet(&mut stream)? {
+            // A bare interrupt while stopped is answered with a stop reply.
+            let packet = if packet == "\x03" {
+                "?".to_string()
+            } else {
+                packet
+            };
+
+            let mut interrupted = || interrupt_requested(&stream);
+            let reply = self.handle(vm, &packet, &mut interrupted);
+
+            match reply {
+                Some(reply) => self.write_packet(&mut stream, &reply)?,
+                None => {
+                    self.write_packet(&mut stream, "OK")?;
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a single packet payload, returning the reply payload.
+    ///
+    /// Returns `None` when the session should end. `interrupted` is polled while
+    /// continuing, and stops execution when it returns true.
+    pub fn handle<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        packet: &str,
+        interrupted: &mut dyn FnMut() -> bool,
+    ) -> Option<String> {
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match command {
+            "?" => "S05".to_string(),
+            "g" => hex_encode(&registers(vm)),
+            "G" => match hex_decode(args) {
+                Some(bytes) if bytes.len() == 7 => {
+                    set_registers(vm, &bytes);
+                    "OK".to_string()
+                }
+                _ => "E01".to_string(),
+            },
+            "p" => match usize::from_str_radix(args, 16)
+                .ok()
+                .and_then(register_range)
+            {
+                Some(range) => hex_encode(&registers(vm)[range]),
+                None => "E01".to_string(),
+            },
+            "P" => self
+                .write_register(vm, args)
+                .unwrap_or_else(|| "E01".to_string()),
+            "m" => read_memory(vm, args).unwrap_or_else(|| "E01".to_string()),
+            "M" => write_memory(vm, args).unwrap_or_else(|| "E01".to_string()),
+            "Z" => self
+                .insert_breakpoint(vm, args)
+                .unwrap_or_else(|| "E01".to_string()),
+            "z" => self
+                .remove_breakpoint(vm, args)
+                .unwrap_or_else(|| "E01".to_string()),
+            "s" => {
+                vm.resume_here();
+                let stop = vm
+                    .debug_step()
+                    .map(|reason| reason.unwrap_or(StopReason::StepComplete));
+                self.stop_reply(stop)
+            }
+            "c" => {
+                vm.resume_here();
+                let stop = run_interruptibly(vm, interrupted);
+                self.stop_reply(stop)
+            }
+            "D" | "k" => return None,
+            "H" | "T" => "OK".to_string(),
+            _ => self.query(packet),
+        };
+
+        Some(reply)
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        match packet {
+            p if p.starts_with("qSupported") => "PacketSize=1000;QStartNoAckMode+".to_string(),
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn write_register<B: Bus>(&mut self, vm: &mut VirtualMachine<B>, args: &str) -> Option<String> {
+        let (number, value) = args.split_once('=')?;
+        let range = register_range(usize::from_str_radix(number, 16).ok()?)?;
+        let value = hex_decode(value)?;
+
+        let mut bytes = registers(vm);
+        if value.len() != range.len() {
+            return None;
+        }
+        bytes[range].copy_from_slice(&value);
+        set_registers(vm, &bytes);
+
+        Some("OK".to_string())
+    }
+
+    fn insert_breakpoint<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &str,
+    ) -> Option<String> {
+        let (kind, address, len) = breakpoint_args(args)?;
+        let end = address.saturating_add(len.saturating_sub(1));
+
+        let breakpoint = match kind {
+            0 | 1 => Breakpoint::Pc(address),
+            2..=4 => Breakpoint::Watch {
+                start: address,
+                end,
+                access: match kind {
+                    2 => Access::Write,
+                    3 => Access::Read,
+                    _ => Access::ReadWrite,
+                },
+            },
+            // Unsupported breakpoint types get an empty reply.
+            _ => return Some(String::new()),
+        };
+
+        self.breakpoints
+            .entry((kind, address))
+            .or_insert_with(|| vm.add_breakpoint(breakpoint));
+
+        Some("OK".to_string())
+    }
+
+    fn remove_breakpoint<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &str,
+    ) -> Option<String> {
+        let (kind, address, _) = breakpoint_args(args)?;
+
+        if let Some(id) = self.breakpoints.remove(&(kind, address)) {
+            vm.remove_breakpoint(id);
+        }
+
+        Some("OK".to_string())
+    }
+
+    fn stop_reply(&self, stop: VmResult<StopReason>) -> String {
+        match stop {
+            Ok(StopReason::Watchpoint { id, address, .. }) => {
+                let kind = self
+                    .breakpoints
+                    .iter()
+                    .find_map(|((kind, _), i)| (*i == id).then_some(*kind));
+                let name = match kind {
+                    Some(3) => "rwatch",
+                    Some(4) => "awatch",
+                    _ => "watch",
+                };
+
+                format!("T05{}:{:04x};", name, address)
+            }
+            Ok(StopReason::Interrupted) => "S02".to_string(),
+            Ok(StopReason::Jammed { .. }) => "S04".to_string(),
+            Ok(_) => "S05".to_string(),
+            Err(VmError::IllegalOpcode { .. }) => "S04".to_string(),
+            Err(_) => "S0B".to_string(),
+        }
+    }
+
+    /// Read the next packet payload, acknowledging it. Returns `None` on disconnect.
+    fn read_packet(&mut self, stream: &mut TcpStream) -> io::Result<Option<String>> {
+        loop {
+            let Some(byte) = read_byte(stream)? else {
+                return Ok(None);
+            };
+
+            match byte {
+                0x03 => return Ok(Some("\x03".to_string())),
+                b'$' => {}
+                // Acks, and anything between packets, are ignored.
+                _ => continue,
+            }
+
+            let mut payload = Vec::new();
+            loop {
+                match read_byte(stream)? {
+                    Some(b'#') => break,
+                    Some(byte) => payload.push(byte),
+                    None => return Ok(None),
+                }
+            }
+
+            let mut checksum = [0; 2];
+            stream.read_exact(&mut checksum)?;
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                == Some(checksum_of(&payload));
+
+            if !self.no_ack {
+                stream.write_all(if valid { b"+" } else { b"-" })?;
+            }
+
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&payload).into_owned()));
+            }
+        }
+    }
+
+    fn write_packet(&mut self, stream: &mut TcpStream, payload: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", payload, checksum_of(payload.as_bytes()));
+        stream.write_all(packet.as_bytes())?;
+        stream.flush()
+    }
+}
+
+/// Continue until a stop, polling `interrupted` every [POLL_INTERVAL] instructions.
+fn run_interruptibly<B: Bus>(
+    vm: &mut VirtualMachine<B>,
+    interrupted: &mut dyn FnMut() -> bool,
+) -> VmResult<StopReason> {
+    loop {
+        for _ in 0..POLL_INTERVAL {
+            if let Some(reason) = vm.debug_step()? {
+                return Ok(reason);
+            }
+        }
+
+        if interrupted() {
+            return Ok(StopReason::Interrupted);
+        }
+    }
+}
+
+/// Check for a pending `0x03` interrupt request without blocking.
+fn interrupt_requested(stream: &TcpStream) -> bool {
+    let mut byte = [0];
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+
+    let requested = matches!((&*stream).read(&mut byte), Ok(1) if byte[0] == 0x03);
+    let _ = stream.set_nonblocking(false);
+
+    requested
+}
+
+fn read_byte(stream: &mut TcpStream) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match stream.read(&mut byte) {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(byte[0])),
+        Err(e) if e.kind() == ErrorKind::ConnectionReset => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn checksum_of(payload: &[u8]) -> u8 {
+    payload.iter().fold(0, |sum: u8, b| sum.wrapping_add(*b))
+}
+
+/// The register file in the stub's register layout.
+fn registers<B: Bus>(vm: &VirtualMachine<B>) -> [u8; 7] {
+    let r = &vm.registers;
+    let [pc_lo, pc_hi] = r.pc.to_le_bytes();
+
+    [r.ac, r.x, r.y, r.sr, r.sp, pc_lo, pc_hi]
+}
+
+fn set_registers<B: Bus>(vm: &mut VirtualMachine<B>, bytes: &[u8]) {
+    let r = &mut vm.registers;
+    r.ac = bytes[0];
+    r.x = bytes[1];
+    r.y = bytes[2];
+    r.sr = bytes[3];
+    r.sp = bytes[4];
+    r.pc = u16::from_le_bytes([bytes[5], bytes[6]]);
+}
+
+/// The bytes of register `number` in the register layout.
+fn register_range(number: usize) -> Option<std::ops::Range<usize>> {
+    match number {
+        0..=4 => Some(number..number + 1),
+        5 => Some(5..7),
+        _ => None,
+    }
+}
+
+/// Parse `addr,len` arguments.
+fn address_len(args: &str) -> Option<(u16, usize)> {
+    let (address, len) = args.split_once(',')?;
+
+    Some((
+        u16::from_str_radix(address, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+/// Parse `type,addr,kind` breakpoint arguments.
+fn breakpoint_args(args: &str) -> Option<(u8, u16, u16)> {
+    let mut fields = args.splitn(3, ',');
+    let kind = fields.next()?.parse().ok()?;
+    let address = u16::from_str_radix(fields.next()?, 16).ok()?;
+    let len = u16::from_str_radix(fields.next()?.split(';').next()?, 16).ok()?;
+
+    Some((kind, address, len))
+}
+
+fn read_memory<B: Bus>(vm: &VirtualMachine<B>, args: &str) -> Option<String> {
+    let (address, len) = address_len(args)?;
+    let bytes = (0..len.min(0x10000))
+        .map(|i| vm.flatmap.peek(address.wrapping_add(i as u16)))
+        .collect::<Vec<u8>>();
+
+    Some(hex_encode(&bytes))
+}
+
+fn write_memory<B: Bus>(vm: &mut VirtualMachine<B>, args: &str) -> Option<String> {
+    let (header, data) = args.split_once(':')?;
+    let (address, len) = address_len(header)?;
+    let bytes = hex_decode(data)?;
+    if bytes.len() != len {
+        return None;
+    }
+
+    for (i, byte) in bytes.into_iter().enumerate() {
+        vm.flatmap.write(address.wrapping_add(i as u16), byte);
+    }
+
+    Some("OK".to_string())
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    hex::decode(hex).ok()
+}
@@ -17,7 +17,9 @@
 //!
 //! ##### Flags
 //! ### Memory
-//! The [VirtualMachine](crate::prelude::VirtualMachine) has a flat memory map which the stack and heap index into.
+//! The [VirtualMachine](crate::prelude::VirtualMachine) accesses memory through a [Bus](crate::prelude::Bus),
+//! which is a flat 64K memory map by default. The stack and heap index into it, and a
+//! [MemoryMap](crate::prelude::MemoryMap) allows mapping ROM, mirrors and devices over address ranges.
 //!
 //! ### Addressing modes
 //! ### Instruction set
@@ -27,11 +29,26 @@
 //! [See more.](crate::utils)
 //!
 //! # !! In construction !!
-//! Also provided is an [assembler](crate::assembler) and a [programmer](crate::program).
+//! Also provided is an [assembler](crate::assembler), a [disassembler](crate::disassembler), a [programmer](crate::program),
+//! [program file formats](crate::loader), [symbol tables](crate::symbols) and
+//! [execution traces](crate::trace).
+//!
+//! The `vm6502` binary wraps a [monitor](crate::monitor), a [terminal debugger](crate::tui) and a
+//! [headless runner](crate::runner).
 //#![deny(missing_docs)]
 
 pub mod assembler;
+#[cfg(feature = "json")]
+pub mod dap;
+pub mod disassembler;
+pub mod gdb;
+pub mod loader;
+pub mod monitor;
 pub mod program;
+pub mod runner;
+pub mod symbols;
+pub mod trace;
+pub mod tui;
 pub mod utils;
 pub mod vm;
 
@@ -45,4 +62,23 @@ pub mod prelude {
     pub use crate::utils::prelude::*;
 
     pub use crate::assembler::prelude::*;
+
+    pub use crate::disassembler::prelude::*;
+
+    pub use crate::loader::prelude::*;
+
+    pub use crate::symbols::prelude::*;
+
+    pub use crate::trace::prelude::*;
+
+    pub use crate::gdb::prelude::*;
+
+    #[cfg(feature = "json")]
+    pub use crate::dap::prelude::*;
+
+    pub use crate::monitor::prelude::*;
+
+    pub use crate::tui::prelude::*;
+
+    pub use crate::runner::prelude::*;
 }
@@ -0,0 +1,149 @@
+use super::*;
+
+/// Data bytes per record written by [write_ihex].
+const RECORD_LEN: usize = 16;
+
+/// Parse an Intel HEX file.
+///
+/// Data records (00) are placed at their address plus any extended segment (02) or linear
+/// (04) base, and a start segment (03) or start linear (05) record sets [ProgramImage::entry].
+/// Parsing stops at the end of file record (01).
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let image = parse_ihex(":03020000A9010051\n:00000001FF").unwrap();
+/// assert_eq!(image.segments[0].address, 0x0200);
+/// assert_eq!(image.segments[0].bytes, vec![0xA9, 0x01, 0x00]);
+/// ```
+pub fn parse_ihex(text: &str) -> std::result::Result<ProgramImage, LoadError> {
+    let mut image = ProgramImage::new();
+    let mut base = 0u32;
+
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let digits = line
+            .strip_prefix(':')
+            .ok_or_else(|| LoadError::syntax(n, "record doesn't start with ':'"))?;
+        let record = decode_hex(n, digits)?;
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(LoadError::syntax(
+                n,
+                "record length doesn't match its byte count",
+            ));
+        }
+
+        let (body, checksum) = record.split_at(record.len() - 1);
+        let expected = body
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b))
+            .wrapping_neg();
+        if checksum[0] != expected {
+            return Err(LoadError::new(
+                n,
+                LoadErrorKind::Checksum {
+                    expected,
+                    actual: checksum[0],
+                },
+            ));
+        }
+
+        let offset = u16::from_be_bytes([body[1], body[2]]) as u32;
+        let data = &body[4..];
+        let word = || match data {
+            [hi, lo] => Ok(u16::from_be_bytes([*hi, *lo]) as u32),
+            _ => Err(LoadError::syntax(n, "expected 2 data bytes")),
+        };
+        let long = || match data {
+            [a, b, c, d] => Ok(u32::from_be_bytes([*a, *b, *c, *d])),
+            _ => Err(LoadError::syntax(n, "expected 4 data bytes")),
+        };
+
+        match body[3] {
+            0x00 => {
+                let address = base + offset;
+                if address as usize + data.len() > 0x10000 {
+                    return Err(LoadError::new(n, LoadErrorKind::AddressOutOfRange(address)));
+                }
+                if !data.is_empty() {
+                    image.add_segment(address as u16, data.to_vec());
+                }
+            }
+            0x01 => break,
+            0x02 => base = word()? << 4,
+            0x04 => base = word()? << 16,
+            kind @ (0x03 | 0x05) => {
+                let start = match kind {
+                    0x03 => {
+                        let cs_ip = long()?;
+                        ((cs_ip >> 16) << 4) + (cs_ip & 0xFFFF)
+                    }
+                    _ => long()?,
+                };
+                image.entry = Some(
+                    u16::try_from(start)
+                        .map_err(|_| LoadError::new(n, LoadErrorKind::AddressOutOfRange(start)))?,
+                );
+            }
+            kind => {
+                return Err(LoadError::new(
+                    n,
+                    LoadErrorKind::UnsupportedRecord(format!("{:02X}", kind)),
+                ))
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+/// Write `bytes` at `address` as Intel HEX, with a start linear address record for `start`.
+///
+/// Data wrapping past 0xFFFF continues at 0x0000.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let text = write_ihex(0x0200, &[0xA9, 0x01, 0x00], None);
+/// assert_eq!(text, ":03020000A9010051\n:00000001FF\n");
+/// ```
+pub fn write_ihex(address: u16, bytes: &[u8], start: Option<u16>) -> String {
+    let mut text = String::new();
+    let mut record = |kind: u8, address: u16, data: &[u8]| {
+        let mut body = vec![data.len() as u8];
+        body.extend(address.to_be_bytes());
+        body.push(kind);
+        body.extend(data);
+        let checksum = body
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b))
+            .wrapping_neg();
+        body.push(checksum);
+
+        text.push(':');
+        text.push_str(&hex::encode_upper(body));
+        text.push('\n');
+    };
+
+    for (address, chunk) in chunks(address, bytes, RECORD_LEN) {
+        // A record can't cross 0xFFFF, so split one that would.
+        let split = (0x10000 - address as usize).min(chunk.len());
+        record(0x00, address, &chunk[..split]);
+        if split < chunk.len() {
+            record(0x00, 0x0000, &chunk[split..]);
+        }
+    }
+    if let Some(start) = start {
+        record(0x05, 0x0000, &(start as u32).to_be_bytes());
+    }
+    record(0x01, 0x0000, &[]);
+
+    text
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// A program to install: segments of code and data, where to start and how to get back in.
+///
+/// Every [loader](crate::loader) produces one, and
+/// [load_image](crate::program::ProgramController::load_image) installs it.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let mut image = ProgramImage::new();
+/// image
+///     .add_segment(0xC000, vec![0xA9, 0x01, 0x00])
+///     .add_segment(0xC003, vec![0x40]);
+/// image.entry = Some(0xC000);
+/// image.reset = Some(0xC000);
+///
+/// let mut vm = VirtualMachine::new();
+/// vm.load_image(&image).unwrap();
+/// assert_eq!(vm.registers.pc, 0xC000);
+/// assert_eq!(vm.flatmap.peek(0xFFFD), 0xC0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgramImage {
+    /// Runs of code and data, in load order.
+    pub segments: Vec<Segment>,
+    /// Where to set the PC.
+    pub entry: Option<u16>,
+    /// The NMI vector at (0xFFFA, 0xFFFB).
+    pub nmi: Option<u16>,
+    /// The reset vector at (0xFFFC, 0xFFFD).
+    pub reset: Option<u16>,
+    /// The IRQ/BRK vector at (0xFFFE, 0xFFFF).
+    pub irq: Option<u16>,
+    /// Initialization routines the program expects to be called while it loads, in order.
+    ///
+    /// These are reported, not run, by [load_image](crate::program::ProgramController::load_image).
+    pub inits: Vec<u16>,
+    /// Names for addresses in the program.
+    pub symbols: HashMap<String, u16>,
+}
+
+impl ProgramImage {
+    pub fn new() -> Self {
+        ProgramImage::default()
+    }
+
+    /// Add `bytes` at `address`, extending the last segment if they're contiguous.
+    pub fn add_segment(&mut self, address: u16, bytes: Vec<u8>) -> &mut Self {
+        match self.segments.last_mut() {
+            Some(last) if last.end() == address as usize => last.bytes.extend(bytes),
+            _ => self.segments.push(Segment { address, bytes }),
+        }
+
+        self
+    }
+
+    /// The set vectors as two byte segments, at their addresses in the default vector table.
+    pub fn vector_segments(&self) -> Vec<Segment> {
+        [(0xFFFA, self.nmi), (0xFFFC, self.reset), (0xFFFE, self.irq)]
+            .into_iter()
+            .filter_map(|(address, vector)| {
+                vector.map(|v| Segment {
+                    address,
+                    bytes: v.to_le_bytes().to_vec(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,153 @@
+//! Program file formats.
+//!
+//! Parsers decode a file into a [ProgramImage] of [Segment]s placed at their own addresses,
+//! and writers export memory back out. Errors carry the [Position] they occurred at.
+//!
+//! - [Intel HEX](parse_ihex), with [write_ihex]
+//! - [Motorola S-records](parse_srec), with [write_srec]
+//! - [Commodore PRG](parse_prg)
+//! - [Atari XEX](parse_xex)
+//! - [o65 relocatable objects](parse_o65), placed with [O65::relocate]
+//!
+//! The [ProgramController](crate::program::ProgramController) loads and saves these files directly.
+use std::fmt::{Display, Formatter, Result};
+
+mod ihex;
+mod image;
+mod o65;
+mod prg;
+mod srec;
+mod xex;
+
+pub use ihex::{parse_ihex, write_ihex};
+pub use image::ProgramImage;
+pub use o65::{parse_o65, O65Layout, O65};
+pub use prg::parse_prg;
+pub use srec::{parse_srec, write_srec};
+pub use xex::parse_xex;
+
+pub mod prelude {
+    pub use crate::loader::{
+        parse_ihex, parse_o65, parse_prg, parse_srec, parse_xex, write_ihex, write_srec, LoadError,
+        LoadErrorKind, O65Layout, Position, ProgramImage, Segment, O65,
+    };
+}
+
+/// Bytes to place at an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl Segment {
+    /// The address after the last byte, which may be 0x10000.
+    pub fn end(&self) -> usize {
+        self.address as usize + self.bytes.len()
+    }
+}
+
+/// The reason a [LoadError] was raised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadErrorKind {
+    /// The line or segment header isn't well formed.
+    Syntax(String),
+    /// The record's checksum doesn't match its contents.
+    Checksum { expected: u8, actual: u8 },
+    /// The record type or file mode isn't supported.
+    UnsupportedRecord(String),
+    /// Data or a start address lies beyond the 64K address space.
+    AddressOutOfRange(u32),
+    /// A record count doesn't match the data records before it.
+    RecordCount { expected: u32, actual: u32 },
+    /// The file ends part way through a header or segment.
+    Truncated,
+    /// An imported symbol wasn't given.
+    UndefinedSymbol(String),
+    /// A relocation can't be applied.
+    Relocation(String),
+}
+
+/// Where in a file a [LoadError] occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// The (1-based) line of a text format.
+    Line(usize),
+    /// The byte offset into a binary format.
+    Offset(usize),
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Position::Line(line) => write!(f, "line {}", line),
+            Position::Offset(offset) => write!(f, "offset ${:X}", offset),
+        }
+    }
+}
+
+/// A load error and the position it occurred at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    pub position: Position,
+    pub kind: LoadErrorKind,
+}
+
+impl LoadError {
+    fn new(line: usize, kind: LoadErrorKind) -> Self {
+        LoadError {
+            position: Position::Line(line),
+            kind,
+        }
+    }
+
+    fn at_offset(offset: usize, kind: LoadErrorKind) -> Self {
+        LoadError {
+            position: Position::Offset(offset),
+            kind,
+        }
+    }
+
+    fn syntax(line: usize, message: impl Into<String>) -> Self {
+        LoadError::new(line, LoadErrorKind::Syntax(message.into()))
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}: ", self.position)?;
+        match &self.kind {
+            LoadErrorKind::Syntax(msg) => write!(f, "syntax error: {}", msg),
+            LoadErrorKind::Checksum { expected, actual } => {
+                write!(f, "checksum is ${:02X}, expected ${:02X}", actual, expected)
+            }
+            LoadErrorKind::UnsupportedRecord(r) => write!(f, "unsupported record {}", r),
+            LoadErrorKind::AddressOutOfRange(a) => write!(f, "address ${:X} out of range", a),
+            LoadErrorKind::RecordCount { expected, actual } => {
+                write!(f, "record count is {}, expected {}", actual, expected)
+            }
+            LoadErrorKind::Truncated => write!(f, "unexpected end of file"),
+            LoadErrorKind::UndefinedSymbol(name) => write!(f, "undefined symbol {}", name),
+            LoadErrorKind::Relocation(msg) => write!(f, "bad relocation: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Decode the hex digits of a record.
+fn decode_hex(line: usize, digits: &str) -> std::result::Result<Vec<u8>, LoadError> {
+    if !digits.len().is_multiple_of(2) {
+        return Err(LoadError::syntax(line, "odd number of hex digits"));
+    }
+
+    hex::decode(digits).map_err(|e| LoadError::syntax(line, e.to_string()))
+}
+
+/// Split memory into chunks of `size` bytes with their addresses.
+fn chunks(address: u16, bytes: &[u8], size: usize) -> impl Iterator<Item = (u16, &[u8])> {
+    bytes
+        .chunks(size)
+        .enumerate()
+        .map(move |(i, chunk)| (address.wrapping_add((i * size) as u16), chunk))
+}
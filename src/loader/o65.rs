@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// Relocation tables hold high bytes without their low byte, so relocated segments must
+/// stay page aligned.
+const MODE_PAGEWISE: u16 = 0x4000;
+/// The bss segment should be zeroed when the file is loaded.
+const MODE_BSSZERO: u16 = 0x0200;
+/// Mode bits this loader can't handle: 65816 code, 32 bit sizes and chained files.
+const MODE_UNSUPPORTED: u16 = 0x8000 | 0x2000 | 0x0400;
+
+/// Segment IDs used by relocation entries and exports.
+const SEGMENT_UNDEFINED: u8 = 0;
+const SEGMENT_ABSOLUTE: u8 = 1;
+const SEGMENT_TEXT: u8 = 2;
+const SEGMENT_DATA: u8 = 3;
+const SEGMENT_BSS: u8 = 4;
+const SEGMENT_ZERO: u8 = 5;
+
+/// A relocatable o65 object, as produced by xa or ld65.
+///
+/// [relocate](O65::relocate) places it at any address, resolving its imports, and returns a
+/// [ProgramImage] whose symbols are the object's exports.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use vm6502::prelude::*;
+///
+/// // An `LDA $1003; RTS` at $1000, with a relocation for the operand and `value` exported.
+/// let mut o65 = vec![0x01, 0x00, b'o', b'6', b'5', 0x00, 0x00, 0x00];
+/// for word in [0x1000u16, 5, 0x1005, 0, 0, 0, 0, 0, 0] {
+///     o65.extend(word.to_le_bytes());
+/// }
+/// o65.extend([0x00, 0xAD, 0x03, 0x10, 0x60, 0x2A]); // Options, text
+/// o65.extend([0x00, 0x00, 0x02, 0x82, 0x00, 0x00]); // Imports, text and data relocations
+/// o65.extend([0x01, 0x00, b'v', b'a', b'l', b'u', b'e', 0x00, 0x02, 0x04, 0x10]);
+///
+/// let object = parse_o65(&o65).unwrap();
+/// let image = object.relocate(&object.layout_at(0xC000), &HashMap::new()).unwrap();
+/// assert_eq!(image.segments[0].bytes, vec![0xAD, 0x03, 0xC0, 0x60, 0x2A]);
+/// assert_eq!(image.symbols["value"], 0xC004);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct O65 {
+    /// The mode word from the header.
+    pub mode: u16,
+    /// The address the text segment was assembled for.
+    pub text_base: u16,
+    pub text: Vec<u8>,
+    /// The address the data segment was assembled for.
+    pub data_base: u16,
+    pub data: Vec<u8>,
+    pub bss_base: u16,
+    pub bss_len: u16,
+    pub zero_base: u16,
+    pub zero_len: u16,
+    /// The stack space the object needs, or 0 if unknown.
+    pub stack: u16,
+    /// Header options, as (type, bytes).
+    pub options: Vec<(u8, Vec<u8>)>,
+    /// Names of the symbols the object imports.
+    pub undefined: Vec<String>,
+    text_relocations: Vec<Relocation>,
+    data_relocations: Vec<Relocation>,
+    exports: Vec<(String, u8, u16)>,
+}
+
+/// Where to place each segment of an [O65].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct O65Layout {
+    pub text: u16,
+    pub data: u16,
+    pub bss: u16,
+    pub zero: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RelocationKind {
+    Word,
+    /// A high byte, with the low byte it was taken from.
+    High(u8),
+    Low,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Relocation {
+    /// The file offset of the entry, for errors.
+    entry: usize,
+    /// The offset into the segment.
+    offset: usize,
+    kind: RelocationKind,
+    segment: u8,
+    /// The index into [O65::undefined] of an import.
+    import: usize,
+}
+
+/// Reads the fields of a binary file, failing with [LoadErrorKind::Truncated] at its end.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> std::result::Result<&[u8], LoadError> {
+        let taken = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or_else(|| LoadError::at_offset(self.bytes.len(), LoadErrorKind::Truncated))?;
+        self.offset += len;
+
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> std::result::Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> std::result::Result<u16, LoadError> {
+        let bytes = self.take(2)?;
+
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// A null terminated name.
+    fn name(&mut self) -> std::result::Result<String, LoadError> {
+        let start = self.offset;
+        while self.byte()? != 0 {}
+
+        let name = &self.bytes[start..self.offset - 1];
+        String::from_utf8(name.to_vec()).map_err(|_| {
+            LoadError::at_offset(start, LoadErrorKind::Syntax("name isn't UTF-8".into()))
+        })
+    }
+
+    fn relocations(
+        &mut self,
+        mode: u16,
+        len: usize,
+    ) -> std::result::Result<Vec<Relocation>, LoadError> {
+        let mut relocations = vec![];
+        // Offsets are relative to the byte before the segment.
+        let mut offset = -1isize;
+
+        loop {
+            let entry = self.offset;
+            match self.byte()? {
+                0 => break,
+                255 => {
+                    offset += 254;
+                    continue;
+                }
+                step => offset += step as isize,
+            }
+
+            let typ = self.byte()?;
+            let segment = typ & 0x0F;
+            let import = match segment {
+                SEGMENT_UNDEFINED => self.word()? as usize,
+                _ => 0,
+            };
+            let kind = match typ & 0xE0 {
+                0x80 => RelocationKind::Word,
+                0x40 if mode & MODE_PAGEWISE != 0 => RelocationKind::High(0),
+                0x40 => RelocationKind::High(self.byte()?),
+                0x20 => RelocationKind::Low,
+                _ => {
+                    return Err(LoadError::at_offset(
+                        entry,
+                        LoadErrorKind::UnsupportedRecord(format!("relocation ${:02X}", typ)),
+                    ))
+                }
+            };
+
+            let width = if kind == RelocationKind::Word { 2 } else { 1 };
+            if offset as usize + width > len {
+                return Err(LoadError::at_offset(
+                    entry,
+                    LoadErrorKind::Relocation(format!(
+                        "offset ${:X} is outside its segment",
+                        offset
+                    )),
+                ));
+            }
+
+            relocations.push(Relocation {
+                entry,
+                offset: offset as usize,
+                kind,
+                segment,
+                import,
+            });
+        }
+
+        Ok(relocations)
+    }
+}
+
+/// Parse an o65 relocatable object.
+///
+/// Fails with [LoadErrorKind::UnsupportedRecord] for 65816, 32 bit or chained objects.
+pub fn parse_o65(bytes: &[u8]) -> std::result::Result<O65, LoadError> {
+    let mut reader = Reader { bytes, offset: 0 };
+
+    if reader.take(6)? != [0x01, 0x00, b'o', b'6', b'5', 0x00] {
+        return Err(LoadError::at_offset(
+            0,
+            LoadErrorKind::Syntax("not an o65 version 0 file".into()),
+        ));
+    }
+    let mode = reader.word()?;
+    if mode & MODE_UNSUPPORTED != 0 {
+        return Err(LoadError::at_offset(
+            6,
+            LoadErrorKind::UnsupportedRecord(format!("mode ${:04X}", mode)),
+        ));
+    }
+
+    let mut header = [0u16; 9];
+    for field in header.iter_mut() {
+        *field = reader.word()?;
+    }
+    let [text_base, text_len, data_base, data_len, bss_base, bss_len, zero_base, zero_len, stack] =
+        header;
+
+    let mut options = vec![];
+    loop {
+        let offset = reader.offset;
+        let len = reader.byte()? as usize;
+        if len == 0 {
+            break;
+        }
+        if len < 2 {
+            return Err(LoadError::at_offset(
+                offset,
+                LoadErrorKind::Syntax("header option is too short".into()),
+            ));
+        }
+
+        let typ = reader.byte()?;
+        options.push((typ, reader.take(len - 2)?.to_vec()));
+    }
+
+    let text = reader.take(text_len as usize)?.to_vec();
+    let data = reader.take(data_len as usize)?.to_vec();
+
+    let undefined = (0..reader.word()?)
+        .map(|_| reader.name())
+        .collect::<std::result::Result<Vec<String>, LoadError>>()?;
+
+    let text_relocations = reader.relocations(mode, text.len())?;
+    let data_relocations = reader.relocations(mode, data.len())?;
+
+    let mut exports = vec![];
+    for _ in 0..reader.word()? {
+        let name = reader.name()?;
+        let segment = reader.byte()?;
+        exports.push((name, segment, reader.word()?));
+    }
+
+    Ok(O65 {
+        mode,
+        text_base,
+        text,
+        data_base,
+        data,
+        bss_base,
+        bss_len,
+        zero_base,
+        zero_len,
+        stack,
+        options,
+        undefined,
+        text_relocations,
+        data_relocations,
+        exports,
+    })
+}
+
+impl O65 {
+    /// Text at `base`, followed by data then bss, with the zero page segment left in place.
+    ///
+    /// Pagewise objects keep each segment's offset into its page, leaving gaps between them.
+    pub fn layout_at(&self, base: u16) -> O65Layout {
+        let align = |address: u16, from: u16| match self.mode & MODE_PAGEWISE {
+            0 => address,
+            _ => address.wrapping_add(from.wrapping_sub(address) & 0xFF),
+        };
+        let text = align(base, self.text_base);
+        let data = align(text.wrapping_add(self.text.len() as u16), self.data_base);
+
+        O65Layout {
+            text,
+            data,
+            bss: align(data.wrapping_add(self.data.len() as u16), self.bss_base),
+            zero: self.zero_base,
+        }
+    }
+
+    /// Relocate the object to `layout`, resolving its imports from `imports`.
+    ///
+    /// The image holds the text and data segments, and the bss segment if the object asks for
+    /// it to be zeroed. Its entry is the start of text and its symbols are the relocated
+    /// exports. Fails with [LoadErrorKind::UndefinedSymbol] if an import isn't in `imports`.
+    pub fn relocate(
+        &self,
+        layout: &O65Layout,
+        imports: &HashMap<String, u16>,
+    ) -> std::result::Result<ProgramImage, LoadError> {
+        if self.mode & MODE_PAGEWISE != 0 {
+            let moved = [
+                (layout.text, self.text_base),
+                (layout.data, self.data_base),
+                (layout.bss, self.bss_base),
+                (layout.zero, self.zero_base),
+            ];
+            if moved
+                .iter()
+                .any(|(to, from)| to.wrapping_sub(*from) & 0xFF != 0)
+            {
+                return Err(LoadError::at_offset(
+                    6,
+                    LoadErrorKind::Relocation("segments must move by whole pages".into()),
+                ));
+            }
+        }
+
+        let mut text = self.text.clone();
+        let mut data = self.data.clone();
+        for (bytes, relocations) in [
+            (&mut text, &self.text_relocations),
+            (&mut data, &self.data_relocations),
+        ] {
+            for relocation in relocations {
+                let delta = self.delta(layout, relocation, imports)?;
+                let at = relocation.offset;
+
+                match relocation.kind {
+                    RelocationKind::Word => {
+                        let word = u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+                        bytes[at..at + 2].copy_from_slice(&word.wrapping_add(delta).to_le_bytes());
+                    }
+                    RelocationKind::High(low) => {
+                        let word = u16::from_le_bytes([low, bytes[at]]);
+                        bytes[at] = (word.wrapping_add(delta) >> 8) as u8;
+                    }
+                    RelocationKind::Low => bytes[at] = bytes[at].wrapping_add(delta as u8),
+                }
+            }
+        }
+
+        let mut image = ProgramImage::new();
+        image.add_segment(layout.text, text);
+        image.add_segment(layout.data, data);
+        if self.mode & MODE_BSSZERO != 0 {
+            image.add_segment(layout.bss, vec![0; self.bss_len as usize]);
+        }
+        image.segments.retain(|s| !s.bytes.is_empty());
+        image.entry = Some(layout.text);
+
+        for (name, segment, value) in &self.exports {
+            let delta = self.segment_delta(layout, *segment).unwrap_or(0);
+            image
+                .symbols
+                .insert(name.clone(), value.wrapping_add(delta));
+        }
+
+        Ok(image)
+    }
+
+    /// What to add to the value at a relocation.
+    fn delta(
+        &self,
+        layout: &O65Layout,
+        relocation: &Relocation,
+        imports: &HashMap<String, u16>,
+    ) -> std::result::Result<u16, LoadError> {
+        if relocation.segment == SEGMENT_UNDEFINED {
+            let name = self.undefined.get(relocation.import).ok_or_else(|| {
+                LoadError::at_offset(
+                    relocation.entry,
+                    LoadErrorKind::Relocation(format!("no import {}", relocation.import)),
+                )
+            })?;
+
+            return imports.get(name).copied().ok_or_else(|| {
+                LoadError::at_offset(
+                    relocation.entry,
+                    LoadErrorKind::UndefinedSymbol(name.clone()),
+                )
+            });
+        }
+
+        self.segment_delta(layout, relocation.segment)
+            .ok_or_else(|| {
+                LoadError::at_offset(
+                    relocation.entry,
+                    LoadErrorKind::Relocation(format!("unknown segment {}", relocation.segment)),
+                )
+            })
+    }
+
+    /// How far `segment` moves in `layout`.
+    fn segment_delta(&self, layout: &O65Layout, segment: u8) -> Option<u16> {
+        match segment {
+            SEGMENT_ABSOLUTE => Some(0),
+            SEGMENT_TEXT => Some(layout.text.wrapping_sub(self.text_base)),
+            SEGMENT_DATA => Some(layout.data.wrapping_sub(self.data_base)),
+            SEGMENT_BSS => Some(layout.bss.wrapping_sub(self.bss_base)),
+            SEGMENT_ZERO => Some(layout.zero.wrapping_sub(self.zero_base)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use super::*;
+
+/// Parse a Commodore PRG file: a little endian load address followed by the program.
+///
+/// The program is placed at the load address, which is also [ProgramImage::entry].
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let image = parse_prg(&[0x01, 0x08, 0xA9, 0x01, 0x00]).unwrap();
+/// assert_eq!(image.segments[0].address, 0x0801);
+/// assert_eq!(image.segments[0].bytes, vec![0xA9, 0x01, 0x00]);
+/// assert_eq!(image.entry, Some(0x0801));
+/// ```
+pub fn parse_prg(bytes: &[u8]) -> std::result::Result<ProgramImage, LoadError> {
+    let (address, data) = match bytes {
+        [lo, hi, data @ ..] => (u16::from_le_bytes([*lo, *hi]), data),
+        _ => return Err(LoadError::at_offset(bytes.len(), LoadErrorKind::Truncated)),
+    };
+
+    let end = address as usize + data.len();
+    if end > 0x10000 {
+        return Err(LoadError::at_offset(
+            2 + 0x10000 - address as usize,
+            LoadErrorKind::AddressOutOfRange(0x10000),
+        ));
+    }
+
+    let mut image = ProgramImage::new();
+    image.add_segment(address, data.to_vec());
+    image.entry = Some(address);
+
+    Ok(image)
+}
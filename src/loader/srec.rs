@@ -0,0 +1,149 @@
+use super::*;
+
+/// Data bytes per record written by [write_srec].
+const RECORD_LEN: usize = 16;
+
+/// Parse a Motorola S-record file.
+///
+/// Data records (S1, S2, S3) are placed at their address and a termination record (S7, S8,
+/// S9) sets [ProgramImage::entry], ending the file. Header records (S0) are skipped and count
+/// records (S5, S6) are checked against the data records before them.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let image = parse_srec("S1060200A901004D\nS9030200FA").unwrap();
+/// assert_eq!(image.segments[0].bytes, vec![0xA9, 0x01, 0x00]);
+/// assert_eq!(image.entry, Some(0x0200));
+/// ```
+pub fn parse_srec(text: &str) -> std::result::Result<ProgramImage, LoadError> {
+    let mut image = ProgramImage::new();
+    let mut data_records = 0u32;
+
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (kind, digits) = match line.strip_prefix('S').map(|l| l.split_at_checked(1)) {
+            Some(Some((kind, digits))) => (kind, digits),
+            _ => return Err(LoadError::syntax(n, "record doesn't start with 'S'")),
+        };
+        let record = decode_hex(n, digits)?;
+        if record.is_empty() || record.len() != record[0] as usize + 1 {
+            return Err(LoadError::syntax(
+                n,
+                "record length doesn't match its byte count",
+            ));
+        }
+
+        let (body, checksum) = record.split_at(record.len() - 1);
+        let expected = !body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        if checksum[0] != expected {
+            return Err(LoadError::new(
+                n,
+                LoadErrorKind::Checksum {
+                    expected,
+                    actual: checksum[0],
+                },
+            ));
+        }
+
+        let address_len = match kind {
+            "0" | "1" | "5" | "9" => 2,
+            "2" | "6" | "8" => 3,
+            "3" | "7" => 4,
+            _ => {
+                return Err(LoadError::new(
+                    n,
+                    LoadErrorKind::UnsupportedRecord(format!("S{}", kind)),
+                ))
+            }
+        };
+        if body.len() < 1 + address_len {
+            return Err(LoadError::syntax(n, "record is too short for its address"));
+        }
+        let address = body[1..=address_len]
+            .iter()
+            .fold(0u32, |address, b| address << 8 | *b as u32);
+        let data = &body[1 + address_len..];
+
+        match kind {
+            "0" => {}
+            "1" | "2" | "3" => {
+                if address as usize + data.len() > 0x10000 {
+                    return Err(LoadError::new(n, LoadErrorKind::AddressOutOfRange(address)));
+                }
+                if !data.is_empty() {
+                    image.add_segment(address as u16, data.to_vec());
+                }
+                data_records += 1;
+            }
+            "5" | "6" => {
+                if address != data_records {
+                    return Err(LoadError::new(
+                        n,
+                        LoadErrorKind::RecordCount {
+                            expected: data_records,
+                            actual: address,
+                        },
+                    ));
+                }
+            }
+            _ => {
+                image.entry =
+                    Some(u16::try_from(address).map_err(|_| {
+                        LoadError::new(n, LoadErrorKind::AddressOutOfRange(address))
+                    })?);
+                break;
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+/// Write `bytes` at `address` as S1 records, with an S5 count and an S9 record for `start`.
+///
+/// Without a start address, the S9 record holds 0x0000. Data wrapping past 0xFFFF continues
+/// at 0x0000.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let text = write_srec(0x0200, &[0xA9, 0x01, 0x00], Some(0x0200));
+/// assert_eq!(text, "S0030000FC\nS1060200A901004D\nS5030001FB\nS9030200FA\n");
+/// ```
+pub fn write_srec(address: u16, bytes: &[u8], start: Option<u16>) -> String {
+    let mut text = String::new();
+    let mut record = |kind: u8, address: u16, data: &[u8]| {
+        let mut body = vec![data.len() as u8 + 3];
+        body.extend(address.to_be_bytes());
+        body.extend(data);
+        let checksum = !body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        body.push(checksum);
+
+        text.push_str(&format!("S{}{}\n", kind, hex::encode_upper(body)));
+    };
+
+    record(0, 0x0000, &[]);
+    let mut count = 0u16;
+    for (address, chunk) in chunks(address, bytes, RECORD_LEN) {
+        // A record can't cross 0xFFFF, so split one that would.
+        let split = (0x10000 - address as usize).min(chunk.len());
+        record(1, address, &chunk[..split]);
+        count = count.wrapping_add(1);
+        if split < chunk.len() {
+            record(1, 0x0000, &chunk[split..]);
+            count = count.wrapping_add(1);
+        }
+    }
+    record(5, count, &[]);
+    record(9, start.unwrap_or(0), &[]);
+
+    text
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+/// The Atari DOS run address, (0x02E0, 0x02E1).
+const RUNAD: u16 = 0x02E0;
+/// The Atari DOS init address, (0x02E2, 0x02E3).
+const INITAD: u16 = 0x02E2;
+
+/// Parse an Atari XEX file: segments of a start and (inclusive) end address then the bytes
+/// between, little endian, with a `$FFFF` marker before the first and optionally the others.
+///
+/// Segments that only write RUNAD or INITAD are taken as vectors rather than loaded. The last
+/// RUNAD written is [ProgramImage::entry], defaulting to the start of the first segment, and
+/// each INITAD written is added to [ProgramImage::inits].
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let xex = [
+///     0xFF, 0xFF, 0x00, 0x20, 0x02, 0x20, 0xA9, 0x01, 0x00, // $2000-$2002
+///     0xE0, 0x02, 0xE1, 0x02, 0x00, 0x20, // RUNAD = $2000
+/// ];
+/// let image = parse_xex(&xex).unwrap();
+/// assert_eq!(image.segments.len(), 1);
+/// assert_eq!(image.entry, Some(0x2000));
+/// ```
+pub fn parse_xex(bytes: &[u8]) -> std::result::Result<ProgramImage, LoadError> {
+    let mut image = ProgramImage::new();
+    let mut offset = 0;
+
+    let word = |offset: usize| match bytes.get(offset..offset + 2) {
+        Some([lo, hi]) => Ok(u16::from_le_bytes([*lo, *hi])),
+        _ => Err(LoadError::at_offset(bytes.len(), LoadErrorKind::Truncated)),
+    };
+
+    if word(0)? != 0xFFFF {
+        return Err(LoadError::at_offset(
+            0,
+            LoadErrorKind::Syntax("missing $FFFF header".to_string()),
+        ));
+    }
+
+    while offset < bytes.len() {
+        let header = offset;
+        if word(offset)? == 0xFFFF {
+            offset += 2;
+        }
+
+        let start = word(offset)?;
+        let end = word(offset + 2)?;
+        if end < start {
+            return Err(LoadError::at_offset(
+                header,
+                LoadErrorKind::Syntax(format!("segment ends at ${:04X} before its start", end)),
+            ));
+        }
+        offset += 4;
+
+        let len = (end - start) as usize + 1;
+        let data = bytes
+            .get(offset..offset + len)
+            .ok_or_else(|| LoadError::at_offset(bytes.len(), LoadErrorKind::Truncated))?;
+        offset += len;
+
+        if start < RUNAD || end > INITAD + 1 {
+            if image.entry.is_none() && image.segments.is_empty() {
+                image.entry = Some(start);
+            }
+            image.add_segment(start, data.to_vec());
+            continue;
+        }
+
+        // Vectors written a byte at a time keep the other byte from an earlier write.
+        let mut vectors = [0u8; 4];
+        if let Some(run) = image.entry {
+            vectors[..2].copy_from_slice(&run.to_le_bytes());
+        }
+        if let Some(init) = image.inits.last() {
+            vectors[2..].copy_from_slice(&init.to_le_bytes());
+        }
+        let first = (start - RUNAD) as usize;
+        vectors[first..first + len].copy_from_slice(data);
+
+        if start <= RUNAD + 1 {
+            image.entry = Some(u16::from_le_bytes([vectors[0], vectors[1]]));
+        }
+        if end >= INITAD {
+            image
+                .inits
+                .push(u16::from_le_bytes([vectors[2], vectors[3]]));
+        }
+    }
+
+    Ok(image)
+}
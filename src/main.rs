@@ -0,0 +1,113 @@
+//! `vm6502`, an interactive machine-language monitor and terminal debugger.
+//!
+//! Usage:
+//! - `vm6502 [file [addr]]` starts the monitor, type `help` at the prompt for the commands.
+//! - `vm6502 tui [file [addr]]` starts the full-screen debugger.
+//! - `vm6502 run [options] file[@addr]...` runs headless and checks expectations, see
+//!   [runner](vm6502::runner). With the `json` feature the final state is printed as JSON.
+//!   The exit code is 1 if the run faulted or an expectation failed, or 2 if it couldn't start.
+//!
+//! The raw binary `file` is loaded at `addr` (default `0200`) and the PC is set to it.
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use vm6502::prelude::*;
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+
+    let (tui, files) = match args.split_first() {
+        Some((command, args)) if command == "run" => return run(args),
+        Some((command, files)) if command == "tui" => (true, files),
+        _ => (false, &args[..]),
+    };
+
+    if let Some(path) = files.first() {
+        let address = files.get(1).map_or("0200", String::as_str);
+        let commands = [
+            format!("l \"{}\" {}", path, address),
+            format!("r pc={}", address),
+        ];
+
+        for command in commands {
+            match monitor.execute(&mut vm, &command) {
+                Ok(Response::Output(output)) if !tui => println!("{}", output),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+
+    if tui {
+        return run_debugger(&mut vm);
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(${:04X}) ", vm.registers.pc);
+        io::stdout().flush().ok();
+
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+
+        match monitor.execute(&mut vm, &line) {
+            Ok(Response::Output(output)) if output.is_empty() => {}
+            Ok(Response::Output(output)) => println!("{}", output),
+            Ok(Response::Quit) => break,
+            Err(e) => println!("?{}", e),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn run(args: &[String]) -> ExitCode {
+    let report = Runner::from_args(args).and_then(|runner| runner.run().map_err(|e| e.to_string()));
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    #[cfg(feature = "json")]
+    println!("{:#}", report.to_json());
+
+    if let Err(e) = &report.stop {
+        eprintln!("FAIL: {}", e);
+    }
+    for failure in &report.failures {
+        eprintln!("FAIL: {}", failure);
+    }
+
+    match report.passed() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+#[cfg(feature = "tui")]
+fn run_debugger(vm: &mut VirtualMachine) -> ExitCode {
+    match run_tui(vm) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_debugger(_vm: &mut VirtualMachine) -> ExitCode {
+    eprintln!("vm6502 was built without the tui feature");
+    ExitCode::FAILURE
+}
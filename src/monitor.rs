@@ -0,0 +1,572 @@
+//! Interactive machine-language monitor.
+//!
+//! A line oriented monitor in the style of the VICE and Apple II monitors, used by the
+//! `vm6502` binary. Addresses and values are hex, with an optional `$` prefix, and addresses
+//! can also be symbols written as `.name` or `.name+3`.
+//!
+//! | Command                 | Action                                             |
+//! |-------------------------|----------------------------------------------------|
+//! | `l file addr`           | Load a raw binary file at `addr`                   |
+//! | `s file start end`      | Save memory `start..=end` to a file                |
+//! | `m [start [end]]`       | Examine memory                                     |
+//! | `> addr byte...`        | Deposit bytes at `addr`, straight onto the bus     |
+//! | `a addr instruction`    | Assemble one instruction at `addr`                 |
+//! | `d [start [end]]`       | Disassemble                                        |
+//! | `sym [file]`            | Load symbols from a file, or list symbols          |
+//! | `r [reg=value...]`      | Show or set the registers `A X Y SP P PC`          |
+//! | `b [addr]`              | Set a breakpoint, or list breakpoints              |
+//! | `w start [end] [r\|w]`  | Set a watchpoint on reads, writes or both          |
+//! | `del id`                | Delete a breakpoint or watchpoint                  |
+//! | `z [count]`             | Step instructions                                  |
+//! | `n`                     | Step over a subroutine call                        |
+//! | `ret`                   | Step out of the current subroutine                 |
+//! | `g [addr]`              | Go, from `addr` if given                           |
+//! | `reset`                 | Reset the CPU from the reset vector                |
+//! | `x`, `q`                | Exit                                               |
+//!
+//! # Example
+//! ```
+//! use vm6502::prelude::*;
+//!
+//! let mut vm = VirtualMachine::new();
+//! let mut monitor = Monitor::new();
+//!
+//! monitor.execute(&mut vm, "> 0200 a9 01 00").unwrap();
+//! monitor.execute(&mut vm, "g 0200").unwrap();
+//! assert_eq!(vm.registers.ac, 0x01);
+//! ```
+use std::fmt::{Display, Formatter, Result};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::monitor::{Monitor, MonitorError, Response};
+}
+
+/// Lines shown by `m` and instructions shown by `d` when no end is given.
+const DEFAULT_LINES: usize = 16;
+
+/// The result of a monitor command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// Text to show, possibly empty.
+    Output(String),
+    /// The user asked to exit.
+    Quit,
+}
+
+/// Why a monitor command failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonitorError {
+    /// The command isn't known.
+    UnknownCommand(String),
+    /// The arguments didn't match the command, with its usage.
+    Usage(&'static str),
+    /// An argument wasn't a valid hex number, symbol or register.
+    InvalidArgument(String),
+    /// A file couldn't be read or written.
+    Io(String),
+    /// The instruction couldn't be assembled.
+    Assembler(AssemblerError),
+    /// The machine faulted.
+    Vm(VmError),
+}
+
+impl Display for MonitorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            MonitorError::UnknownCommand(command) => write!(f, "unknown command: {}", command),
+            MonitorError::Usage(usage) => write!(f, "usage: {}", usage),
+            MonitorError::InvalidArgument(argument) => write!(f, "invalid argument: {}", argument),
+            MonitorError::Io(message) => write!(f, "{}", message),
+            MonitorError::Assembler(e) => write!(f, "{}", e),
+            MonitorError::Vm(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<VmError> for MonitorError {
+    fn from(e: VmError) -> Self {
+        MonitorError::Vm(e)
+    }
+}
+
+/// Monitor state kept between commands.
+#[derive(Debug, Clone, Default)]
+pub struct Monitor {
+    /// Where `m` continues from without a start address.
+    next_memory: Option<u16>,
+    /// Where `d` continues from without a start address.
+    next_disassembly: Option<u16>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Monitor::default()
+    }
+
+    /// Run a single command line against `vm`.
+    pub fn execute<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        line: &str,
+    ) -> std::result::Result<Response, MonitorError> {
+        let args = split(line);
+        let Some((command, args)) = args.split_first() else {
+            return Ok(Response::Output(String::new()));
+        };
+
+        let output = match command.to_ascii_lowercase().as_str() {
+            "l" | "load" => self.load(vm, args)?,
+            "s" | "save" => self.save(vm, args)?,
+            "m" => self.memory(vm, args)?,
+            ">" => self.deposit(vm, args)?,
+            "a" => self.assemble(vm, args)?,
+            "d" => self.disassemble(vm, args)?,
+            "sym" => self.symbols(vm, args)?,
+            "r" => self.registers(vm, args)?,
+            "b" | "break" => self.breakpoint(vm, args)?,
+            "w" | "watch" => self.watchpoint(vm, args)?,
+            "del" | "delete" => {
+                let [id] = args else {
+                    return Err(MonitorError::Usage("del id"));
+                };
+                let id = id
+                    .parse::<usize>()
+                    .map_err(|_| MonitorError::InvalidArgument(id.clone()))?;
+
+                if !vm.remove_breakpoint(id) {
+                    return Err(MonitorError::InvalidArgument(id.to_string()));
+                }
+                String::new()
+            }
+            "z" | "step" => {
+                let count = match args {
+                    [] => 1,
+                    [count] => count
+                        .parse::<u64>()
+                        .map_err(|_| MonitorError::InvalidArgument(count.clone()))?,
+                    _ => return Err(MonitorError::Usage("z [count]")),
+                };
+                let stop = vm.run_for_instructions(count)?;
+                self.stopped(vm, stop)
+            }
+            "n" | "next" => {
+                let stop = vm.step_over()?;
+                self.stopped(vm, stop)
+            }
+            "ret" => {
+                let stop = vm.step_out()?;
+                self.stopped(vm, stop)
+            }
+            "g" | "go" => {
+                match args {
+                    [] => {}
+                    [address] => {
+                        vm.registers.pc = parse_address(vm, address)?;
+                        vm.halted = false;
+                    }
+                    _ => return Err(MonitorError::Usage("g [addr]")),
+                }
+                let stop = vm.execute()?;
+                self.stopped(vm, stop)
+            }
+            "reset" => {
+                vm.reset();
+                registers(vm)
+            }
+            "x" | "q" | "exit" | "quit" => return Ok(Response::Quit),
+            "?" | "help" => HELP.to_string(),
+            _ => return Err(MonitorError::UnknownCommand(command.clone())),
+        };
+
+        Ok(Response::Output(output))
+    }
+
+    fn load<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        let [path, address] = args else {
+            return Err(MonitorError::Usage("l file addr"));
+        };
+        let address = parse_address(vm, address)?;
+
+        let bytes = std::fs::read(path)
+            .map_err(|e| MonitorError::Io(format!("failed to read {}: {}", path, e)))?;
+        let end = (address as usize + bytes.len()).saturating_sub(1);
+
+        let mut image = ProgramImage::new();
+        image.add_segment(address, bytes);
+        vm.load_image(&image)?;
+
+        Ok(format!("loaded ${:04X}-${:04X}", address, end))
+    }
+
+    fn save<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        let [path, start, end] = args else {
+            return Err(MonitorError::Usage("s file start end"));
+        };
+        let (start, end) = (parse_address(vm, start)?, parse_address(vm, end)?);
+        if end < start {
+            return Err(MonitorError::InvalidArgument(format!("${:04X}", end)));
+        }
+
+        let bytes = (start..=end)
+            .map(|address| vm.flatmap.peek(address))
+            .collect::<Vec<u8>>();
+        std::fs::write(path, bytes)
+            .map_err(|e| MonitorError::Io(format!("failed to write {}: {}", path, e)))?;
+
+        Ok(format!("saved ${:04X}-${:04X}", start, end))
+    }
+
+    fn memory<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        let start = match args.first() {
+            Some(start) => parse_address(vm, start)?,
+            None => self.next_memory.unwrap_or(vm.registers.pc),
+        };
+        let end = match args {
+            [] | [_] => start.saturating_add((DEFAULT_LINES * 16 - 1) as u16),
+            [_, end] => parse_address(vm, end)?,
+            _ => return Err(MonitorError::Usage("m [start [end]]")),
+        };
+
+        let mut lines = Vec::new();
+        let mut address = start as u32;
+        while address <= end as u32 {
+            let row = (address..=(address + 15).min(end as u32))
+                .map(|a| vm.flatmap.peek(a as u16))
+                .collect::<Vec<u8>>();
+
+            let hex = row
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let text = row
+                .iter()
+                .map(|b| match b {
+                    0x20..=0x7E => *b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+
+            lines.push(format!("{:04X}  {:<47}  {}", address, hex, text));
+            address += 16;
+        }
+
+        self.next_memory = Some(address as u16);
+        Ok(lines.join("\n"))
+    }
+
+    fn deposit<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        let [address, bytes @ ..] = args else {
+            return Err(MonitorError::Usage("> addr byte..."));
+        };
+        if bytes.is_empty() {
+            return Err(MonitorError::Usage("> addr byte..."));
+        }
+
+        let address = parse_address(vm, address)?;
+        let bytes = bytes
+            .iter()
+            .map(|b| u8::from_str_radix(b.trim_start_matches('$'), 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|e| MonitorError::InvalidArgument(e.to_string()))?;
+
+        // Unlike `l` and `a`, deposits are raw bus writes, so they can poke device registers
+        // that don't read back what was written.
+        for (i, byte) in bytes.iter().enumerate() {
+            vm.flatmap.write(address.wrapping_add(i as u16), *byte);
+        }
+
+        Ok(String::new())
+    }
+
+    fn assemble<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        let [address, instruction @ ..] = args else {
+            return Err(MonitorError::Usage("a addr instruction"));
+        };
+        let address = parse_address(vm, address)?;
+
+        let bytes = Assembler::with_origin(address)
+            .assemble(&instruction.join(" "))
+            .map_err(MonitorError::Assembler)?;
+        let mut image = ProgramImage::new();
+        image.add_segment(address, bytes);
+        vm.load_image(&image)?;
+
+        let assembled = vm.disassemble_count(address, 1);
+        Ok(assembled[0].listing())
+    }
+
+    fn disassemble<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        let start = match args.first() {
+            Some(start) => parse_address(vm, start)?,
+            None => self.next_disassembly.unwrap_or(vm.registers.pc),
+        };
+        let instructions = match args {
+            [] | [_] => vm.disassemble_count(start, DEFAULT_LINES),
+            [_, end] => vm.disassemble(start, parse_address(vm, end)?),
+            _ => return Err(MonitorError::Usage("d [start [end]]")),
+        };
+
+        self.next_disassembly = instructions.last().map(|i| i.next());
+
+        // Labels go on a line of their own before their instruction.
+        let mut lines = Vec::new();
+        for instruction in &instructions {
+            if let Some(name) = vm.symbols.name(instruction.address) {
+                lines.push(format!("{}:", name));
+            }
+            lines.push(instruction.labelled_listing(&vm.symbols));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn symbols<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        match args {
+            [] => Ok(vm
+                .symbols
+                .iter()
+                .map(|(name, address)| format!("${:04X}  {}", address, name))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            [path] => {
+                let count = vm.load_symbols(path).map_err(|e| match e {
+                    VmError::Io { reason, .. } => MonitorError::Io(reason),
+                    e => MonitorError::Vm(e),
+                })?;
+                Ok(format!("loaded {} symbols", count))
+            }
+            _ => Err(MonitorError::Usage("sym [file]")),
+        }
+    }
+
+    fn registers<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        for assignment in args {
+            let Some((register, value)) = assignment.split_once('=') else {
+                return Err(MonitorError::Usage("r [reg=value...]"));
+            };
+            let value = match register.to_ascii_uppercase().as_str() {
+                "PC" => parse_address(vm, value)?,
+                _ => parse_hex(value)?,
+            };
+            let byte = || {
+                u8::try_from(value).map_err(|_| MonitorError::InvalidArgument(assignment.clone()))
+            };
+
+            match register.to_ascii_uppercase().as_str() {
+                "A" | "AC" => vm.registers.ac = byte()?,
+                "X" => vm.registers.x = byte()?,
+                "Y" => vm.registers.y = byte()?,
+                "SP" => vm.registers.sp = byte()?,
+                "P" | "SR" => vm.registers.sr = byte()?,
+                "PC" => {
+                    vm.registers.pc = value;
+                    vm.halted = false;
+                }
+                _ => return Err(MonitorError::InvalidArgument(register.to_string())),
+            }
+        }
+
+        Ok(registers(vm))
+    }
+
+    fn breakpoint<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        match args {
+            [] => Ok(vm
+                .breakpoints()
+                .iter()
+                .map(|(id, breakpoint)| format!("{}: {}", id, describe(vm, breakpoint)))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            [address] => {
+                let id = vm.add_breakpoint(Breakpoint::Pc(parse_address(vm, address)?));
+                Ok(format!("breakpoint {}", id))
+            }
+            _ => Err(MonitorError::Usage("b [addr]")),
+        }
+    }
+
+    fn watchpoint<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        args: &[String],
+    ) -> std::result::Result<String, MonitorError> {
+        const USAGE: &str = "w start [end] [r|w|rw]";
+
+        let (range, access) = match args.last().map(|a| a.to_ascii_lowercase()).as_deref() {
+            Some("r") => (&args[..args.len() - 1], Access::Read),
+            Some("w") => (&args[..args.len() - 1], Access::Write),
+            Some("rw") => (&args[..args.len() - 1], Access::ReadWrite),
+            _ => (args, Access::ReadWrite),
+        };
+        let (start, end) = match range {
+            [start] => (parse_address(vm, start)?, parse_address(vm, start)?),
+            [start, end] => (parse_address(vm, start)?, parse_address(vm, end)?),
+            _ => return Err(MonitorError::Usage(USAGE)),
+        };
+
+        let id = vm.add_breakpoint(Breakpoint::Watch { start, end, access });
+        Ok(format!("watchpoint {}", id))
+    }
+
+    /// Describe why execution stopped and where.
+    fn stopped<B: Bus>(&mut self, vm: &VirtualMachine<B>, stop: StopReason) -> String {
+        let reason = match stop {
+            StopReason::Breakpoint { id, .. } => format!("breakpoint {}", id),
+            StopReason::Watchpoint {
+                id,
+                address,
+                access,
+            } => format!("watchpoint {}: {:?} ${:04X}", id, access, address),
+            StopReason::Halted => "halted".to_string(),
+            StopReason::Jammed { pc } => format!("jammed at ${:04X}", pc),
+            _ => String::new(),
+        };
+
+        let current = vm.disassemble_count(vm.registers.pc, 1)[0];
+        self.next_disassembly = Some(current.next());
+
+        [reason, registers(vm), current.labelled_listing(&vm.symbols)]
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+const HELP: &str = "\
+l file addr          load a binary file
+s file start end     save memory to a file
+m [start [end]]      examine memory
+> addr byte...       deposit bytes
+a addr instruction   assemble an instruction
+d [start [end]]      disassemble
+sym [file]           load or list symbols
+r [reg=value...]     show or set registers (A X Y SP P PC)
+b [addr]             set or list breakpoints
+w start [end] [r|w]  set a watchpoint
+del id               delete a breakpoint
+z [count]            step
+n                    step over
+ret                  step out
+g [addr]             go
+reset                reset the CPU
+x                    exit";
+
+/// The register line, ex. `PC=0200 A=00 X=00 Y=00 SP=FF NV-BDIZC=00100100 CYC=0`.
+///
+/// A PC near a symbol is followed by its name, as in `PC=0203 (main+3)`.
+fn registers<B: Bus>(vm: &VirtualMachine<B>) -> String {
+    let r = &vm.registers;
+    let pc = match vm.symbols.locate(r.pc) {
+        Some(_) => format!("{:04X} ({})", r.pc, vm.symbols.describe(r.pc)),
+        None => format!("{:04X}", r.pc),
+    };
+
+    format!(
+        "PC={} A={:02X} X={:02X} Y={:02X} SP={:02X} NV-BDIZC={:08b} CYC={}",
+        pc, r.ac, r.x, r.y, r.sp, r.sr, vm.cycles
+    )
+}
+
+fn describe<B: Bus>(vm: &VirtualMachine<B>, breakpoint: &Breakpoint) -> String {
+    match breakpoint {
+        Breakpoint::Pc(pc) => match vm.symbols.locate(*pc) {
+            Some(_) => format!("pc ${:04X} ({})", pc, vm.symbols.describe(*pc)),
+            None => format!("pc ${:04X}", pc),
+        },
+        Breakpoint::Watch { start, end, access } => {
+            format!("watch ${:04X}-${:04X} {:?}", start, end, access)
+        }
+        Breakpoint::Register { register, value } => format!("{:?} == ${:02X}", register, value),
+        Breakpoint::Flag { flag, set } => format!("{:?} == {}", flag, set),
+    }
+}
+
+/// Split a command line on whitespace, keeping double quoted arguments whole.
+///
+/// `>` may be written without a following space, as in `>0200 ea`.
+fn split(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = match line.strip_prefix('>') {
+        Some(rest) => format!("> {}", rest),
+        None => line.to_string(),
+    };
+
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+
+    args
+}
+
+/// Parse a hex address, or a symbol written as `.name` or `.name+offset`.
+fn parse_address<B: Bus>(
+    vm: &VirtualMachine<B>,
+    s: &str,
+) -> std::result::Result<u16, MonitorError> {
+    match s.strip_prefix('.') {
+        Some(symbol) => vm
+            .symbols
+            .resolve(symbol)
+            .ok_or_else(|| MonitorError::InvalidArgument(s.to_string())),
+        None => parse_hex(s),
+    }
+}
+
+/// Parse a hex address or value, with an optional `$` prefix.
+fn parse_hex(s: &str) -> std::result::Result<u16, MonitorError> {
+    u16::from_str_radix(s.trim_start_matches('$'), 16)
+        .map_err(|_| MonitorError::InvalidArgument(s.to_string()))
+}
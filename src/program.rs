@@ -1,7 +1,9 @@
 use hex::decode;
 /// Higher level abstractions over vm module.
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use crate::loader::prelude::*;
 use crate::vm::prelude::*;
 
 pub mod prelude {
@@ -15,94 +17,275 @@ pub mod prelude {
 /// applying and using programs with the VM.
 pub trait ProgramController {
     /// Insert a hex encoded string `prog` at heap offset `offset`.
-    fn insert_program(&mut self, offset: u16, prog: &str);
-    /// Insert a hex encoded string `prog` at heap offset `offset` and set the PC to `offset`.
-    fn set_program(&mut self, offset: u16, prog: &str);
+    ///
+    /// Fails with [VmError::MalformedProgram] if `prog` isn't hex encoded, and
+    /// [VmError::OutOfBounds] if it doesn't fit in memory.
+    fn insert_program(&mut self, offset: u16, prog: &str) -> VmResult<()>;
+    /// Insert a hex encoded string `prog` at heap offset `offset` and set the PC to it.
+    ///
+    /// The [PC](Registers::pc) is a bus address, so it's set to `heap_bounds.0 + offset`,
+    /// ex. 0x0200 for offset 0, rather than to `offset` itself.
+    fn set_program(&mut self, offset: u16, prog: &str) -> VmResult<()>;
 
     /// Insert a series of bytes `prog` at heap offset `offset`.
-    fn insert_bytes(&mut self, offset: u16, prog: Vec<u8>);
+    fn insert_bytes(&mut self, offset: u16, prog: Vec<u8>) -> VmResult<()>;
+
+    /// Install a [ProgramImage]: write its segments and vectors, add its symbols to the
+    /// [symbol table](VirtualMachine::symbols), then set the PC to its entry.
+    ///
+    /// Nothing is written unless the whole image is valid. Fails with [VmError::OutOfBounds]
+    /// if a segment runs past 0xFFFF, and [VmError::MalformedProgram] if segments or vectors
+    /// overlap.
+    ///
+    /// Every byte is read back with [peek](Bus::peek) once written, and the load fails with
+    /// [VmError::MalformedProgram] if one doesn't stick, ex. because it landed in a [Rom].
+    /// The rest of the image has been written by then, and the PC and symbols are unchanged.
+    fn load_image(&mut self, image: &ProgramImage) -> VmResult<()>;
 
     /// Load a program from a file at `path` at heap offset `offset`.
     /// The file should be a flat binary file.
-    fn load_program(&mut self, offset: u16, path: &str);
-
-    /// Set the interrupt vectors to the given values.
-    fn set_interrupt_vectors(&mut self, nmi: u16, irq: u16, brk: u16);
+    ///
+    /// Fails with [VmError::Io] if the file can't be read, and like
+    /// [load_image](ProgramController::load_image) if it runs past 0xFFFF.
+    fn load_program(&mut self, offset: u16, path: &str) -> VmResult<()>;
+
+    /// Load an Intel HEX file at `path`, placing each record at its own address.
+    ///
+    /// A start address record sets the PC. Fails with [VmError::Io] if the file can't be read,
+    /// and [VmError::MalformedProgram] naming the line of a bad record or checksum.
+    fn load_ihex(&mut self, path: &str) -> VmResult<()>;
+    /// Load a Motorola S-record file at `path`, placing each record at its own address.
+    ///
+    /// A termination record sets the PC. Fails like [load_ihex](ProgramController::load_ihex).
+    fn load_srec(&mut self, path: &str) -> VmResult<()>;
+    /// Load a Commodore PRG file at `path` at its load address, and set the PC to it.
+    ///
+    /// Returns the loaded image. Fails with [VmError::Io] if the file can't be read, and
+    /// [VmError::MalformedProgram] if it's too short or runs past 0xFFFF.
+    fn load_prg(&mut self, path: &str) -> VmResult<ProgramImage>;
+    /// Load an Atari XEX file at `path`, placing each segment at its own address, and set the PC
+    /// to its run address.
+    ///
+    /// Returns the loaded image, whose [inits](ProgramImage::inits) are left for the caller to
+    /// run. Fails like [load_prg](ProgramController::load_prg), or with
+    /// [VmError::MalformedProgram] if segments overlap.
+    fn load_xex(&mut self, path: &str) -> VmResult<ProgramImage>;
+    /// Load an o65 object at `path` with its text at `base`, followed by its data and bss, and
+    /// set the PC to the start of text.
+    ///
+    /// Imports are resolved from `imports`, which can be the symbols of an earlier module.
+    /// Returns the loaded image, whose symbols are the module's relocated exports. Fails with
+    /// [VmError::Io] if the file can't be read, and [VmError::MalformedProgram] if it isn't a
+    /// 6502 o65 object, an import is missing or it overlaps itself.
+    fn load_o65(
+        &mut self,
+        path: &str,
+        base: u16,
+        imports: &HashMap<String, u16>,
+    ) -> VmResult<ProgramImage>;
+    /// Save memory `start..=end` to `path` as Intel HEX, with the PC as the start address.
+    ///
+    /// Fails with [VmError::Io] if the file can't be written.
+    fn save_ihex(&self, path: &str, start: u16, end: u16) -> VmResult<()>;
+    /// Save memory `start..=end` to `path` as S-records, with the PC as the start address.
+    ///
+    /// Fails with [VmError::Io] if the file can't be written.
+    fn save_srec(&self, path: &str, start: u16, end: u16) -> VmResult<()>;
+
+    /// Set the NMI, reset and IRQ/BRK vectors to the given values.
+    fn set_interrupt_vectors(&mut self, nmi: u16, reset: u16, irq: u16);
     /// Set the interrupt vectors to the values: (0xFFFA, 0xFFFB), (0xFFFC, 0xFFFD), (0xFFFE, 0xFFFF)
     fn default_interrupt_vectors(&mut self);
 
-    /// Run the internal program.
-    fn execute(&mut self) -> u64;
-
-    /// Run the internally set program at `offset` for `duration`.
-    fn run(&mut self, duration: Duration) -> (u64, Duration);
+    /// Run the internal program until it halts or hits a [breakpoint](DebugInterface).
+    fn execute(&mut self) -> VmResult<StopReason>;
+
+    /// Run the internally set program for `duration`, or until it halts or hits a breakpoint.
+    ///
+    /// Returns the cycles executed, the time taken and why it stopped.
+    fn run(&mut self, duration: Duration) -> VmResult<(u64, Duration, StopReason)>;
+
+    /// Step one instruction, treating a JSR and its subroutine as a single step.
+    ///
+    /// Stops with [StopReason::StepComplete] once the matching RTS returns.
+    fn step_over(&mut self) -> VmResult<StopReason>;
+    /// Run until the current subroutine returns with an RTS.
+    ///
+    /// Stops with [StopReason::StepComplete] once the RTS has executed.
+    fn step_out(&mut self) -> VmResult<StopReason>;
+    /// Run until the PC reaches `pc`, stopping with [StopReason::ReachedPc].
+    fn run_until_pc(&mut self, pc: u16) -> VmResult<StopReason>;
+    /// Run `count` instructions, stopping with [StopReason::InstructionLimit].
+    fn run_for_instructions(&mut self, count: u64) -> VmResult<StopReason>;
+    /// Run until at least `cycles` cycles have passed, stopping with [StopReason::CycleLimit].
+    fn run_for_cycles(&mut self, cycles: u64) -> VmResult<StopReason>;
+    /// Run until `predicate` holds after an instruction, stopping with [StopReason::Predicate].
+    ///
+    /// Like every run control, this also stops when the machine halts or hits a breakpoint.
+    fn run_until<F: FnMut(&Self) -> bool>(&mut self, predicate: F) -> VmResult<StopReason>
+    where
+        Self: Sized;
 
     /// Fill the stack with ops.
-    fn fill_stack(&mut self, ops: Vec<u8>);
-
-    /// Reset machine state.
+    fn fill_stack(&mut self, ops: Vec<u8>) -> VmResult<()>;
+
+    /// Save a [snapshot](SnapshotInterface::snapshot) of the machine to the file at `path`.
+    ///
+    /// Fails with [VmError::Io] if the file can't be written.
+    fn save_snapshot(&self, path: &str) -> VmResult<()>;
+    /// Restore the machine from a snapshot file at `path`.
+    ///
+    /// Fails with [VmError::Io] if the file can't be read, and [VmError::InvalidSnapshot]
+    /// if it isn't a snapshot of the current version.
+    fn load_snapshot(&mut self, path: &str) -> VmResult<()>;
+
+    /// Hardware reset, as if the RES line was pulled low.
+    ///
+    /// Memory is left intact, SP is set to 0xFD, [Status::Interrupt] is set and the PC is
+    /// loaded from the reset vector at (0xFFFC, 0xFFFD). This takes 7 cycles.
+    /// A [jammed](VirtualMachine::jammed) CPU runs again after a reset.
     fn reset(&mut self);
+    /// Power cycle the machine, clearing all memory and registers.
+    fn power_on(&mut self);
 }
 
-impl ProgramController for VirtualMachine {
+impl<B: Bus> ProgramController for VirtualMachine<B> {
     /// Insert a hex encoded string `prog` at heap offset `offset`.s
-    fn insert_program(&mut self, offset: u16, prog: &str) {
-        let offset = offset + self.heap_bounds.0 as u16;
-        let decoded = if let Ok(d) = decode(prog) {
-            d
-        } else {
-            panic!("Failed to decode program - it probably wasn't byte aligned or hex encoded.");
-        };
-
-        for (i, byte) in decoded.iter().enumerate() {
-            self.flatmap[offset as usize + i] = *byte;
-        }
+    fn insert_program(&mut self, offset: u16, prog: &str) -> VmResult<()> {
+        let decoded = decode(prog).map_err(|e| {
+            self.malformed_program(format!(
+                "failed to decode program, it probably wasn't byte aligned or hex encoded: {}",
+                e
+            ))
+        })?;
+
+        self.insert_bytes(offset, decoded)
     }
 
     // TODO: Higher level program allocator.
     /// Replaces and runs the program at `offset`.
-    fn set_program(&mut self, offset: u16, prog: &str) {
-        self.insert_program(offset, prog);
-        self.registers.pc = offset;
+    fn set_program(&mut self, offset: u16, prog: &str) -> VmResult<()> {
+        self.insert_program(offset, prog)?;
+        self.registers.pc = self.heap_address(offset)?;
+
+        Ok(())
     }
 
     /// Insert a series of bytes `prog` at heap offset `offset`.
-    fn insert_bytes(&mut self, offset: u16, prog: Vec<u8>) {
-        let offset = offset + self.heap_bounds.0 as u16;
+    fn insert_bytes(&mut self, offset: u16, prog: Vec<u8>) -> VmResult<()> {
+        let mut image = ProgramImage::new();
+        image.add_segment(self.heap_address(offset)?, prog);
+
+        self.load_image(&image)
+    }
 
-        for (i, byte) in prog.iter().enumerate() {
-            self.flatmap[offset as usize + i] = *byte;
+    fn load_image(&mut self, image: &ProgramImage) -> VmResult<()> {
+        let mut segments = image
+            .segments
+            .iter()
+            .chain(&image.vector_segments())
+            .filter(|s| !s.bytes.is_empty())
+            .cloned()
+            .collect::<Vec<Segment>>();
+        segments.sort_by_key(|s| s.address);
+
+        if let Some(segment) = segments.iter().find(|s| s.end() > 0x10000) {
+            return Err(self.out_of_bounds(segment.end() - 1));
         }
+        for pair in segments.windows(2) {
+            if pair[0].end() > pair[1].address as usize {
+                return Err(self.malformed_program(format!(
+                    "segments at ${:04X}-${:04X} and ${:04X}-${:04X} overlap",
+                    pair[0].address,
+                    pair[0].end() - 1,
+                    pair[1].address,
+                    pair[1].end() - 1
+                )));
+            }
+        }
+
+        for segment in &segments {
+            for (i, byte) in segment.bytes.iter().enumerate() {
+                self.flatmap.write(segment.address + i as u16, *byte);
+            }
+        }
+        // A bus may ignore writes, ex. a ROM mapped through a MemoryMap.
+        for segment in &segments {
+            for (i, byte) in segment.bytes.iter().enumerate() {
+                let address = segment.address + i as u16;
+                if self.flatmap.peek(address) != *byte {
+                    return Err(self.malformed_program(format!(
+                        "${:04X} in segment ${:04X}-${:04X} isn't writable",
+                        address,
+                        segment.address,
+                        segment.end() - 1
+                    )));
+                }
+            }
+        }
+        self.symbols.extend(image.symbols.clone());
+        if let Some(entry) = image.entry {
+            self.registers.pc = entry;
+        }
+
+        Ok(())
     }
 
     /// Load a program from a file at `path` at heap offset `offset`.
-    fn load_program(&mut self, offset: u16, path: &str) {
-        let offset = offset + self.heap_bounds.0 as u16;
-        let prog = std::fs::read(path).unwrap();
+    fn load_program(&mut self, offset: u16, path: &str) -> VmResult<()> {
+        let prog = std::fs::read(path)
+            .map_err(|e| self.io_error(format!("failed to read {}: {}", path, e)))?;
 
-        let len = prog.len();
+        self.insert_bytes(offset, prog)
+    }
 
-        // 0x200 + 0x012 is accounting for zp, stack and interrupt vectors.
-        if (offset as usize + len) > self.heap_bounds.1 - 0x212 {
-            panic!("Program is too large to fit in heap.");
-        } else if len % 2 != 0 {
-            panic!("Program is not byte aligned.");
-        }
+    fn load_ihex(&mut self, path: &str) -> VmResult<()> {
+        self.load_records(path, parse_ihex)
+    }
 
-        for (i, byte) in prog.iter().enumerate() {
-            self.flatmap[offset as usize + i] = *byte;
-        }
+    fn load_srec(&mut self, path: &str) -> VmResult<()> {
+        self.load_records(path, parse_srec)
+    }
+
+    fn load_prg(&mut self, path: &str) -> VmResult<ProgramImage> {
+        self.load_binary(path, parse_prg)
+    }
+
+    fn load_xex(&mut self, path: &str) -> VmResult<ProgramImage> {
+        self.load_binary(path, parse_xex)
+    }
+
+    fn load_o65(
+        &mut self,
+        path: &str,
+        base: u16,
+        imports: &HashMap<String, u16>,
+    ) -> VmResult<ProgramImage> {
+        self.load_binary(path, |bytes| {
+            let object = parse_o65(bytes)?;
+            object.relocate(&object.layout_at(base), imports)
+        })
+    }
+
+    fn save_ihex(&self, path: &str, start: u16, end: u16) -> VmResult<()> {
+        self.save_records(path, start, end, write_ihex)
     }
 
-    fn set_interrupt_vectors(&mut self, nmi: u16, irq: u16, brk: u16) {
-        self.flatmap[self.interrupt_bounds.0] = (nmi & 0xFF) as u8;
-        self.flatmap[self.interrupt_bounds.1] = (nmi >> 8) as u8;
+    fn save_srec(&self, path: &str, start: u16, end: u16) -> VmResult<()> {
+        self.save_records(path, start, end, write_srec)
+    }
 
-        self.flatmap[self.reset_bounds.0] = (irq & 0xFF) as u8;
-        self.flatmap[self.reset_bounds.1] = (irq >> 8) as u8;
+    fn set_interrupt_vectors(&mut self, nmi: u16, reset: u16, irq: u16) {
+        let vectors = [
+            (self.interrupt_bounds, nmi),
+            (self.reset_bounds, reset),
+            (self.irq_bounds, irq),
+        ];
 
-        self.flatmap[self.irq_bounds.0] = (brk & 0xFF) as u8;
-        self.flatmap[self.irq_bounds.1] = (brk >> 8) as u8;
+        for ((lo, hi), vector) in vectors {
+            self.flatmap.write(lo as u16, (vector & 0xFF) as u8);
+            self.flatmap.write(hi as u16, (vector >> 8) as u8);
+        }
     }
 
     fn default_interrupt_vectors(&mut self) {
@@ -110,54 +293,223 @@ impl ProgramController for VirtualMachine {
     }
 
     /// Run the internally set program. Intended API for running programs.
-    fn execute(&mut self) -> u64 {
-        let old_cycles = self.cycles;
-
-        while !self.halted {
-            self.step();
+    fn execute(&mut self) -> VmResult<StopReason> {
+        loop {
+            if let Some(reason) = self.debug_step()? {
+                return Ok(reason);
+            }
         }
-
-        self.cycles - old_cycles
     }
 
     /// Run the internally set program for `duration` time, returning the number of cycles executed.
-    fn run(&mut self, duration: Duration) -> (u64, Duration) {
+    fn run(&mut self, duration: Duration) -> VmResult<(u64, Duration, StopReason)> {
         // Save cycles for delta.
         let old_cycles = self.cycles;
 
         let start = Instant::now();
-        while start.elapsed() < duration && !self.halted {
-            self.step();
+        let reason = loop {
+            if start.elapsed() >= duration {
+                break StopReason::TimedOut;
+            }
+
+            if let Some(reason) = self.debug_step()? {
+                break reason;
+            }
 
             if self.registers.pc == self.irq_bounds.0 as u16 {
                 self.halted = true;
             }
+        };
+
+        Ok((self.cycles - old_cycles, start.elapsed(), reason))
+    }
+
+    fn reset(&mut self) {
+        // The reset sequence performs three suppressed pushes.
+        self.registers.sp = 0xFD;
+        self.set_status(Status::Interrupt, true);
+        if cfg!(feature = "cmos") {
+            self.set_status(Status::Decimal, false);
         }
 
-        (self.cycles - old_cycles, start.elapsed())
+        self.registers.pc = u16::from_le_bytes([
+            self.flatmap.read(self.reset_bounds.0 as u16),
+            self.flatmap.read(self.reset_bounds.1 as u16),
+        ]);
+
+        self.nmi_pending = false;
+        self.halted = false;
+        self.jammed = false;
+        self.cycles += 7;
+
+        let pc = self.registers.pc;
+        self.notify(|o| o.on_interrupt(InterruptKind::Reset, pc));
+    }
+
+    fn step_over(&mut self) -> VmResult<StopReason> {
+        let pc = self.registers.pc;
+        let sp = self.registers.sp;
+        self.resume_here();
+
+        if self.flatmap.peek(pc) != 0x20 {
+            return Ok(self.debug_step()?.unwrap_or(StopReason::StepComplete));
+        }
+
+        // The matching RTS returns past the JSR with the stack as it was.
+        let ret = pc.wrapping_add(3);
+        self.run_while(|vm| {
+            (vm.registers.pc == ret && vm.registers.sp == sp).then_some(StopReason::StepComplete)
+        })
+    }
+
+    fn step_out(&mut self) -> VmResult<StopReason> {
+        let sp = self.registers.sp;
+        self.resume_here();
+
+        // Returning from this frame pulls the stack above where it is now.
+        self.run_while(|vm| {
+            (vm.opcode == 0x60 && vm.registers.sp > sp).then_some(StopReason::StepComplete)
+        })
+    }
+
+    fn run_until_pc(&mut self, pc: u16) -> VmResult<StopReason> {
+        self.run_while(|vm| (vm.registers.pc == pc).then_some(StopReason::ReachedPc(pc)))
+    }
+
+    fn run_for_instructions(&mut self, count: u64) -> VmResult<StopReason> {
+        if count == 0 {
+            return Ok(StopReason::InstructionLimit);
+        }
+
+        let mut executed = 0;
+        self.run_while(|_| {
+            executed += 1;
+            (executed >= count).then_some(StopReason::InstructionLimit)
+        })
+    }
+
+    fn run_for_cycles(&mut self, cycles: u64) -> VmResult<StopReason> {
+        if cycles == 0 {
+            return Ok(StopReason::CycleLimit);
+        }
+
+        let end = self.cycles.saturating_add(cycles);
+        self.run_while(|vm| (vm.cycles >= end).then_some(StopReason::CycleLimit))
+    }
+
+    fn run_until<F: FnMut(&Self) -> bool>(&mut self, mut predicate: F) -> VmResult<StopReason> {
+        self.run_while(|vm| predicate(vm).then_some(StopReason::Predicate))
     }
 
     /// Resets the total machine state.
-    fn reset(&mut self) {
-        self.flatmap.iter_mut().for_each(|m| {
-            *m = 0;
-        });
+    fn power_on(&mut self) {
+        for address in 0..=u16::MAX {
+            self.flatmap.write(address, 0);
+        }
 
         self.registers = Registers::new();
         self.cycles = 0;
         self.halted = false;
+        self.jammed = false;
+        self.irq = false;
+        self.nmi = false;
+        self.nmi_pending = false;
+        self.clear_history();
     }
 
     // TODO: move to helpers? macro?
     /// Fill the stack with ops.
-    fn fill_stack(&mut self, ops: Vec<u8>) {
+    fn fill_stack(&mut self, ops: Vec<u8>) -> VmResult<()> {
         for (i, byte) in ops.iter().enumerate() {
             if i > 0xFF {
                 break;
             };
 
             self.registers.ac = *byte;
-            self.push(self.registers.ac);
+            self.push(self.registers.ac)?;
+        }
+
+        Ok(())
+    }
+
+    fn save_snapshot(&self, path: &str) -> VmResult<()> {
+        std::fs::write(path, self.snapshot())
+            .map_err(|e| self.io_error(format!("failed to write {}: {}", path, e)))
+    }
+
+    fn load_snapshot(&mut self, path: &str) -> VmResult<()> {
+        let snapshot = std::fs::read(path)
+            .map_err(|e| self.io_error(format!("failed to read {}: {}", path, e)))?;
+
+        self.restore(&snapshot)
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// The address of heap offset `offset`.
+    fn heap_address(&self, offset: u16) -> VmResult<u16> {
+        let address = offset as usize + self.heap_bounds.0;
+
+        u16::try_from(address).map_err(|_| self.out_of_bounds(address))
+    }
+
+    /// Read the record file at `path` and install its image.
+    fn load_records(
+        &mut self,
+        path: &str,
+        parse: fn(&str) -> Result<ProgramImage, LoadError>,
+    ) -> VmResult<()> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| self.io_error(format!("failed to read {}: {}", path, e)))?;
+        let image = parse(&text).map_err(|e| self.malformed_program(format!("{}: {}", path, e)))?;
+
+        self.load_image(&image)
+    }
+
+    /// Read the binary file at `path` and install its image.
+    fn load_binary(
+        &mut self,
+        path: &str,
+        parse: impl FnOnce(&[u8]) -> Result<ProgramImage, LoadError>,
+    ) -> VmResult<ProgramImage> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| self.io_error(format!("failed to read {}: {}", path, e)))?;
+        let image =
+            parse(&bytes).map_err(|e| self.malformed_program(format!("{}: {}", path, e)))?;
+
+        self.load_image(&image)?;
+        Ok(image)
+    }
+
+    /// Write memory `start..=end` to `path` with `write`.
+    fn save_records(
+        &self,
+        path: &str,
+        start: u16,
+        end: u16,
+        write: fn(u16, &[u8], Option<u16>) -> String,
+    ) -> VmResult<()> {
+        let bytes = (start..=end)
+            .map(|address| self.flatmap.peek(address))
+            .collect::<Vec<u8>>();
+
+        std::fs::write(path, write(start, &bytes, Some(self.registers.pc)))
+            .map_err(|e| self.io_error(format!("failed to write {}: {}", path, e)))
+    }
+
+    /// Step until `done` returns a stop reason after an instruction, or the debugger stops.
+    fn run_while(
+        &mut self,
+        mut done: impl FnMut(&Self) -> Option<StopReason>,
+    ) -> VmResult<StopReason> {
+        loop {
+            if let Some(reason) = self.debug_step()? {
+                return Ok(reason);
+            }
+
+            if let Some(reason) = done(self) {
+                return Ok(reason);
+            }
         }
     }
 }
@@ -0,0 +1,412 @@
+//! Headless runner for testing 6502 programs in CI, used by `vm6502 run`.
+//!
+//! Loads binaries with [load_image](ProgramController::load_image), runs them under an
+//! instruction or cycle limit and checks [Expectation]s against the final state.
+//!
+//! ```text
+//! vm6502 run [options] file[@addr]...
+//!     file[@addr]             load a raw binary at addr (default $0200)
+//!     --entry addr            start at addr (default the first file's address)
+//!     --reset                 start from the reset vector instead
+//!     --max-instructions n    stop after n instructions (default 10000000)
+//!     --max-cycles n          stop after n cycles
+//!     --stop-at addr          stop when the PC reaches addr
+//!     --undocumented          execute the undocumented NMOS opcodes
+//!     --expect target=value   check A, X, Y, SP, P, PC, CYCLES, a flag N V D I Z C or mem[addr]
+//! ```
+//!
+//! Addresses are hex, with an optional `$` or `0x` prefix. Expected values are decimal, or hex
+//! with a `$` or `0x` prefix.
+//!
+//! # Example
+//! ```no_run
+//! use vm6502::prelude::*;
+//!
+//! let args = ["test.bin@$0200", "--expect", "A=$42", "--expect", "mem[$0300]=$FF"];
+//! let runner = Runner::from_args(&args.map(String::from)).unwrap();
+//!
+//! let report = runner.run().unwrap();
+//! # #[cfg(feature = "json")]
+//! println!("{}", report.to_json());
+//! assert!(report.passed());
+//! ```
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+#[cfg(feature = "json")]
+use serde_json::{json, Value};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::runner::{Entry, Expectation, Failure, Report, Runner, Target};
+}
+
+/// The instruction limit when neither limit is given, so a stuck program can't hang CI.
+pub const DEFAULT_MAX_INSTRUCTIONS: u64 = 10_000_000;
+
+/// What an [Expectation] checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Register(Register),
+    Pc,
+    Cycles,
+    Flag(Status),
+    Memory(u16),
+}
+
+/// An assertion on the final state, written as `target=value`.
+///
+/// Ex. `A=$42`, `PC=$0207`, `C=1` or `mem[$0300]=255`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expectation {
+    pub target: Target,
+    pub value: u64,
+}
+
+impl FromStr for Expectation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid expectation: {}", s);
+        let (target, value) = s.split_once('=').ok_or_else(invalid)?;
+
+        let target = match target.trim().to_ascii_uppercase().as_str() {
+            "A" | "AC" => Target::Register(Register::Ac),
+            "X" => Target::Register(Register::X),
+            "Y" => Target::Register(Register::Y),
+            "SP" => Target::Register(Register::Sp),
+            "P" | "SR" => Target::Register(Register::Sr),
+            "PC" => Target::Pc,
+            "CYCLES" => Target::Cycles,
+            "N" => Target::Flag(Status::Negative),
+            "V" => Target::Flag(Status::Overflow),
+            "D" => Target::Flag(Status::Decimal),
+            "I" => Target::Flag(Status::Interrupt),
+            "Z" => Target::Flag(Status::Zero),
+            "C" => Target::Flag(Status::Carry),
+            target => {
+                let address = target
+                    .strip_prefix("MEM[")
+                    .and_then(|t| t.strip_suffix(']'))
+                    .ok_or_else(invalid)?;
+                Target::Memory(parse_address(address).ok_or_else(invalid)?)
+            }
+        };
+        let value = parse_value(value.trim()).ok_or_else(invalid)?;
+
+        let max = match target {
+            Target::Register(_) | Target::Memory(_) => 0xFF,
+            Target::Pc => 0xFFFF,
+            Target::Flag(_) => 1,
+            Target::Cycles => u64::MAX,
+        };
+        if value > max {
+            return Err(invalid());
+        }
+
+        Ok(Expectation { target, value })
+    }
+}
+
+impl Display for Expectation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.target {
+            Target::Register(register) => {
+                let name = match register {
+                    Register::Ac => "A",
+                    Register::X => "X",
+                    Register::Y => "Y",
+                    Register::Sp => "SP",
+                    Register::Sr => "P",
+                };
+                write!(f, "{}=${:02X}", name, self.value)
+            }
+            Target::Pc => write!(f, "PC=${:04X}", self.value),
+            Target::Cycles => write!(f, "CYCLES={}", self.value),
+            Target::Flag(flag) => {
+                // The letters of NV-BDIZC, as parsed by from_str.
+                let name = match flag {
+                    Status::Negative => "N",
+                    Status::Overflow => "V",
+                    Status::Unused => "-",
+                    Status::Break => "B",
+                    Status::Decimal => "D",
+                    Status::Interrupt => "I",
+                    Status::Zero => "Z",
+                    Status::Carry => "C",
+                };
+                write!(f, "{}={}", name, self.value)
+            }
+            Target::Memory(address) => write!(f, "mem[${:04X}]=${:02X}", address, self.value),
+        }
+    }
+}
+
+impl Expectation {
+    /// The current value of the target in `vm`.
+    pub fn actual<B: Bus>(&self, vm: &VirtualMachine<B>) -> u64 {
+        let r = &vm.registers;
+
+        match self.target {
+            Target::Register(Register::Ac) => r.ac as u64,
+            Target::Register(Register::X) => r.x as u64,
+            Target::Register(Register::Y) => r.y as u64,
+            Target::Register(Register::Sp) => r.sp as u64,
+            Target::Register(Register::Sr) => r.sr as u64,
+            Target::Pc => r.pc as u64,
+            Target::Cycles => vm.cycles,
+            Target::Flag(flag) => vm.get_status(flag) as u64,
+            Target::Memory(address) => vm.flatmap.peek(address) as u64,
+        }
+    }
+}
+
+/// Where execution starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entry {
+    /// The address of the first loaded file.
+    FirstLoad,
+    Pc(u16),
+    /// The reset vector, through [reset](ProgramController::reset).
+    Reset,
+}
+
+/// A headless run: what to load, how long to run and what to check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Runner {
+    /// Files and the addresses to load them at.
+    pub loads: Vec<(String, u16)>,
+    pub entry: Entry,
+    pub max_instructions: Option<u64>,
+    pub max_cycles: Option<u64>,
+    pub stop_at: Option<u16>,
+    /// Execute the undocumented NMOS opcodes, see [VirtualMachine::undocumented].
+    pub undocumented: bool,
+    pub expectations: Vec<Expectation>,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Runner {
+            loads: Vec::new(),
+            entry: Entry::FirstLoad,
+            max_instructions: None,
+            max_cycles: None,
+            stop_at: None,
+            undocumented: false,
+            expectations: Vec::new(),
+        }
+    }
+}
+
+/// An [Expectation] that didn't hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Failure {
+    pub expectation: Expectation,
+    pub actual: u64,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let actual = Expectation {
+            value: self.actual,
+            ..self.expectation
+        };
+        write!(f, "expected {}, got {}", self.expectation, actual)
+    }
+}
+
+/// The outcome of a [Runner::run].
+#[derive(Debug)]
+pub struct Report {
+    /// The machine as it was when the run stopped.
+    pub vm: VirtualMachine,
+    /// Why the run stopped, or the fault that stopped it.
+    pub stop: VmResult<StopReason>,
+    pub instructions: u64,
+    pub failures: Vec<Failure>,
+}
+
+impl Runner {
+    /// Parse the arguments following `vm6502 run`.
+    pub fn from_args(args: &[String]) -> std::result::Result<Self, String> {
+        let mut runner = Runner::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value =
+                |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+            let address = |value: &str| {
+                parse_address(value).ok_or_else(|| format!("invalid address: {}", value))
+            };
+            let count =
+                |value: &str| parse_value(value).ok_or_else(|| format!("invalid count: {}", value));
+
+            match arg.as_str() {
+                "--entry" => runner.entry = Entry::Pc(address(value(arg)?)?),
+                "--reset" => runner.entry = Entry::Reset,
+                "--max-instructions" => runner.max_instructions = Some(count(value(arg)?)?),
+                "--max-cycles" => runner.max_cycles = Some(count(value(arg)?)?),
+                "--stop-at" => runner.stop_at = Some(address(value(arg)?)?),
+                "--undocumented" => runner.undocumented = true,
+                "--expect" => runner.expectations.push(value(arg)?.parse()?),
+                option if option.starts_with("--") => {
+                    return Err(format!("unknown option: {}", option))
+                }
+                file => {
+                    let (path, at) = match file.rsplit_once('@') {
+                        Some((path, at)) => (path, address(at)?),
+                        None => (file, 0x0200),
+                    };
+                    runner.loads.push((path.to_string(), at));
+                }
+            }
+        }
+
+        if runner.loads.is_empty() {
+            return Err("no files to run".to_string());
+        }
+
+        Ok(runner)
+    }
+
+    /// Load, run and check the expectations.
+    ///
+    /// Every file is a [Segment] of one [ProgramImage], so files can go anywhere in the 64K
+    /// address space, including the zero page and the vectors, as long as they don't overlap.
+    ///
+    /// Fails if a file can't be loaded. A fault while running is reported in [Report::stop].
+    pub fn run(&self) -> VmResult<Report> {
+        let mut vm = VirtualMachine::new();
+        vm.undocumented |= self.undocumented;
+
+        let mut image = ProgramImage::new();
+        for (path, address) in &self.loads {
+            let bytes = std::fs::read(path)
+                .map_err(|e| vm.io_error(format!("failed to read {}: {}", path, e)))?;
+            image.segments.push(Segment {
+                address: *address,
+                bytes,
+            });
+        }
+        vm.load_image(&image)?;
+
+        match self.entry {
+            Entry::FirstLoad => vm.registers.pc = self.loads[0].1,
+            Entry::Pc(pc) => vm.registers.pc = pc,
+            Entry::Reset => vm.reset(),
+        }
+
+        let max_instructions = match (self.max_instructions, self.max_cycles) {
+            (None, None) => Some(DEFAULT_MAX_INSTRUCTIONS),
+            (limit, _) => limit,
+        };
+        let max_cycles = self.max_cycles.map(|c| vm.cycles.saturating_add(c));
+
+        let mut instructions = 0;
+        let mut limit = None;
+        let stop = vm
+            .run_until(|vm| {
+                instructions += 1;
+                limit = if Some(vm.registers.pc) == self.stop_at {
+                    Some(StopReason::ReachedPc(vm.registers.pc))
+                } else if max_instructions.is_some_and(|max| instructions >= max) {
+                    Some(StopReason::InstructionLimit)
+                } else if max_cycles.is_some_and(|max| vm.cycles >= max) {
+                    Some(StopReason::CycleLimit)
+                } else {
+                    None
+                };
+                limit.is_some()
+            })
+            .map(|stop| limit.unwrap_or(stop));
+
+        let failures = self
+            .expectations
+            .iter()
+            .filter_map(|expectation| {
+                let actual = expectation.actual(&vm);
+                (actual != expectation.value).then_some(Failure {
+                    expectation: *expectation,
+                    actual,
+                })
+            })
+            .collect();
+
+        Ok(Report {
+            vm,
+            stop,
+            instructions,
+            failures,
+        })
+    }
+}
+
+impl Report {
+    /// Whether the run ended without a fault and every expectation held.
+    pub fn passed(&self) -> bool {
+        self.stop.is_ok() && self.failures.is_empty()
+    }
+
+    /// The final registers, why the run stopped and any failures.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Value {
+        let r = &self.vm.registers;
+
+        let stop = match &self.stop {
+            Ok(StopReason::Halted) => "halted".to_string(),
+            Ok(StopReason::Jammed { pc }) => format!("jammed at ${:04X}", pc),
+            Ok(StopReason::ReachedPc(_)) => "stop address".to_string(),
+            Ok(StopReason::InstructionLimit) => "instruction limit".to_string(),
+            Ok(StopReason::CycleLimit) => "cycle limit".to_string(),
+            Ok(reason) => format!("{:?}", reason),
+            Err(e) => e.to_string(),
+        };
+
+        let failures = self
+            .failures
+            .iter()
+            .map(|f| json!({ "expected": f.expectation.to_string(), "actual": f.actual }))
+            .collect::<Vec<Value>>();
+
+        json!({
+            "passed": self.passed(),
+            "stop": stop,
+            "registers": {
+                "pc": r.pc,
+                "a": r.ac,
+                "x": r.x,
+                "y": r.y,
+                "sp": r.sp,
+                "p": r.sr,
+            },
+            "cycles": self.vm.cycles,
+            "instructions": self.instructions,
+            "failures": failures,
+        })
+    }
+}
+
+/// Parse a hex address, with an optional `$` or `0x` prefix.
+fn parse_address(s: &str) -> Option<u16> {
+    let hex = s
+        .strip_prefix('$')
+        .or_else(|| s.strip_prefix("0x"))
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// Parse a decimal value, or hex with a `$` or `0x` prefix.
+fn parse_value(s: &str) -> Option<u64> {
+    match s
+        .strip_prefix('$')
+        .or_else(|| s.strip_prefix("0x"))
+        .or_else(|| s.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
@@ -0,0 +1,487 @@
+//! Symbol tables: names for addresses, and the source lines they were assembled from.
+//!
+//! Symbols are imported from
+//!
+//! - VICE label files (`al C:1234 .name`), as written by VICE and ld65's `-Ln`, with
+//!   [parse_vice_labels];
+//! - ld65 `.dbg` debug info, which also maps addresses to source lines, with [parse_dbg];
+//! - simple `name = $1234` lists, with [parse_symbol_list].
+//!
+//! The [VirtualMachine]'s table labels [traces](crate::trace) and
+//! [disassembly](crate::disassembler::Instruction::labelled), names the PC as `func+3`
+//! and resolves [breakpoints by name](SymbolInterface::add_symbol_breakpoint).
+//!
+//! # Example
+//! ```
+//! use vm6502::prelude::*;
+//!
+//! let mut vm = VirtualMachine::new();
+//! vm.symbols = parse_symbol_list("main = $0200\nloop = $0202").unwrap();
+//! // LDX #$05, DEX, BNE loop, BRK
+//! vm.set_program(0x0000, "A205CAD0FD00").unwrap();
+//!
+//! assert_eq!(vm.trace_line().text, "LDX #$05");
+//! vm.add_symbol_breakpoint("loop+1").unwrap();
+//! vm.execute().unwrap();
+//! assert_eq!(vm.symbols.describe(vm.registers.pc), "loop+1");
+//! assert_eq!(vm.trace_line().text, "BNE loop");
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Result};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::symbols::{
+        parse_dbg, parse_symbol_list, parse_symbols, parse_vice_labels, SourceLine,
+        SymbolInterface, SymbolTable,
+    };
+}
+
+/// How far past a symbol an address is still described relative to it.
+const MAX_OFFSET: u16 = 0xFF;
+
+/// A line of a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    /// The (1-based) line number.
+    pub line: usize,
+}
+
+/// Formats as `file:line`.
+impl Display for SourceLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Names for addresses, and the source lines code was assembled from.
+///
+/// An address can have several names; the first one given is used to label it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    addresses: HashMap<String, u16>,
+    /// The label of each named address.
+    names: BTreeMap<u16, String>,
+    /// The length and source line of each assembled range, by start address.
+    lines: BTreeMap<u16, (u16, SourceLine)>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable::default()
+    }
+
+    /// Name `address`, replacing any address the name had.
+    ///
+    /// If the name labelled its old address, the alphabetically first of that address's
+    /// other names labels it instead.
+    pub fn insert(&mut self, name: impl Into<String>, address: u16) {
+        let name = name.into();
+        if let Some(old) = self.addresses.insert(name.clone(), address) {
+            if old != address && self.names.get(&old) == Some(&name) {
+                let alias = self
+                    .addresses
+                    .iter()
+                    .filter(|(_, a)| **a == old)
+                    .map(|(n, _)| n)
+                    .min()
+                    .cloned();
+                match alias {
+                    Some(alias) => self.names.insert(old, alias),
+                    None => self.names.remove(&old),
+                };
+            }
+        }
+
+        self.names.entry(address).or_insert(name);
+    }
+
+    /// Map `len` bytes from `address` to `line`.
+    pub fn insert_line(&mut self, address: u16, len: u16, line: SourceLine) {
+        self.lines.entry(address).or_insert((len, line));
+    }
+
+    /// The address of `name`.
+    pub fn get(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// The label of `address`.
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// The nearest label at or before `address`, with the offset from it.
+    pub fn locate(&self, address: u16) -> Option<(&str, u16)> {
+        let (at, name) = self.names.range(..=address).next_back()?;
+        let offset = address - at;
+
+        (offset <= MAX_OFFSET).then_some((name.as_str(), offset))
+    }
+
+    /// Describe `address` as `name`, `name+3` or `$1234`.
+    pub fn describe(&self, address: u16) -> String {
+        match self.locate(address) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{}+{}", name, offset),
+            None => format!("${:04X}", address),
+        }
+    }
+
+    /// Resolve `name` or `name+offset`, with a decimal or `$` hex offset.
+    pub fn resolve(&self, expression: &str) -> Option<u16> {
+        let (name, offset) = match expression.split_once('+') {
+            Some((name, offset)) => {
+                let offset = match offset.strip_prefix('$') {
+                    Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+                    None => offset.parse().ok()?,
+                };
+                (name, offset)
+            }
+            None => (expression, 0),
+        };
+
+        Some(self.get(name.trim())?.wrapping_add(offset))
+    }
+
+    /// The source line `address` was assembled from.
+    pub fn source_line(&self, address: u16) -> Option<&SourceLine> {
+        let (start, (len, line)) = self.lines.range(..=address).next_back()?;
+
+        ((address - start) < *len).then_some(line)
+    }
+
+    /// The first address assembled from `line` of `file`.
+    ///
+    /// Files match when one path ends with the other, as the debug info may hold relative paths.
+    pub fn line_address(&self, file: &str, line: usize) -> Option<u16> {
+        let same = |a: &str, b: &str| a == b || a.ends_with(&format!("/{}", b));
+
+        self.lines
+            .iter()
+            .find(|(_, (_, l))| l.line == line && (same(&l.file, file) || same(file, &l.file)))
+            .map(|(address, _)| *address)
+    }
+
+    /// Every name and its address, in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u16)> {
+        let mut symbols = self
+            .addresses
+            .iter()
+            .map(|(name, address)| (name.as_str(), *address))
+            .collect::<Vec<(&str, u16)>>();
+        symbols.sort_by_key(|(name, address)| (*address, *name));
+
+        symbols.into_iter()
+    }
+
+    /// The number of names.
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Whether there are no names. Like [len](SymbolTable::len), this ignores source lines.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Add every name and source line of `other`.
+    pub fn merge(&mut self, other: &SymbolTable) {
+        for (name, address) in other.iter() {
+            self.insert(name, address);
+        }
+        for (address, (len, line)) in &other.lines {
+            self.insert_line(*address, *len, line.clone());
+        }
+    }
+}
+
+impl Extend<(String, u16)> for SymbolTable {
+    fn extend<T: IntoIterator<Item = (String, u16)>>(&mut self, symbols: T) {
+        for (name, address) in symbols {
+            self.insert(name, address);
+        }
+    }
+}
+
+impl FromIterator<(String, u16)> for SymbolTable {
+    fn from_iter<T: IntoIterator<Item = (String, u16)>>(symbols: T) -> Self {
+        let mut table = SymbolTable::new();
+        table.extend(symbols);
+        table
+    }
+}
+
+fn syntax(line: usize, message: impl Into<String>) -> LoadError {
+    LoadError {
+        position: Position::Line(line),
+        kind: LoadErrorKind::Syntax(message.into()),
+    }
+}
+
+/// Parse an address written as `$1234`, `0x1234` or decimal.
+fn parse_number(text: &str) -> Option<u32> {
+    match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Parse a VICE label file, with lines of `al C:1234 .name`.
+///
+/// The `C:` memory space is optional, as ld65 leaves it out, and labels of drive memory
+/// spaces are skipped.
+pub fn parse_vice_labels(text: &str) -> std::result::Result<SymbolTable, LoadError> {
+    let mut table = SymbolTable::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        let (address, name) = match fields[..] {
+            [] => continue,
+            ["al", address, name] => (address, name),
+            [command, ..] => {
+                return Err(LoadError {
+                    position: Position::Line(n),
+                    kind: LoadErrorKind::UnsupportedRecord(command.to_string()),
+                })
+            }
+        };
+
+        let address = match address.split_once(':') {
+            Some(("C" | "c", address)) => address,
+            Some(_) => continue,
+            None => address,
+        };
+        let address = u16::from_str_radix(address, 16)
+            .map_err(|_| syntax(n, format!("invalid address {}", address)))?;
+
+        table.insert(name.trim_start_matches('.'), address);
+    }
+
+    Ok(table)
+}
+
+/// Parse a list of `name = $1234` lines, where `;` starts a comment.
+///
+/// Addresses may also be written as `0x1234` or in decimal.
+pub fn parse_symbol_list(text: &str) -> std::result::Result<SymbolTable, LoadError> {
+    let mut table = SymbolTable::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| syntax(n, "expected name = address"))?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(syntax(n, format!("invalid name {}", name)));
+        }
+
+        let address =
+            parse_number(value).ok_or_else(|| syntax(n, format!("invalid address {}", value)))?;
+        let address = u16::try_from(address).map_err(|_| LoadError {
+            position: Position::Line(n),
+            kind: LoadErrorKind::AddressOutOfRange(address),
+        })?;
+
+        table.insert(name, address);
+    }
+
+    Ok(table)
+}
+
+/// The `key=value` fields of a `.dbg` record, with quotes removed from strings.
+fn dbg_fields(line: usize, text: &str) -> std::result::Result<HashMap<String, String>, LoadError> {
+    let mut fields = HashMap::new();
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        let (key, tail) = rest
+            .split_once('=')
+            .ok_or_else(|| syntax(line, format!("expected key=value in {}", rest)))?;
+
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted
+                    .find('"')
+                    .ok_or_else(|| syntax(line, "unterminated string"))?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => tail.split_at(tail.find(',').unwrap_or(tail.len())),
+        };
+
+        fields.insert(key.trim().to_string(), value.to_string());
+        rest = tail.trim_start_matches(',');
+    }
+
+    Ok(fields)
+}
+
+/// Parse ld65 `.dbg` debug info.
+///
+/// Labels are named by their scope, as `name` or `scope::name`, and cheap local labels are
+/// skipped. Assembler and C source lines are mapped to the addresses they were assembled to,
+/// while lines from macros are left out in favor of the line invoking the macro.
+pub fn parse_dbg(text: &str) -> std::result::Result<SymbolTable, LoadError> {
+    let mut files = HashMap::new();
+    let mut segments = HashMap::new();
+    let mut spans = HashMap::new();
+    let mut scopes = HashMap::new();
+    let mut lines = vec![];
+    let mut symbols = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        let Some((kind, fields)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let fields = dbg_fields(n, fields)?;
+        let field = |key: &str| fields.get(key).map(String::as_str);
+        let number = |key: &str| {
+            let value = field(key).ok_or_else(|| syntax(n, format!("missing {}", key)))?;
+            parse_number(value).ok_or_else(|| syntax(n, format!("invalid {} {}", key, value)))
+        };
+
+        match kind {
+            "version" => {
+                if number("major")? != 2 {
+                    return Err(LoadError {
+                        position: Position::Line(n),
+                        kind: LoadErrorKind::UnsupportedRecord(line.trim().to_string()),
+                    });
+                }
+            }
+            "file" => {
+                files.insert(number("id")?, field("name").unwrap_or_default().to_string());
+            }
+            "seg" => {
+                segments.insert(number("id")?, number("start")?);
+            }
+            "span" => {
+                spans.insert(
+                    number("id")?,
+                    (number("seg")?, number("start")?, number("size")?),
+                );
+            }
+            "scope" => {
+                let parent = field("parent").and_then(parse_number);
+                let name = field("name").unwrap_or_default().to_string();
+                scopes.insert(number("id")?, (name, parent));
+            }
+            // Type 2 is a line of a macro.
+            "line" if field("type") != Some("2") => {
+                if let Some(span) = field("span") {
+                    lines.push((n, number("file")?, number("line")?, span.to_string()));
+                }
+            }
+            "sym" if field("type") == Some("lab") && field("parent").is_none() => {
+                let scope = field("scope").and_then(parse_number);
+                let name = field("name").unwrap_or_default().to_string();
+                symbols.push((n, name, scope, number("val")?));
+            }
+            _ => {}
+        }
+    }
+
+    let mut table = SymbolTable::new();
+    let address = |n: usize, value: u32| {
+        u16::try_from(value).map_err(|_| LoadError {
+            position: Position::Line(n),
+            kind: LoadErrorKind::AddressOutOfRange(value),
+        })
+    };
+
+    for (n, name, scope, value) in symbols {
+        let mut path = vec![name];
+        let mut scope = scope;
+        while let Some((name, parent)) = scope.and_then(|id| scopes.get(&id)) {
+            if !name.is_empty() {
+                path.push(name.clone());
+            }
+            scope = *parent;
+        }
+        path.reverse();
+
+        table.insert(path.join("::"), address(n, value)?);
+    }
+
+    for (n, file, line, span_ids) in lines {
+        let file = files
+            .get(&file)
+            .ok_or_else(|| syntax(n, format!("unknown file {}", file)))?;
+
+        for id in span_ids.split('+') {
+            let id = parse_number(id).ok_or_else(|| syntax(n, format!("invalid span {}", id)))?;
+            let (segment, start, size) = spans
+                .get(&id)
+                .ok_or_else(|| syntax(n, format!("unknown span {}", id)))?;
+            let base = segments
+                .get(segment)
+                .ok_or_else(|| syntax(n, format!("unknown segment {}", segment)))?;
+
+            let len = u16::try_from(*size).map_err(|_| syntax(n, "span is too large"))?;
+            if len > 0 {
+                table.insert_line(
+                    address(n, base + start)?,
+                    len,
+                    SourceLine {
+                        file: file.clone(),
+                        line: line as usize,
+                    },
+                );
+            }
+        }
+    }
+
+    Ok(table)
+}
+
+/// Parse a `.dbg` file, VICE label file or symbol list, telling them apart by their first line.
+pub fn parse_symbols(text: &str) -> std::result::Result<SymbolTable, LoadError> {
+    let first = text.lines().map(str::trim).find(|l| !l.is_empty());
+
+    match first {
+        Some(line) if line.starts_with("version") => parse_dbg(text),
+        Some(line) if line.starts_with("al ") => parse_vice_labels(text),
+        _ => parse_symbol_list(text),
+    }
+}
+
+/// Symbol names for the virtual machine.
+pub trait SymbolInterface {
+    /// Import the symbols of a `.dbg` file, VICE label file or symbol list at `path`,
+    /// returning how many names it had.
+    ///
+    /// Fails with [VmError::Io] if the file can't be read, and [VmError::MalformedProgram]
+    /// if it can't be parsed.
+    fn load_symbols(&mut self, path: &str) -> VmResult<usize>;
+    /// Add a breakpoint at `name` or `name+offset`, returning its id or `None` if the
+    /// name isn't known.
+    fn add_symbol_breakpoint(&mut self, name: &str) -> Option<usize>;
+}
+
+impl<B: Bus> SymbolInterface for VirtualMachine<B> {
+    fn load_symbols(&mut self, path: &str) -> VmResult<usize> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| self.io_error(format!("failed to read {}: {}", path, e)))?;
+        let symbols =
+            parse_symbols(&text).map_err(|e| self.malformed_program(format!("{}: {}", path, e)))?;
+
+        self.symbols.merge(&symbols);
+        Ok(symbols.len())
+    }
+
+    fn add_symbol_breakpoint(&mut self, name: &str) -> Option<usize> {
+        let address = self.symbols.resolve(name)?;
+
+        Some(self.add_breakpoint(Breakpoint::Pc(address)))
+    }
+}
@@ -0,0 +1,271 @@
+//! Per-instruction execution traces in the nestest.log layout, and trace diffing.
+//!
+//! Each [TraceLine] describes the machine state before an instruction executes:
+//!
+//! ```text
+//! C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:7
+//! ```
+//!
+//! The PPU column of NES traces is not produced, and is skipped when parsing, so logs
+//! from other emulators can be compared with [diff_traces].
+//!
+//! # Example
+//! ```
+//! use vm6502::prelude::*;
+//!
+//! let mut vm = VirtualMachine::new();
+//! vm.set_program(0x0000, "A9014C0002").unwrap();
+//!
+//! let line = vm.step_traced().unwrap();
+//! assert_eq!(
+//!     line.to_string(),
+//!     "0200  A9 01     LDA #$01                        A:00 X:00 Y:00 P:20 SP:FF CYC:0"
+//! );
+//!
+//! let expected = parse_trace(&line.to_string());
+//! assert!(diff_traces(&expected, &[line]).is_none());
+//! ```
+use std::fmt::{Display, Formatter, Result};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::trace::{diff_traces, parse_trace, Trace, TraceDiff, TraceField, TraceLine};
+}
+
+/// Flags compared by [diff_traces]. B and bit 5 only exist on the stack, so they're ignored.
+const FLAGS: [(Status, u8); 6] = [
+    (Status::Negative, 0x80),
+    (Status::Overflow, 0x40),
+    (Status::Decimal, 0x08),
+    (Status::Interrupt, 0x04),
+    (Status::Zero, 0x02),
+    (Status::Carry, 0x01),
+];
+
+/// The machine state before an instruction executes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceLine {
+    pub pc: u16,
+    /// The raw instruction bytes.
+    pub bytes: Vec<u8>,
+    /// Disassembly of the instruction.
+    pub text: String,
+    pub ac: u8,
+    pub x: u8,
+    pub y: u8,
+    /// The status register as read by PHP, with bit 5 set and B clear.
+    pub p: u8,
+    pub sp: u8,
+    pub cycles: u64,
+}
+
+impl TraceLine {
+    /// Parse a nestest.log style line, returning `None` if it isn't one.
+    ///
+    /// The raw bytes are optional, and unknown columns such as `PPU:` are skipped.
+    pub fn parse(line: &str) -> Option<TraceLine> {
+        let pc = u16::from_str_radix(line.get(..4)?, 16).ok()?;
+        let registers = line.find("A:")?;
+
+        // PC, bytes and disassembly are in fixed columns before the registers.
+        let head = line.get(4..registers)?;
+        let split = head.len().min(12);
+        let (bytes, text) = (head.get(..split)?, head.get(split..)?);
+        // Undocumented opcodes are marked with a `*` just before the disassembly.
+        let mark = if bytes.ends_with('*') { "*" } else { "" };
+        let bytes = bytes
+            .split_whitespace()
+            .filter(|b| *b != "*")
+            .map(|b| u8::from_str_radix(b, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        let mut parsed = TraceLine {
+            pc,
+            bytes,
+            text: format!("{}{}", mark, text.trim()),
+            ac: 0,
+            x: 0,
+            y: 0,
+            p: 0,
+            sp: 0,
+            cycles: 0,
+        };
+
+        let mut found = 0;
+        for (key, value) in line[registers..]
+            .split_whitespace()
+            .filter_map(|field| field.split_once(':'))
+        {
+            let byte = || u8::from_str_radix(value, 16).ok();
+            match key {
+                "A" => parsed.ac = byte()?,
+                "X" => parsed.x = byte()?,
+                "Y" => parsed.y = byte()?,
+                "P" => parsed.p = byte()?,
+                "SP" => parsed.sp = byte()?,
+                "CYC" => parsed.cycles = value.parse().ok()?,
+                _ => continue,
+            }
+            found += 1;
+        }
+
+        (found == 6).then_some(parsed)
+    }
+}
+
+/// Formats the line in the nestest.log layout.
+impl Display for TraceLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let bytes = self
+            .bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<String>>()
+            .join(" ");
+        // The `*` marking an undocumented opcode hangs just before the disassembly column.
+        let (mark, text) = match self.text.strip_prefix('*') {
+            Some(text) => ("*", text),
+            None => (" ", self.text.as_str()),
+        };
+
+        write!(
+            f,
+            "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            self.pc, bytes, mark, text, self.ac, self.x, self.y, self.p, self.sp, self.cycles
+        )
+    }
+}
+
+/// Parse every trace line in `text`, skipping lines that aren't trace lines.
+pub fn parse_trace(text: &str) -> Vec<TraceLine> {
+    text.lines().filter_map(TraceLine::parse).collect()
+}
+
+/// A part of a [TraceLine] that differs between two traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceField {
+    Pc,
+    Bytes,
+    Ac,
+    X,
+    Y,
+    Sp,
+    Flag(Status),
+    Cycles,
+    /// One trace ended before the other.
+    Length,
+}
+
+/// The first instruction where two traces diverge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDiff {
+    /// Index of the diverging instruction.
+    pub index: usize,
+    pub expected: Option<TraceLine>,
+    pub actual: Option<TraceLine>,
+    /// Every field that differs.
+    pub fields: Vec<TraceField>,
+}
+
+impl Display for TraceDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(
+            f,
+            "traces diverge at instruction {}: {:?}",
+            self.index, self.fields
+        )?;
+
+        let line =
+            |l: &Option<TraceLine>| l.as_ref().map_or("<end>".to_string(), |l| l.to_string());
+        writeln!(f, "expected: {}", line(&self.expected))?;
+        write!(f, "actual:   {}", line(&self.actual))
+    }
+}
+
+/// Compare two traces, returning the first instruction that differs.
+///
+/// The disassembly text isn't compared, since emulators annotate it differently.
+/// Raw bytes are only compared when both lines have them.
+pub fn diff_traces(expected: &[TraceLine], actual: &[TraceLine]) -> Option<TraceDiff> {
+    for index in 0..expected.len().max(actual.len()) {
+        let (e, a) = match (expected.get(index), actual.get(index)) {
+            (Some(e), Some(a)) => (e, a),
+            (e, a) => {
+                return Some(TraceDiff {
+                    index,
+                    expected: e.cloned(),
+                    actual: a.cloned(),
+                    fields: vec![TraceField::Length],
+                })
+            }
+        };
+
+        let mut fields = Vec::new();
+        let mut check = |field, differs: bool| {
+            if differs {
+                fields.push(field);
+            }
+        };
+
+        check(TraceField::Pc, e.pc != a.pc);
+        check(
+            TraceField::Bytes,
+            !e.bytes.is_empty() && !a.bytes.is_empty() && e.bytes != a.bytes,
+        );
+        check(TraceField::Ac, e.ac != a.ac);
+        check(TraceField::X, e.x != a.x);
+        check(TraceField::Y, e.y != a.y);
+        check(TraceField::Sp, e.sp != a.sp);
+        for (flag, mask) in FLAGS {
+            check(TraceField::Flag(flag), (e.p ^ a.p) & mask != 0);
+        }
+        check(TraceField::Cycles, e.cycles != a.cycles);
+
+        if !fields.is_empty() {
+            return Some(TraceDiff {
+                index,
+                expected: Some(e.clone()),
+                actual: Some(a.clone()),
+                fields,
+            });
+        }
+    }
+
+    None
+}
+
+/// Tracing of the virtual machine's execution.
+pub trait Trace {
+    /// Describe the current state and the instruction at the PC, labelled with the
+    /// [symbol table](VirtualMachine::symbols).
+    fn trace_line(&self) -> TraceLine;
+    /// [Step](InstructionController::step), returning the trace line for the instruction.
+    fn step_traced(&mut self) -> VmResult<TraceLine>;
+}
+
+impl<B: Bus> Trace for VirtualMachine<B> {
+    fn trace_line(&self) -> TraceLine {
+        let instruction = self.disassemble_count(self.registers.pc, 1)[0];
+        let r = &self.registers;
+
+        TraceLine {
+            pc: r.pc,
+            bytes: instruction.bytes(),
+            text: instruction.labelled(&self.symbols),
+            ac: r.ac,
+            x: r.x,
+            y: r.y,
+            p: (r.sr | 0x20) & !0x10,
+            sp: r.sp,
+            cycles: self.cycles,
+        }
+    }
+
+    fn step_traced(&mut self) -> VmResult<TraceLine> {
+        let line = self.trace_line();
+        self.step()?;
+
+        Ok(line)
+    }
+}
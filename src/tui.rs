@@ -0,0 +1,543 @@
+//! Full-screen terminal debugger.
+//!
+//! Panes for the registers and NV-BDIZC flags, the cycle counter, the stack page, a
+//! disassembly following the PC and a hex memory view highlighting the bytes changed by
+//! the last command.
+//!
+//! [Tui] renders the screen as styled [Line]s and handles [Key]s, so it's independent of
+//! the terminal. With the `tui` feature, `run` drives it in a terminal with crossterm.
+//!
+//! | Key                  | Action                                          |
+//! |----------------------|-------------------------------------------------|
+//! | `s`, F11             | Step                                            |
+//! | `n`, F10             | Step over                                       |
+//! | `o`                  | Step out                                        |
+//! | `c`, F5              | Continue, any key pauses                        |
+//! | `b`, F9              | Toggle a breakpoint on the selected instruction |
+//! | Up, Down             | Select an instruction, Esc follows the PC again |
+//! | PageUp, PageDown     | Scroll the memory view by a page                |
+//! | `-`, `+`             | Scroll the memory view by a line                |
+//! | `q`                  | Quit                                            |
+use std::fmt::{Display, Formatter, Result};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    #[cfg(feature = "tui")]
+    pub use crate::tui::run as run_tui;
+    pub use crate::tui::{Action, Key, Line, Span, Style, Tui};
+}
+
+/// Width of the registers and stack column.
+const LEFT_WIDTH: usize = 30;
+
+/// Instructions a continue runs before checking for a key press.
+pub const RUN_SLICE: usize = 10_000;
+
+/// A key press, independent of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    F(u8),
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Esc,
+}
+
+/// What the frontend should do after a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Redraw,
+    Quit,
+}
+
+/// How a [Span] is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Normal,
+    /// A pane title.
+    Title,
+    /// The instruction at the PC, or the stack pointer.
+    Current,
+    /// The instruction selected with Up and Down.
+    Selected,
+    /// An instruction with a breakpoint.
+    Breakpoint,
+    /// A byte changed by the last command.
+    Changed,
+}
+
+/// Styled text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// A row of the screen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line(pub Vec<Span>);
+
+impl Line {
+    fn push(&mut self, text: impl Into<String>, style: Style) {
+        self.0.push(Span {
+            text: text.into(),
+            style,
+        });
+    }
+
+    /// Width in characters.
+    pub fn width(&self) -> usize {
+        self.0.iter().map(|s| s.text.chars().count()).sum()
+    }
+
+    /// Pad with spaces, or truncate, to exactly `width` characters.
+    fn fit(mut self, width: usize) -> Self {
+        let mut remaining = width;
+        for span in self.0.iter_mut() {
+            let len = span.text.chars().count();
+            if len > remaining {
+                span.text = span.text.chars().take(remaining).collect();
+            }
+            remaining -= span.text.chars().count();
+        }
+        self.0.retain(|s| !s.text.is_empty());
+        self.push(" ".repeat(remaining), Style::Normal);
+        self
+    }
+}
+
+/// The plain text, without styles.
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for span in &self.0 {
+            write!(f, "{}", span.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Terminal debugger state.
+#[derive(Debug, Clone)]
+pub struct Tui {
+    /// Start of the memory view.
+    pub memory: u16,
+    /// The selected instruction, or `None` to follow the PC.
+    selected: Option<u16>,
+    /// Memory before the last command, to highlight changes.
+    previous: Vec<u8>,
+    /// Whether a continue is in progress.
+    running: bool,
+    /// Why execution last stopped.
+    status: String,
+}
+
+impl Default for Tui {
+    fn default() -> Self {
+        Tui {
+            memory: 0x0200,
+            selected: None,
+            previous: Vec::new(),
+            running: false,
+            status: String::new(),
+        }
+    }
+}
+
+impl Tui {
+    pub fn new() -> Self {
+        Tui::default()
+    }
+
+    /// Whether a continue is in progress, see [run_slice](Tui::run_slice).
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    /// Handle a key press. While running, any key pauses.
+    pub fn handle_key<B: Bus>(&mut self, vm: &mut VirtualMachine<B>, key: Key) -> Action {
+        if self.running {
+            self.running = false;
+            self.status = "paused".to_string();
+            return Action::Redraw;
+        }
+
+        match key {
+            Key::Char('q') => return Action::Quit,
+            Key::Char('s') | Key::F(11) => self.execute(vm, |vm| {
+                vm.resume_here();
+                vm.run_for_instructions(1)
+            }),
+            Key::Char('n') | Key::F(10) => self.execute(vm, |vm| vm.step_over()),
+            Key::Char('o') => self.execute(vm, |vm| vm.step_out()),
+            Key::Char('c') | Key::F(5) => {
+                self.snapshot(vm);
+                self.selected = None;
+                self.running = true;
+                self.status = "running".to_string();
+                vm.resume_here();
+            }
+            Key::Char('b') | Key::F(9) => {
+                let address = self.selected.unwrap_or(vm.registers.pc);
+                self.toggle_breakpoint(vm, address);
+            }
+            Key::Up => {
+                let focus = self.selected.unwrap_or(vm.registers.pc);
+                let previous = vm.disassemble_around(focus, 1, 0);
+                self.selected = Some(previous.first().map_or(focus, |i| i.address));
+            }
+            Key::Down => {
+                let focus = self.selected.unwrap_or(vm.registers.pc);
+                self.selected = Some(vm.disassemble_count(focus, 1)[0].next());
+            }
+            Key::Esc => self.selected = None,
+            Key::PageUp => self.memory = self.memory.wrapping_sub(0x100),
+            Key::PageDown => self.memory = self.memory.wrapping_add(0x100),
+            Key::Char('-') => self.memory = self.memory.wrapping_sub(0x10),
+            Key::Char('+') | Key::Char('=') => self.memory = self.memory.wrapping_add(0x10),
+            _ => {}
+        }
+
+        Action::Redraw
+    }
+
+    /// Run up to `instructions` of a continue, stopping it on a breakpoint, halt or fault.
+    pub fn run_slice<B: Bus>(&mut self, vm: &mut VirtualMachine<B>, instructions: usize) {
+        if !self.running {
+            return;
+        }
+
+        for _ in 0..instructions {
+            match vm.debug_step() {
+                Ok(None) => {}
+                Ok(Some(stop)) => {
+                    self.stopped(Ok(stop));
+                    return;
+                }
+                Err(e) => {
+                    self.stopped(Err(e));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Render the screen at `width` by `height` characters.
+    pub fn render<B: Bus>(&self, vm: &VirtualMachine<B>, width: u16, height: u16) -> Vec<Line> {
+        let (width, height) = (width as usize, height as usize);
+        let memory_rows = (height / 3).max(4);
+        let top_rows = height.saturating_sub(memory_rows + 3).max(1);
+
+        let left = self.left_pane(vm, top_rows);
+        let right = self.disassembly_pane(vm, top_rows);
+        let right_width = width.saturating_sub(LEFT_WIDTH + 1);
+
+        let mut lines = Vec::with_capacity(height);
+        for row in 0..top_rows {
+            let mut line = left.get(row).cloned().unwrap_or_default().fit(LEFT_WIDTH);
+            line.push("│", Style::Normal);
+            line.0.extend(
+                right
+                    .get(row)
+                    .cloned()
+                    .unwrap_or_default()
+                    .fit(right_width)
+                    .0,
+            );
+            lines.push(line);
+        }
+
+        let mut title = Line::default();
+        title.push("─".repeat(width), Style::Normal);
+        lines.push(title);
+        lines.extend(self.memory_pane(vm, memory_rows + 1));
+
+        let mut status = Line::default();
+        status.push(format!("{:<12}", self.status), Style::Title);
+        status.push(
+            " s step  n over  o out  c continue  b break  ↑↓ select  PgUp/PgDn memory  q quit",
+            Style::Normal,
+        );
+        lines.push(status);
+
+        lines.truncate(height);
+        lines.into_iter().map(|l| l.fit(width)).collect()
+    }
+
+    fn left_pane<B: Bus>(&self, vm: &VirtualMachine<B>, rows: usize) -> Vec<Line> {
+        let r = &vm.registers;
+        let mut lines = Vec::new();
+        let mut line = |text: String, style: Style| {
+            let mut l = Line::default();
+            l.push(text, style);
+            lines.push(l);
+        };
+
+        line(" Registers".into(), Style::Title);
+        line(" PC   A  X  Y  SP".into(), Style::Normal);
+        line(
+            format!(
+                " {:04X} {:02X} {:02X} {:02X} {:02X}",
+                r.pc, r.ac, r.x, r.y, r.sp
+            ),
+            Style::Normal,
+        );
+        line(" Flags NV-BDIZC".into(), Style::Title);
+        line(format!("       {:08b}", r.sr), Style::Normal);
+        line(format!(" Cycles {}", vm.cycles), Style::Title);
+        line(" Stack".into(), Style::Title);
+
+        // The rows of page one ending at its top, showing as much above SP as fits.
+        let stack_rows = rows.saturating_sub(lines.len()).min(32);
+        let sp = vm.stack_bounds.0 as u16 + r.sp as u16;
+        let first = (sp & 0xFFF8).min(0x01F8 - 8 * (stack_rows.saturating_sub(1) as u16));
+        for row in 0..stack_rows as u16 {
+            let start = first + row * 8;
+            if start > 0x01F8 {
+                break;
+            }
+
+            let mut l = Line::default();
+            l.push(format!(" {:04X}", start), Style::Normal);
+            for address in start..start + 8 {
+                let style = if address == sp {
+                    Style::Current
+                } else {
+                    self.change_style(vm, address)
+                };
+                l.push(" ", Style::Normal);
+                l.push(format!("{:02X}", vm.flatmap.peek(address)), style);
+            }
+            lines.push(l);
+        }
+
+        lines
+    }
+
+    fn disassembly_pane<B: Bus>(&self, vm: &VirtualMachine<B>, rows: usize) -> Vec<Line> {
+        let pc = vm.registers.pc;
+        let focus = self.selected.unwrap_or(pc);
+        let listing = rows.saturating_sub(1);
+        let before = listing / 3;
+
+        // The PC by symbol and source line, when they're known.
+        let mut title = Line::default();
+        title.push(" Disassembly", Style::Title);
+        if vm.symbols.locate(pc).is_some() {
+            title.push(format!("  {}", vm.symbols.describe(pc)), Style::Normal);
+        }
+        if let Some(line) = vm.symbols.source_line(pc) {
+            title.push(format!("  {}", line), Style::Normal);
+        }
+        let mut lines = vec![title];
+
+        for instruction in vm.disassemble_around(focus, before, listing) {
+            let breakpoint = vm
+                .breakpoints()
+                .iter()
+                .any(|(_, b)| *b == Breakpoint::Pc(instruction.address));
+
+            let marker = match (instruction.address == pc, breakpoint) {
+                (true, true) => ">*",
+                (true, false) => "> ",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
+            let style = if Some(instruction.address) == self.selected {
+                Style::Selected
+            } else if instruction.address == pc {
+                Style::Current
+            } else if breakpoint {
+                Style::Breakpoint
+            } else {
+                Style::Normal
+            };
+
+            let mut line = Line::default();
+            line.push(
+                format!(" {}{}", marker, instruction.labelled_listing(&vm.symbols)),
+                style,
+            );
+            lines.push(line);
+        }
+
+        lines.truncate(rows);
+        lines
+    }
+
+    fn memory_pane<B: Bus>(&self, vm: &VirtualMachine<B>, rows: usize) -> Vec<Line> {
+        let mut title = Line::default();
+        title.push(" Memory", Style::Title);
+        let mut lines = vec![title];
+
+        for row in 0..rows.saturating_sub(1) as u16 {
+            let start = self.memory.wrapping_add(row * 16);
+
+            let mut line = Line::default();
+            line.push(format!(" {:04X} ", start), Style::Normal);
+            let mut text = String::new();
+            for i in 0..16 {
+                let address = start.wrapping_add(i);
+                let byte = vm.flatmap.peek(address);
+                line.push(" ", Style::Normal);
+                line.push(format!("{:02X}", byte), self.change_style(vm, address));
+                text.push(match byte {
+                    0x20..=0x7E => byte as char,
+                    _ => '.',
+                });
+            }
+            line.push(format!("  {}", text), Style::Normal);
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    fn change_style<B: Bus>(&self, vm: &VirtualMachine<B>, address: u16) -> Style {
+        match self.previous.get(address as usize) {
+            Some(byte) if *byte != vm.flatmap.peek(address) => Style::Changed,
+            _ => Style::Normal,
+        }
+    }
+
+    fn toggle_breakpoint<B: Bus>(&mut self, vm: &mut VirtualMachine<B>, address: u16) {
+        let existing = vm
+            .breakpoints()
+            .iter()
+            .find(|(_, b)| *b == Breakpoint::Pc(address))
+            .map(|(id, _)| *id);
+
+        match existing {
+            Some(id) => {
+                vm.remove_breakpoint(id);
+            }
+            None => {
+                vm.add_breakpoint(Breakpoint::Pc(address));
+            }
+        }
+    }
+
+    /// Remember memory as it is, to highlight what the next command changes.
+    fn snapshot<B: Bus>(&mut self, vm: &VirtualMachine<B>) {
+        self.previous = (0..=0xFFFF).map(|a| vm.flatmap.peek(a)).collect();
+    }
+
+    fn execute<B: Bus>(
+        &mut self,
+        vm: &mut VirtualMachine<B>,
+        command: impl FnOnce(&mut VirtualMachine<B>) -> VmResult<StopReason>,
+    ) {
+        self.snapshot(vm);
+        self.selected = None;
+        let stop = command(vm).map(|stop| match (vm.jammed, vm.halted) {
+            (true, _) => StopReason::Jammed {
+                pc: vm.registers.pc,
+            },
+            (false, true) => StopReason::Halted,
+            (false, false) => stop,
+        });
+        self.stopped(stop);
+    }
+
+    fn stopped(&mut self, stop: VmResult<StopReason>) {
+        self.running = false;
+        self.status = match stop {
+            Ok(StopReason::Breakpoint { id, .. }) => format!("breakpoint {}", id),
+            Ok(StopReason::Watchpoint { id, .. }) => format!("watchpoint {}", id),
+            Ok(StopReason::Halted) => "halted".to_string(),
+            Ok(StopReason::Jammed { pc }) => format!("jammed at ${:04X}", pc),
+            Ok(_) => String::new(),
+            Err(e) => e.to_string(),
+        };
+    }
+}
+
+/// Run the debugger on `vm` in the terminal until the user quits.
+#[cfg(feature = "tui")]
+pub fn run<B: Bus>(vm: &mut VirtualMachine<B>) -> std::io::Result<()> {
+    use std::io::{stdout, Write};
+    use std::time::Duration;
+
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+    use crossterm::{cursor, execute, queue, terminal};
+
+    /// Restores the terminal however the debugger exits.
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen).ok();
+            terminal::disable_raw_mode().ok();
+        }
+    }
+
+    terminal::enable_raw_mode()?;
+    let _guard = Guard;
+    let mut out = stdout();
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let mut tui = Tui::new();
+    loop {
+        let (width, height) = terminal::size()?;
+        queue!(out, cursor::MoveTo(0, 0))?;
+        for (row, line) in tui.render(vm, width, height).iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            for span in &line.0 {
+                match span.style {
+                    Style::Normal => {}
+                    Style::Title => queue!(out, SetAttribute(Attribute::Bold))?,
+                    Style::Current => queue!(out, SetAttribute(Attribute::Reverse))?,
+                    Style::Selected => queue!(out, SetAttribute(Attribute::Underlined))?,
+                    Style::Breakpoint => queue!(out, SetForegroundColor(Color::Red))?,
+                    Style::Changed => queue!(out, SetForegroundColor(Color::Yellow))?,
+                }
+                queue!(
+                    out,
+                    Print(&span.text),
+                    SetAttribute(Attribute::Reset),
+                    ResetColor
+                )?;
+            }
+        }
+        out.flush()?;
+
+        if tui.running() {
+            tui.run_slice(vm, RUN_SLICE);
+            if !event::poll(Duration::ZERO)? {
+                continue;
+            }
+        }
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+
+        // Raw mode swallows SIGINT, so Ctrl-C quits directly.
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            break;
+        }
+
+        let key = match key.code {
+            KeyCode::Char(c) => Key::Char(c),
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Esc => Key::Esc,
+            _ => continue,
+        };
+
+        if tui.handle_key(vm, key) == Action::Quit {
+            break;
+        }
+    }
+
+    Ok(())
+}
@@ -13,17 +13,17 @@ mod macros {
 mod vm_macros {
     #[macro_export]
     macro_rules! stuff_program_at_end {
-        ($vm:expr, $prog:expr) => {
+        ($vm:expr, $prog:expr) => {{
             let offset = ($vm.heap_bounds.1 - $prog.len());
-            $vm.insert_program(offset as u16, $prog);
-        };
+            $vm.insert_program(offset as u16, $prog)
+        }};
     }
 
     #[macro_export]
     macro_rules! check_page_cross {
-        ($vm:expr, $new_pc:expr) => {
+        ($from:expr, $to:expr) => {
             // We're comparing the page bytes, and don't care about the lower bytes.
-            $new_pc & 0xFF00 != $vm.registers.pc & 0xFF00
+            $to & 0xFF00 != $from & 0xFF00
         };
     }
 }
@@ -72,11 +72,9 @@ mod status_macros {
 pub mod machine_arrays {
     pub mod prelude {
         pub use crate::utils::machine_arrays::{
-            valid_op,
-            COMPLETE_OPCODE_TABLE,
-            N_VALID_OPS,
-            OP_MODES, //VALID_CYCLE_COUNTS,
-            VALID_OPCODES,
+            op_mode, page_cross_penalty, undocumented_mode, valid_op, COMPLETE_OPCODE_TABLE,
+            N_UNDOCUMENTED_OPS, N_VALID_OPS, OPCODE_CYCLES, OP_MODES, UNDOCUMENTED_MODES,
+            UNDOCUMENTED_OPCODES, VALID_CYCLE_COUNTS, VALID_OPCODES,
         };
     }
 
@@ -98,24 +96,103 @@ pub mod machine_arrays {
         0xFE,
     ];
 
-    //The total number of cycles VALID_OPCODES[\n] should spend.
-    //
-    // This is mostly for debuggin purposes and is to be deprecated for internal use.
+    /// Total number of stable undocumented NMOS opcodes.
+    pub const N_UNDOCUMENTED_OPS: usize = 97;
+
+    /// The stable undocumented NMOS ops in order, including the JAMs and the NOP variants.
+    ///
+    /// The unstable ANE, LXA, LAS, SHA, SHX, SHY and TAS aren't included, and are always illegal.
+    pub static UNDOCUMENTED_OPCODES: [u8; N_UNDOCUMENTED_OPS] = [
+        0x02, 0x03, 0x04, 0x07, 0x0B, 0x0C, 0x0F, 0x12, 0x13, 0x14, 0x17, 0x1A, 0x1B, 0x1C, 0x1F,
+        0x22, 0x23, 0x27, 0x2B, 0x2F, 0x32, 0x33, 0x34, 0x37, 0x3A, 0x3B, 0x3C, 0x3F, 0x42, 0x43,
+        0x44, 0x47, 0x4B, 0x4F, 0x52, 0x53, 0x54, 0x57, 0x5A, 0x5B, 0x5C, 0x5F, 0x62, 0x63, 0x64,
+        0x67, 0x6B, 0x6F, 0x72, 0x73, 0x74, 0x77, 0x7A, 0x7B, 0x7C, 0x7F, 0x80, 0x82, 0x83, 0x87,
+        0x89, 0x8F, 0x92, 0x97, 0xA3, 0xA7, 0xAF, 0xB2, 0xB3, 0xB7, 0xBF, 0xC2, 0xC3, 0xC7, 0xCB,
+        0xCF, 0xD2, 0xD3, 0xD4, 0xD7, 0xDA, 0xDB, 0xDC, 0xDF, 0xE2, 0xE3, 0xE7, 0xEB, 0xEF, 0xF2,
+        0xF3, 0xF4, 0xF7, 0xFA, 0xFB, 0xFC, 0xFF,
+    ];
+
+    /// The base number of cycles `VALID_OPCODES[n]` takes, see [OPCODE_CYCLES].
     pub static VALID_CYCLE_COUNTS: [u8; N_VALID_OPS] = [
-        7, 6, 3, 5, 3, 2, 2, 4, 6, 2, 5, 4, 6, 2, 4, 4, 7, 6, 6, 3, 3, 5, 3, 2, 2, 4, 6, 2, 5, 4,
-        6, 2, 4, 4, 7, 6, 6, 3, 4, 4, 6, 5, 3, 2, 2, 3, 4, 2, 5, 4, 6, 2, 4, 4, 7, 6, 6, 3, 4, 4,
-        6, 5, 3, 2, 2, 4, 4, 2, 5, 4, 6, 2, 4, 4, 7, 6, 6, 3, 2, 2, 3, 4, 2, 2, 4, 4, 7, 6, 2, 2,
-        3, 4, 2, 2, 4, 4, 7, 6, 2, 2, 2, 4, 2, 2, 4, 4, 7, 6, 2, 2, 3, 4, 2, 2, 4, 4, 7, 6, 2, 2,
-        2, 4, 2, 2, 4, 4, 7, 6, 2, 2, 3, 4, 2, 2, 4, 4, 7, 6, 2, 2, 2, 4, 2, 2, 4, 4, 7, 6, 2, 2,
-        2,
+        7, 6, 3, 5, 3, 2, 2, 4, 6, 2, 5, 4, 6, 2, 4, 4, 7, 6, 6, 3, 3, 5, 4, 2, 2, 4, 4, 6, 2, 5,
+        4, 6, 2, 4, 4, 7, 6, 6, 3, 5, 3, 2, 2, 3, 4, 6, 2, 5, 4, 6, 2, 4, 4, 7, 6, 6, 3, 5, 4, 2,
+        2, 5, 4, 6, 2, 5, 4, 6, 2, 4, 4, 7, 6, 3, 3, 3, 2, 2, 4, 4, 4, 2, 6, 4, 4, 4, 2, 5, 2, 5,
+        2, 6, 2, 3, 3, 3, 2, 2, 2, 4, 4, 4, 2, 5, 4, 4, 4, 2, 4, 2, 4, 4, 4, 2, 6, 3, 3, 5, 2, 2,
+        2, 4, 4, 6, 2, 5, 4, 6, 2, 4, 4, 7, 2, 6, 3, 3, 5, 2, 2, 2, 4, 4, 6, 2, 5, 4, 6, 2, 4, 4,
+        7,
+    ];
+
+    /// The base number of cycles every NMOS 6502 opcode takes, indexed by opcode.
+    ///
+    /// Indexed reads which cross a page take one more cycle, see [page_cross_penalty].
+    /// Branches take one more cycle when taken, and another when the branch crosses a page.
+    pub static OPCODE_CYCLES: [u8; 256] = [
+        7, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0x0_
+        2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x1_
+        6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 0x2_
+        2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x3_
+        6, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 0x4_
+        2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x5_
+        6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 0x6_
+        2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x7_
+        2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0x8_
+        2, 6, 2, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 0x9_
+        2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0xA_
+        2, 5, 2, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // 0xB_
+        2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xC_
+        2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xD_
+        2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xE_
+        2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xF_
     ];
 
+    /// Whether an indexed read by `op` takes an extra cycle when its address crosses a page.
+    ///
+    /// Stores and read-modify-write ops always take the extra cycle, so it's in their base count.
+    pub fn page_cross_penalty(op: u8) -> bool {
+        matches!(
+            op,
+            0x11 | 0x19
+                | 0x1D
+                | 0x31
+                | 0x39
+                | 0x3D
+                | 0x51
+                | 0x59
+                | 0x5D
+                | 0x71
+                | 0x79
+                | 0x7D
+                | 0xB1
+                | 0xB9
+                | 0xBC
+                | 0xBD
+                | 0xBE
+                | 0xD1
+                | 0xD9
+                | 0xDD
+                | 0xF1
+                | 0xF9
+                | 0xFD
+                // Undocumented LAX and NOP reads.
+                | 0x1C
+                | 0x3C
+                | 0x5C
+                | 0x7C
+                | 0xB3
+                | 0xBF
+                | 0xDC
+                | 0xFC
+        )
+    }
+
     /// All opcodes and their names, as tuples in order.
     ///
     /// For example, 0x00 is "BRK", 0x01 is "ORA (indirect, X)".
     ///
     /// Ex. `COMPLETE_OPCODE_TABLE[0] == ("BRK", 0x00)`
     ///
+    /// Undocumented opcodes have their NMOS names, ex. 0xA7 is "LAX" and 0x02 is "JAM".
+    ///
     /// # Use valid_op to filter out invalid ops.
     /// ## Example:
     /// ```
@@ -128,260 +205,260 @@ pub mod machine_arrays {
     pub static COMPLETE_OPCODE_TABLE: [(&str, u8); 256] = [
         ("BRK", 0x00),
         ("ORA", 0x01),
-        ("NOP", 0x02),
-        ("NOP", 0x03),
+        ("JAM", 0x02),
+        ("SLO", 0x03),
         ("NOP", 0x04),
         ("ORA", 0x05),
         ("ASL", 0x06),
-        ("NOP", 0x07),
+        ("SLO", 0x07),
         ("PHP", 0x08),
         ("ORA", 0x09),
         ("ASL", 0x0A),
-        ("NOP", 0x0B),
+        ("ANC", 0x0B),
         ("NOP", 0x0C),
         ("ORA", 0x0D),
         ("ASL", 0x0E),
-        ("NOP", 0x0F),
+        ("SLO", 0x0F),
         ("BPL", 0x10),
         ("ORA", 0x11),
-        ("NOP", 0x12),
-        ("NOP", 0x13),
+        ("JAM", 0x12),
+        ("SLO", 0x13),
         ("NOP", 0x14),
         ("ORA", 0x15),
         ("ASL", 0x16),
-        ("NOP", 0x17),
+        ("SLO", 0x17),
         ("CLC", 0x18),
         ("ORA", 0x19),
         ("NOP", 0x1A),
-        ("NOP", 0x1B),
+        ("SLO", 0x1B),
         ("NOP", 0x1C),
         ("ORA", 0x1D),
         ("ASL", 0x1E),
-        ("NOP", 0x1F),
+        ("SLO", 0x1F),
         ("JSR", 0x20),
         ("AND", 0x21),
-        ("NOP", 0x22),
-        ("NOP", 0x23),
+        ("JAM", 0x22),
+        ("RLA", 0x23),
         ("BIT", 0x24),
         ("AND", 0x25),
         ("ROL", 0x26),
-        ("NOP", 0x27),
+        ("RLA", 0x27),
         ("PLP", 0x28),
         ("AND", 0x29),
         ("ROL", 0x2A),
-        ("NOP", 0x2B),
+        ("ANC", 0x2B),
         ("BIT", 0x2C),
         ("AND", 0x2D),
         ("ROL", 0x2E),
-        ("NOP", 0x2F),
+        ("RLA", 0x2F),
         ("BMI", 0x30),
         ("AND", 0x31),
-        ("NOP", 0x32),
-        ("NOP", 0x33),
+        ("JAM", 0x32),
+        ("RLA", 0x33),
         ("NOP", 0x34),
         ("AND", 0x35),
         ("ROL", 0x36),
-        ("NOP", 0x37),
+        ("RLA", 0x37),
         ("SEC", 0x38),
         ("AND", 0x39),
         ("NOP", 0x3A),
-        ("NOP", 0x3B),
+        ("RLA", 0x3B),
         ("NOP", 0x3C),
         ("AND", 0x3D),
         ("ROL", 0x3E),
-        ("NOP", 0x3F),
+        ("RLA", 0x3F),
         ("RTI", 0x40),
         ("EOR", 0x41),
-        ("NOP", 0x42),
-        ("NOP", 0x43),
+        ("JAM", 0x42),
+        ("SRE", 0x43),
         ("NOP", 0x44),
         ("EOR", 0x45),
         ("LSR", 0x46),
-        ("NOP", 0x47),
+        ("SRE", 0x47),
         ("PHA", 0x48),
         ("EOR", 0x49),
         ("LSR", 0x4A),
-        ("NOP", 0x4B),
+        ("ALR", 0x4B),
         ("JMP", 0x4C),
         ("EOR", 0x4D),
         ("LSR", 0x4E),
-        ("NOP", 0x4F),
+        ("SRE", 0x4F),
         ("BVC", 0x50),
         ("EOR", 0x51),
-        ("NOP", 0x52),
-        ("NOP", 0x53),
+        ("JAM", 0x52),
+        ("SRE", 0x53),
         ("NOP", 0x54),
         ("EOR", 0x55),
         ("LSR", 0x56),
-        ("NOP", 0x57),
+        ("SRE", 0x57),
         ("CLI", 0x58),
         ("EOR", 0x59),
         ("NOP", 0x5A),
-        ("NOP", 0x5B),
+        ("SRE", 0x5B),
         ("NOP", 0x5C),
         ("EOR", 0x5D),
         ("LSR", 0x5E),
-        ("NOP", 0x5F),
+        ("SRE", 0x5F),
         ("RTS", 0x60),
         ("ADC", 0x61),
-        ("NOP", 0x62),
-        ("NOP", 0x63),
+        ("JAM", 0x62),
+        ("RRA", 0x63),
         ("NOP", 0x64),
         ("ADC", 0x65),
         ("ROR", 0x66),
-        ("NOP", 0x67),
+        ("RRA", 0x67),
         ("PLA", 0x68),
         ("ADC", 0x69),
         ("ROR", 0x6A),
-        ("NOP", 0x6B),
+        ("ARR", 0x6B),
         ("JMP", 0x6C),
         ("ADC", 0x6D),
         ("ROR", 0x6E),
-        ("NOP", 0x6F),
+        ("RRA", 0x6F),
         ("BVS", 0x70),
         ("ADC", 0x71),
-        ("NOP", 0x72),
-        ("NOP", 0x73),
+        ("JAM", 0x72),
+        ("RRA", 0x73),
         ("NOP", 0x74),
         ("ADC", 0x75),
         ("ROR", 0x76),
-        ("NOP", 0x77),
+        ("RRA", 0x77),
         ("SEI", 0x78),
         ("ADC", 0x79),
         ("NOP", 0x7A),
-        ("NOP", 0x7B),
+        ("RRA", 0x7B),
         ("NOP", 0x7C),
         ("ADC", 0x7D),
         ("ROR", 0x7E),
-        ("NOP", 0x7F),
+        ("RRA", 0x7F),
         ("NOP", 0x80),
         ("STA", 0x81),
         ("NOP", 0x82),
-        ("NOP", 0x83),
+        ("SAX", 0x83),
         ("STY", 0x84),
         ("STA", 0x85),
         ("STX", 0x86),
-        ("NOP", 0x87),
+        ("SAX", 0x87),
         ("DEY", 0x88),
         ("NOP", 0x89),
         ("TXA", 0x8A),
-        ("NOP", 0x8B),
+        ("ANE", 0x8B),
         ("STY", 0x8C),
         ("STA", 0x8D),
         ("STX", 0x8E),
-        ("NOP", 0x8F),
+        ("SAX", 0x8F),
         ("BCC", 0x90),
         ("STA", 0x91),
-        ("NOP", 0x92),
-        ("NOP", 0x93),
+        ("JAM", 0x92),
+        ("SHA", 0x93),
         ("STY", 0x94),
         ("STA", 0x95),
         ("STX", 0x96),
-        ("NOP", 0x97),
+        ("SAX", 0x97),
         ("TYA", 0x98),
         ("STA", 0x99),
         ("TXS", 0x9A),
-        ("NOP", 0x9B),
-        ("NOP", 0x9C),
+        ("TAS", 0x9B),
+        ("SHY", 0x9C),
         ("STA", 0x9D),
-        ("NOP", 0x9E),
-        ("NOP", 0x9F),
+        ("SHX", 0x9E),
+        ("SHA", 0x9F),
         ("LDY", 0xA0),
         ("LDA", 0xA1),
         ("LDX", 0xA2),
-        ("NOP", 0xA3),
+        ("LAX", 0xA3),
         ("LDY", 0xA4),
         ("LDA", 0xA5),
         ("LDX", 0xA6),
-        ("NOP", 0xA7),
+        ("LAX", 0xA7),
         ("TAY", 0xA8),
         ("LDA", 0xA9),
         ("TAX", 0xAA),
-        ("NOP", 0xAB),
+        ("LXA", 0xAB),
         ("LDY", 0xAC),
         ("LDA", 0xAD),
         ("LDX", 0xAE),
-        ("NOP", 0xAF),
+        ("LAX", 0xAF),
         ("BCS", 0xB0),
         ("LDA", 0xB1),
-        ("NOP", 0xB2),
-        ("NOP", 0xB3),
+        ("JAM", 0xB2),
+        ("LAX", 0xB3),
         ("LDY", 0xB4),
         ("LDA", 0xB5),
         ("LDX", 0xB6),
-        ("NOP", 0xB7),
+        ("LAX", 0xB7),
         ("CLV", 0xB8),
         ("LDA", 0xB9),
         ("TSX", 0xBA),
-        ("NOP", 0xBB),
+        ("LAS", 0xBB),
         ("LDY", 0xBC),
         ("LDA", 0xBD),
         ("LDX", 0xBE),
-        ("NOP", 0xBF),
+        ("LAX", 0xBF),
         ("CPY", 0xC0),
         ("CMP", 0xC1),
         ("NOP", 0xC2),
-        ("NOP", 0xC3),
+        ("DCP", 0xC3),
         ("CPY", 0xC4),
         ("CMP", 0xC5),
         ("DEC", 0xC6),
-        ("NOP", 0xC7),
+        ("DCP", 0xC7),
         ("INY", 0xC8),
         ("CMP", 0xC9),
         ("DEX", 0xCA),
-        ("NOP", 0xCB),
+        ("SBX", 0xCB),
         ("CPY", 0xCC),
         ("CMP", 0xCD),
         ("DEC", 0xCE),
-        ("NOP", 0xCF),
+        ("DCP", 0xCF),
         ("BNE", 0xD0),
         ("CMP", 0xD1),
-        ("NOP", 0xD2),
-        ("NOP", 0xD3),
+        ("JAM", 0xD2),
+        ("DCP", 0xD3),
         ("NOP", 0xD4),
         ("CMP", 0xD5),
         ("DEC", 0xD6),
-        ("NOP", 0xD7),
+        ("DCP", 0xD7),
         ("CLD", 0xD8),
         ("CMP", 0xD9),
         ("NOP", 0xDA),
-        ("NOP", 0xDB),
+        ("DCP", 0xDB),
         ("NOP", 0xDC),
         ("CMP", 0xDD),
         ("DEC", 0xDE),
-        ("NOP", 0xDF),
+        ("DCP", 0xDF),
         ("CPX", 0xE0),
         ("SBC", 0xE1),
         ("NOP", 0xE2),
-        ("NOP", 0xE3),
+        ("ISC", 0xE3),
         ("CPX", 0xE4),
         ("SBC", 0xE5),
         ("INC", 0xE6),
-        ("NOP", 0xE7),
+        ("ISC", 0xE7),
         ("INX", 0xE8),
         ("SBC", 0xE9),
         ("NOP", 0xEA),
-        ("NOP", 0xEB),
+        ("SBC", 0xEB),
         ("CPX", 0xEC),
         ("SBC", 0xED),
         ("INC", 0xEE),
-        ("NOP", 0xEF),
+        ("ISC", 0xEF),
         ("BEQ", 0xF0),
         ("SBC", 0xF1),
-        ("NOP", 0xF2),
-        ("NOP", 0xF3),
+        ("JAM", 0xF2),
+        ("ISC", 0xF3),
         ("NOP", 0xF4),
         ("SBC", 0xF5),
         ("INC", 0xF6),
-        ("NOP", 0xF7),
+        ("ISC", 0xF7),
         ("SED", 0xF8),
         ("SBC", 0xF9),
         ("NOP", 0xFA),
-        ("NOP", 0xFB),
+        ("ISC", 0xFB),
         ("NOP", 0xFC),
         ("SBC", 0xFD),
         ("INC", 0xFE),
-        ("NOP", 0xFF),
+        ("ISC", 0xFF),
     ];
 
     pub fn valid_op(op: u8) -> bool {
@@ -394,6 +471,38 @@ pub mod machine_arrays {
         false
     }
 
+    /// The addressing mode of a valid opcode, as listed in [OP_MODES].
+    ///
+    /// ## Example:
+    /// ```
+    /// use vm6502::prelude::*;
+    ///
+    /// assert_eq!(op_mode(0x6C), Some(Mode::Indirect));
+    /// assert_eq!(op_mode(0x02), None);
+    /// ```
+    pub fn op_mode(op: u8) -> Option<Mode> {
+        VALID_OPCODES
+            .iter()
+            .position(|vop| *vop == op)
+            .map(|i| OP_MODES[i])
+    }
+
+    /// The addressing mode of a stable undocumented opcode, as listed in [UNDOCUMENTED_MODES].
+    ///
+    /// ## Example:
+    /// ```
+    /// use vm6502::prelude::*;
+    ///
+    /// assert_eq!(undocumented_mode(0xB3), Some(Mode::IndirectY));
+    /// assert_eq!(undocumented_mode(0x6C), None);
+    /// ```
+    pub fn undocumented_mode(op: u8) -> Option<Mode> {
+        UNDOCUMENTED_OPCODES
+            .iter()
+            .position(|uop| *uop == op)
+            .map(|i| UNDOCUMENTED_MODES[i])
+    }
+
     use crate::prelude::Mode;
     use crate::prelude::Mode::*;
     /// The expected modes for the VALID_OPCODES.
@@ -550,6 +659,22 @@ pub mod machine_arrays {
         AbsoluteX,
         AbsoluteX,
     ];
+
+    /// The modes of the UNDOCUMENTED_OPCODES.
+    pub static UNDOCUMENTED_MODES: [Mode; N_UNDOCUMENTED_OPS] = [
+        Implied, IndirectX, ZeroPage, ZeroPage, Immediate, Absolute, Absolute, Implied, IndirectY,
+        ZeroPageX, ZeroPageX, Implied, AbsoluteY, AbsoluteX, AbsoluteX, Implied, IndirectX,
+        ZeroPage, Immediate, Absolute, Implied, IndirectY, ZeroPageX, ZeroPageX, Implied,
+        AbsoluteY, AbsoluteX, AbsoluteX, Implied, IndirectX, ZeroPage, ZeroPage, Immediate,
+        Absolute, Implied, IndirectY, ZeroPageX, ZeroPageX, Implied, AbsoluteY, AbsoluteX,
+        AbsoluteX, Implied, IndirectX, ZeroPage, ZeroPage, Immediate, Absolute, Implied, IndirectY,
+        ZeroPageX, ZeroPageX, Implied, AbsoluteY, AbsoluteX, AbsoluteX, Immediate, Immediate,
+        IndirectX, ZeroPage, Immediate, Absolute, Implied, ZeroPageY, IndirectX, ZeroPage,
+        Absolute, Implied, IndirectY, ZeroPageY, AbsoluteY, Immediate, IndirectX, ZeroPage,
+        Immediate, Absolute, Implied, IndirectY, ZeroPageX, ZeroPageX, Implied, AbsoluteY,
+        AbsoluteX, AbsoluteX, Immediate, IndirectX, ZeroPage, Immediate, Absolute, Implied,
+        IndirectY, ZeroPageX, ZeroPageX, Implied, AbsoluteY, AbsoluteX, AbsoluteX,
+    ];
 }
 
 #[macro_use]
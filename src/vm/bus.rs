@@ -0,0 +1,207 @@
+use bytes::BytesMut;
+
+pub mod prelude {
+    pub use crate::vm::bus::{Bus, MemoryMap, Rom};
+}
+
+/// The 6502 address bus.
+///
+/// Every memory access made by the [VirtualMachine] goes through its bus, so
+/// implementing this trait allows for memory mapped I/O, ROM and mirrored RAM.
+/// The default bus is a flat 64K [BytesMut].
+pub trait Bus {
+    /// Read the byte at `address`. Reads may have side effects on devices.
+    fn read(&mut self, address: u16) -> u8;
+    /// Write `value` to `address`.
+    fn write(&mut self, address: u16, value: u8);
+    /// Read the byte at `address` without side effects, for debuggers and disassembly.
+    fn peek(&self, address: u16) -> u8;
+    /// Write `value` to `address` without side effects, for restoring snapshots.
+    ///
+    /// Defaults to [write](Bus::write), which suits plain memory.
+    fn poke(&mut self, address: u16, value: u8) {
+        self.write(address, value);
+    }
+}
+
+/// Flat RAM. Addresses outside of the buffer read as 0 and ignore writes.
+impl Bus for BytesMut {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if let Some(byte) = self.get_mut(address as usize) {
+            *byte = value;
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.get(address as usize).copied().unwrap_or(0)
+    }
+}
+
+/// Read only memory. Writes are ignored, and reads past the end are 0xFF.
+#[derive(Debug, Clone, Default)]
+pub struct Rom(pub Vec<u8>);
+
+impl Bus for Rom {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, _address: u16, _value: u8) {}
+
+    fn peek(&self, address: u16) -> u8 {
+        self.0.get(address as usize).copied().unwrap_or(0xFF)
+    }
+}
+
+enum Mapping {
+    Device(usize),
+    Mirror { base: u16, size: u16 },
+}
+
+struct Region {
+    start: u16,
+    end: u16,
+    mapping: Mapping,
+}
+
+/// A bus made of 64K of RAM with devices and mirrors mapped over address ranges.
+///
+/// Regions are searched from the most recently mapped, so later mappings take
+/// precedence over earlier ones. Unmapped addresses fall through to RAM.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let mut bus = MemoryMap::new();
+/// bus.map_rom(0xF000, vec![0xEA; 0x1000]);
+/// // 0x0800..=0x1FFF mirrors the first 2K of RAM.
+/// bus.mirror(0x0800, 0x1FFF, 0x0000, 0x0800);
+///
+/// let mut vm = VirtualMachine::with_bus(bus);
+/// vm.flatmap.write(0x0801, 0x42);
+/// vm.flatmap.write(0xF000, 0x00);
+///
+/// assert_eq!(vm.flatmap.read(0x0001), 0x42);
+/// assert_eq!(vm.flatmap.read(0xF000), 0xEA);
+/// ```
+pub struct MemoryMap {
+    /// Backing RAM for every unmapped address.
+    pub ram: BytesMut,
+    devices: Vec<Box<dyn Bus>>,
+    regions: Vec<Region>,
+}
+
+impl MemoryMap {
+    pub fn new() -> Self {
+        MemoryMap {
+            ram: BytesMut::zeroed(0x10000),
+            devices: Vec::new(),
+            regions: Vec::new(),
+        }
+    }
+
+    /// Map `device` over `start..=end`. The device is addressed relative to `start`.
+    pub fn map(&mut self, start: u16, end: u16, device: impl Bus + 'static) -> &mut Self {
+        self.devices.push(Box::new(device));
+        self.regions.push(Region {
+            start,
+            end,
+            mapping: Mapping::Device(self.devices.len() - 1),
+        });
+
+        self
+    }
+
+    /// Map `bytes` as ROM starting at `start`.
+    ///
+    /// Bytes that would run past 0xFFFF are dropped, so a full 64K image maps at 0x0000.
+    pub fn map_rom(&mut self, start: u16, mut bytes: Vec<u8>) -> &mut Self {
+        bytes.truncate(0x10000 - start as usize);
+        let end = (start as usize + bytes.len().max(1) - 1) as u16;
+        self.map(start, end, Rom(bytes))
+    }
+
+    /// Mirror the `size` bytes at `base` repeatedly over `start..=end`.
+    ///
+    /// The mirrored addresses are resolved once more, so a mirror can target a device.
+    pub fn mirror(&mut self, start: u16, end: u16, base: u16, size: u16) -> &mut Self {
+        self.regions.push(Region {
+            start,
+            end,
+            mapping: Mapping::Mirror { base, size },
+        });
+
+        self
+    }
+
+    /// Find the region containing `address`, skipping mirrors if `devices_only`.
+    fn region(&self, address: u16, devices_only: bool) -> Option<usize> {
+        self.regions.iter().rposition(|r| {
+            (r.start..=r.end).contains(&address)
+                && !(devices_only && matches!(r.mapping, Mapping::Mirror { .. }))
+        })
+    }
+
+    /// Resolve `address` to a device and offset, or a RAM address.
+    fn resolve(&self, address: u16) -> (Option<usize>, u16) {
+        let Some(i) = self.region(address, false) else {
+            return (None, address);
+        };
+
+        let address = match self.regions[i].mapping {
+            Mapping::Device(device) => return (Some(device), address - self.regions[i].start),
+            Mapping::Mirror { base, size } => {
+                base.wrapping_add((address - self.regions[i].start) % size.max(1))
+            }
+        };
+
+        match self.region(address, true).map(|j| &self.regions[j]) {
+            Some(Region {
+                start,
+                mapping: Mapping::Device(device),
+                ..
+            }) => (Some(*device), address - start),
+            _ => (None, address),
+        }
+    }
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for MemoryMap {
+    fn read(&mut self, address: u16) -> u8 {
+        match self.resolve(address) {
+            (Some(device), offset) => self.devices[device].read(offset),
+            (None, address) => self.ram.read(address),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match self.resolve(address) {
+            (Some(device), offset) => self.devices[device].write(offset, value),
+            (None, address) => self.ram.write(address, value),
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        match self.resolve(address) {
+            (Some(device), offset) => self.devices[device].peek(offset),
+            (None, address) => self.ram.peek(address),
+        }
+    }
+    /// Only RAM is written. Devices and ROMs keep their own state.
+    fn poke(&mut self, address: u16, value: u8) {
+        if let (None, address) = self.resolve(address) {
+            self.ram.write(address, value);
+        }
+    }
+}
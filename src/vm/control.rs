@@ -11,7 +11,7 @@ pub mod prelude {
 
 /// Virtual machine addressing mode enum.
 ///
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Mode {
     Accumulator,
     Implied,
@@ -48,17 +48,34 @@ impl Debug for Mode {
     }
 }
 
+impl Mode {
+    /// Number of operand bytes following an opcode in this mode.
+    pub fn operand_len(&self) -> u16 {
+        match self {
+            Mode::Accumulator | Mode::Implied => 0,
+            Mode::Immediate
+            | Mode::ZeroPage
+            | Mode::ZeroPageX
+            | Mode::ZeroPageY
+            | Mode::Relative
+            | Mode::IndirectX
+            | Mode::IndirectY => 1,
+            Mode::Absolute | Mode::AbsoluteX | Mode::AbsoluteY | Mode::Indirect => 2,
+        }
+    }
+}
+
 pub trait InstructionController {
-    fn step(&mut self) -> u64;
+    fn step(&mut self) -> VmResult<u64>;
     // TODO: Abstract matches out of step so that you can get the ops then step with opcode.
     // fn opcode(&mut self, op: &str);
     // fn opcode(&mut self, op: u8);
 
     // TODO: Mode could be a macro, or other macros could be integrated. Consider this API decision more closely.
-    fn mode(&mut self, op: u8) -> Mode;
-    fn fetch(&mut self) -> u8;
-    fn fetch_byte(&mut self) -> u8;
-    fn apply(&mut self, address: u16, operation: fn(u8) -> u8) -> u8;
+    fn mode(&mut self, op: u8) -> VmResult<Mode>;
+    fn fetch(&mut self) -> VmResult<u8>;
+    fn address(&mut self) -> VmResult<u16>;
+    fn apply(&mut self, address: u16, operation: fn(u8) -> u8) -> VmResult<u8>;
 
     //
     fn relative_jump(&mut self, offset: u8, cond: bool);
@@ -67,7 +84,8 @@ pub trait InstructionController {
 /**
 Virtual machine core control functionality.
 
-This provides three main internal functions, `step`, `mode`, and `fetch`.
+This provides the main internal functions, `step`, `mode`, `fetch` and `address`.
+All memory accesses go through the machine's [Bus], using physical addresses.
 
 # Examples
 ## `step`
@@ -75,24 +93,24 @@ This provides three main internal functions, `step`, `mode`, and `fetch`.
 use vm6502::prelude::*;
 let mut vm = VirtualMachine::new();
 
-vm.insert_program(0x00, "69FFFF");
-vm.registers.pc = 0x00;
+vm.insert_program(0x00, "69FFFF").unwrap();
+vm.registers.pc = 0x0200;
 
-vm.step();
+vm.step().unwrap();
 
-assert_eq!(vm.flatmap[vm.registers.pc as usize + vm.heap_bounds.0], 0xFF);
+assert_eq!(vm.registers.pc, 0x0202);
+assert_eq!(vm.flatmap[vm.registers.pc as usize], 0xFF);
 ```
 ## `mode`
 ```
 use vm6502::prelude::*;
 
 let mut vm = VirtualMachine::new();
-let mode = vm.mode(0x69);
+let mode = vm.mode(0x69).unwrap();
 
 assert_eq!(mode, Mode::Immediate);
 ```
 ## `fetch`
-// TODO: Unignore this later.
 ```
 use vm6502::prelude::*;
 
@@ -100,121 +118,94 @@ let mut vm = VirtualMachine::new();
 let byte = 0x01;
 
 // 0x200 is heap start. See `VirtualMachine::heap_bounds`.
-vm.set_heap(0x0000, 0x69);
-vm.set_heap(0x0001, byte);
+vm.set_heap(0x0000, 0x69).unwrap();
+vm.set_heap(0x0001, byte).unwrap();
 
 assert_ne!(vm.flatmap[0x0001], byte, "Byte {} was not set to 0x0201", byte);
 assert_eq!(byte, vm.flatmap[0x0201], "Byte {} was not set at 0x0201", byte);
 
-// Should PC be 0x01 or two here?
-vm.registers.pc = 0x00;
+// The PC points past the opcode once it's been stepped.
+vm.registers.pc = 0x0201;
 vm.addr_mode = Mode::Immediate;
 
-let fetched = vm.fetch();
-assert_eq!(vm.registers.pc, 0x01, "PC should be incremented by 1 after fetch");
+let fetched = vm.fetch().unwrap();
+assert_eq!(vm.registers.pc, 0x0202, "PC should be incremented by 1 after fetch");
 
 assert_eq!(fetched, byte, "Fetched byte {} does not match expected byte {}", fetched, byte);
 ```
 */
-impl InstructionController for VirtualMachine {
-    // This can probably be combined with fetch byte in some way.
-    fn apply(&mut self, address: u16, operation: fn(u8) -> u8) -> u8 {
-        let doit = |d: &mut u8| -> u8 {
-            let r = operation(*d);
-            *d = r;
-            r
-        };
+impl<B: Bus> InstructionController for VirtualMachine<B> {
+    /// Apply `operation` to the byte at `address`, or to the accumulator, returning the result.
+    fn apply(&mut self, address: u16, operation: fn(u8) -> u8) -> VmResult<u8> {
+        if self.addr_mode == Mode::Accumulator {
+            self.registers.ac = operation(self.registers.ac);
+            return Ok(self.registers.ac);
+        }
 
-        match self.addr_mode {
-            Mode::Accumulator => doit(&mut self.registers.ac),
-            Mode::ZeroPage => doit(&mut self.flatmap[address as usize]),
-            Mode::ZeroPageX => {
-                doit(&mut self.flatmap[address as usize + self.registers.x as usize])
-            }
-            Mode::Absolute => doit(&mut self.flatmap[address as usize]),
-            Mode::AbsoluteX => {
-                doit(&mut self.flatmap[address as usize + self.registers.x as usize])
-            }
-            Mode::AbsoluteY => {
-                doit(&mut self.flatmap[address as usize + self.registers.y as usize])
-            }
-            Mode::Indirect => doit(&mut self.flatmap[address as usize]),
-            Mode::IndirectX => {
-                doit(&mut self.flatmap[address as usize + self.registers.x as usize])
-            }
-            Mode::IndirectY => {
-                doit(&mut self.flatmap[address as usize + self.registers.y as usize])
-            }
-            Mode::Immediate => doit(&mut self.flatmap[address as usize]),
-            Mode::Relative => doit(&mut self.flatmap[address as usize]),
-            Mode::Implied => doit(&mut self.flatmap[address as usize]),
-            Mode::ZeroPageY => {
-                doit(&mut self.flatmap[address as usize + self.registers.y as usize])
+        let result = operation(self.bus_read(address));
+        self.bus_write(address, result);
+
+        Ok(result)
+    }
+
+    /// Fetch the operand value for the current mode, moving the PC past it.
+    fn fetch(&mut self) -> VmResult<u8> {
+        let fetched = match self.addr_mode {
+            // OPC A
+            Mode::Accumulator => self.registers.ac,
+            // OPC
+            Mode::Implied => 0,
+            // OPC #$BB, OPC $BB
+            // Like every other operand byte, these are fetched without watchpoints.
+            Mode::Immediate | Mode::Relative => self.next_pc_byte()?,
+            _ => {
+                let address = self.address()?;
+                self.bus_read(address)
             }
-        }
+        };
+
+        Ok(fetched)
     }
 
-    /// Fetch the next byte from the program counter.
-    fn fetch(&mut self) -> u8 {
-        match self.addr_mode {
-            Mode::Absolute => {
-                // Todo can we move increments to get heap? We have to fix Relative to be
-                // parallel. I don't think so, because indirect fetching.
-                let ll = self.inc_pc_and_get_byte() as usize;
-                let hh = self.inc_pc_and_get_byte() as usize;
-
-                let offset = (hh << 2) | ll;
-                self.get_heap(offset as u16)
+    /// Resolve the effective address of the operand for the current mode, moving the PC past it.
+    ///
+    /// Accumulator and implied modes have no operand, and resolve to 0.
+    fn address(&mut self) -> VmResult<u16> {
+        let address = match self.addr_mode {
+            Mode::Accumulator | Mode::Implied => 0,
+            // OPC #$BB, OPC $BB
+            // The operand is the byte following the opcode.
+            Mode::Immediate | Mode::Relative => {
+                let address = self.registers.pc;
+                self.registers.pc = self.registers.pc.wrapping_add(1);
+                address
             }
+            // OPC $LL
+            Mode::ZeroPage => self.next_pc_byte()? as u16,
+            // OPC $LL,X
+            Mode::ZeroPageX => self.next_pc_byte()?.wrapping_add(self.registers.x) as u16,
+            // OPC $LL,Y
+            Mode::ZeroPageY => self.next_pc_byte()?.wrapping_add(self.registers.y) as u16,
+            // OPC $LLHH
+            Mode::Absolute => self.next_pc_word()?,
             // OPC $LLHH,X
             Mode::AbsoluteX => {
-                let ll = self.inc_pc_and_get_byte() as usize;
-                let hh = self.inc_pc_and_get_byte() as usize;
-
-                let offset = (hh << 2) | (ll + self.registers.x as usize);
-                self.get_heap(offset as u16)
+                let base = self.next_pc_word()?;
+                self.indexed(base, self.registers.x)
             }
             // OPC $LLHH,Y
             Mode::AbsoluteY => {
-                let ll = self.inc_pc_and_get_byte() as usize;
-                let hh = self.inc_pc_and_get_byte() as usize;
-
-                let offset = (hh << 2) | (ll + self.registers.y as usize);
-                self.get_heap(offset as u16)
+                let base = self.next_pc_word()?;
+                self.indexed(base, self.registers.y)
             }
-            _ => self.fetch_byte(),
-        }
-    }
-
-    fn fetch_byte(&mut self) -> u8 {
-        #[cfg(feature = "show_mode")]
-        println!("\n\tfetch mode: {:?}", self.addr_mode);
-
-        // TODO: Implement all PC incrementing.
-        let fetched: u8 = match self.addr_mode {
-            // OPC A
-            Mode::Accumulator => self.registers.ac,
-            // OPC $LLHH
-            // operand is address $HHLL
-            // OPC #$BB
-            Mode::Immediate => {
-                /*
-                TODO: Can we factor pc addition into get_heap?
-                For some reason this is off by one, control.rs:93
-                seems to be showing that we're fetching the previous byte.
-                */
-                // I believe we can also increment the cycles:
-                self.inc_pc_and_get_byte()
-            }
-            // OPC
-            Mode::Implied => 0,
             // OPC ($LLHH)
+            // The NMOS 6502 doesn't carry into the high byte of the pointer.
             Mode::Indirect => {
-                let ll = self.inc_pc_and_get_byte() as usize;
-                let hh = self.inc_pc_and_get_byte() as usize;
+                let pointer = self.next_pc_word()?;
+                let ll = self.bus_read(pointer);
+                let hh = self.bus_read((pointer & 0xFF00) | (pointer.wrapping_add(1) & 0x00FF));
 
-                let offset = (hh << 2) | ll;
-                self.get_heap(offset as u16)
+                u16::from_le_bytes([ll, hh])
             }
             /*
             OPC ($LL, X)
@@ -222,187 +213,171 @@ impl InstructionController for VirtualMachine {
             inc. without carry: C.w(0LL + X)
             */
             Mode::IndirectX => {
-                let ll = self.inc_pc_and_get_byte();
-                let ell = self.get_heap((ll + self.registers.x) as u16) as usize;
-                let ehh = self.get_heap((ll + self.registers.x + 1) as u16) as usize;
-
-                let offset = (ehh << 2) | ell;
-                self.get_heap(offset as u16)
+                let ll = self.next_pc_byte()?.wrapping_add(self.registers.x);
+                self.zero_page_word(ll)
             }
             /*
             OPC ($LL), Y
             operand is zeropage address; effective address is word in (LL, LL + 1)
             incremented by Y with carry: C.w(0LL) + Y
-            TODO: check if this is correct.
             */
             Mode::IndirectY => {
-                let ll = self.inc_pc_and_get_byte();
-                let ell = self.get_heap(ll as u16);
-                let ehh = self.get_heap(ll as u16);
-
-                let offset = (ehh << 2) | (ell + self.registers.y);
-                self.get_heap(offset as u16)
-            }
-            // OPC $BB
-            Mode::Relative => {
-                // TODO: Check if i should be setting this
-                self.inc_pc_and_get_byte()
-            }
-            // OPC $LL
-            Mode::ZeroPage => {
-                let ll = self.inc_pc_and_get_byte();
-                self.get_heap(ll as u16)
-            }
-            // OPC $LL, X
-            Mode::ZeroPageX => {
-                let ll = self.inc_pc_and_get_byte();
-                self.get_heap((ll + self.registers.x).into())
+                let ll = self.next_pc_byte()?;
+                let base = self.zero_page_word(ll);
+                self.indexed(base, self.registers.y)
             }
-            // OPC $LL, Y
-            Mode::ZeroPageY => {
-                self.registers.pc += 1;
-                let ll = self.inc_pc_and_get_byte();
-                self.get_heap((ll + self.registers.y).into())
-            }
-            _ => panic!(
-                "No way to be here in this address mode! {:?}",
-                self.addr_mode
-            ),
         };
 
-        #[cfg(feature = "show_fetched")]
-        println!(
-            "\n\tfetched value: {:02X} by mode: {:?}",
-            fetched, self.addr_mode
-        );
-
-        fetched
+        Ok(address)
     }
 
     // This is setting the offset for branch instructions inside of step(). (TODO: refactor step into get_op, step, then add run.)
     // Because we set the offset here, we don't set it in fetch(), instead we call it.
     // TODO convert all self.flatmap[self.heap_bounds.0 + ....] to a self::HeapInterface.read() fn call
     fn relative_jump(&mut self, fetched: u8, cond: bool) {
-        let offset = fetched as i16;
+        // The offset is a signed byte.
+        let offset = fetched as i8 as i16;
         let newpc = self.registers.pc.wrapping_add_signed(offset);
-        self.cycles += 2;
-
-        #[cfg(feature = "show_relative_offset")]
-        println!("\t\tRelative jump: 0x{:02X}", offset);
 
         if cond {
-            self.cycles += if check_page_cross!(self, newpc) { 2 } else { 1 };
+            // Taking the branch costs a cycle, and crossing a page another.
+            self.cycles += if check_page_cross!(self.registers.pc, newpc) {
+                2
+            } else {
+                1
+            };
             self.registers.pc = newpc;
-
-            #[cfg(feature = "show_relative_offset")]
-            println!("\t\t\tRelative jump taken. PC: {:04X}", self.registers.pc);
         }
     }
 
     /// Check the opcode and return the addressing mode.
+    ///
+    /// Undocumented opcodes are illegal unless [undocumented](VirtualMachine::undocumented) is set.
     #[allow(clippy::bad_bit_mask)]
     #[bitmatch]
-    fn mode(&mut self, op: u8) -> Mode {
+    fn mode(&mut self, op: u8) -> VmResult<Mode> {
+        let illegal = VmError::IllegalOpcode {
+            pc: self.registers.pc,
+            opcode: op,
+        };
+
+        if !valid_op(op) {
+            return match undocumented_mode(op) {
+                Some(mode) if self.undocumented => Ok(mode),
+                _ => Err(illegal),
+            };
+        }
+
         #[bitmatch]
         match op {
             "aaabbbcc" => match c {
                 0x00 => match b {
                     0x00 => match a {
-                        0x00 => Mode::Implied,
-                        0x01 => Mode::Absolute,
-                        0x02 | 0x03 => Mode::Implied,
-                        0x05..=0x07 => Mode::Immediate,
-                        _ => panic!("Illegal a value {} for cc0.(b=0x00)", a),
+                        0x00 => Ok(Mode::Implied),
+                        0x01 => Ok(Mode::Absolute),
+                        0x02 | 0x03 => Ok(Mode::Implied),
+                        0x05..=0x07 => Ok(Mode::Immediate),
+                        _ => Err(illegal),
                     },
                     0x01 => match a {
-                        0x01 => Mode::ZeroPage,
-                        0x04..=0x07 => Mode::ZeroPage,
-                        _ => panic!("Illegal a value {:02X} for cc0.(b=0x04..0x07)", a),
+                        0x01 => Ok(Mode::ZeroPage),
+                        0x04..=0x07 => Ok(Mode::ZeroPage),
+                        _ => Err(illegal),
                     },
-                    0x02 => Mode::Implied,
+                    0x02 => Ok(Mode::Implied),
                     0x03 => match a {
-                        0x00 => panic!("Illegal opcode 0x00 for cc0."),
-                        0x03 => Mode::Indirect,
-                        0x01 | 0x02 | 0x04..=0x07 => Mode::Absolute,
-                        _ => panic!("Illegal a value {} for cc0.(b=0x01..0x07)", a),
+                        0x00 => Err(illegal),
+                        0x03 => Ok(Mode::Indirect),
+                        0x01 | 0x02 | 0x04..=0x07 => Ok(Mode::Absolute),
+                        _ => Err(illegal),
                     },
-                    0x04 => Mode::Relative,
+                    0x04 => Ok(Mode::Relative),
                     0x05 => match a {
-                        0x04 | 0x05 => Mode::ZeroPageX,
-                        _ => panic!("Illegal a value {} for cc0.(b=0x04|0x05)", a),
+                        0x04 | 0x05 => Ok(Mode::ZeroPageX),
+                        _ => Err(illegal),
                     },
-                    0x06 => Mode::Implied,
+                    0x06 => Ok(Mode::Implied),
                     0x07 => match a {
-                        0x05 => Mode::AbsoluteX,
-                        _ => panic!("Illegal a value {} for cc0.", a),
+                        0x05 => Ok(Mode::AbsoluteX),
+                        _ => Err(illegal),
                     },
-                    _ => panic!("Invalid cc0 mode: {}", b),
+                    _ => Err(illegal),
                 },
                 0x01 => match b {
-                    0x00 => Mode::IndirectX,
-                    0x01 => Mode::ZeroPage,
+                    0x00 => Ok(Mode::IndirectX),
+                    0x01 => Ok(Mode::ZeroPage),
                     0x02 => match a {
-                        0x04 => panic!("Illegal opcode 0x04 for cc1.(b=0x02)"),
-                        _ => Mode::Immediate,
+                        0x04 => Err(illegal),
+                        _ => Ok(Mode::Immediate),
                     },
-                    0x03 => Mode::Absolute,
-                    0x04 => Mode::IndirectY,
-                    0x05 => Mode::ZeroPageX,
-                    0x06 => Mode::AbsoluteY,
-                    0x07 => Mode::AbsoluteX,
-                    _ => panic!("Invalid cc1 mode: {}", b),
+                    0x03 => Ok(Mode::Absolute),
+                    0x04 => Ok(Mode::IndirectY),
+                    0x05 => Ok(Mode::ZeroPageX),
+                    0x06 => Ok(Mode::AbsoluteY),
+                    0x07 => Ok(Mode::AbsoluteX),
+                    _ => Err(illegal),
                 },
                 0x02 => match b {
                     0x00 => match a {
-                        0x00 => Mode::Implied,
-                        0x05 => Mode::Immediate,
-                        _ => panic!("Illegal a value {} for cc2(b=0x00)", a),
+                        0x00 => Ok(Mode::Implied),
+                        0x05 => Ok(Mode::Immediate),
+                        _ => Err(illegal),
                     },
-                    0x01 => Mode::ZeroPage,
+                    0x01 => Ok(Mode::ZeroPage),
                     0x02 => match a {
-                        0x00..=0x03 => Mode::Accumulator,
-                        0x04..=0x07 => Mode::Implied,
-                        _ => panic!("Illegal a value {} for cc2(b=0x02)", a),
+                        0x00..=0x03 => Ok(Mode::Accumulator),
+                        0x04..=0x07 => Ok(Mode::Implied),
+                        _ => Err(illegal),
                     },
-                    0x03 => Mode::Absolute,
-                    0x04 => Mode::ZeroPageX,
+                    0x03 => Ok(Mode::Absolute),
+                    0x04 => Ok(Mode::ZeroPageX),
                     0x05 => match a {
-                        0x00..=0x03 | 0x06 | 0x07 => Mode::ZeroPageX,
-                        0x04 | 0x05 => Mode::ZeroPageY,
-                        _ => panic!("Illegal a value {} for cc2.(b=0x05)", a),
+                        0x00..=0x03 | 0x06 | 0x07 => Ok(Mode::ZeroPageX),
+                        0x04 | 0x05 => Ok(Mode::ZeroPageY),
+                        _ => Err(illegal),
                     },
                     0x06 => match a {
-                        0x04 | 0x05 => Mode::Implied,
-                        _ => panic!("Illegal a value {} for cc2.(b=0x06)", a),
+                        0x04 | 0x05 => Ok(Mode::Implied),
+                        _ => Err(illegal),
                     },
                     0x07 => match a {
-                        0x00..=0x03 | 0x06 | 0x07 => Mode::AbsoluteX,
-                        0x05 => Mode::AbsoluteY,
-                        _ => panic!("Illegal a value {} for cc2.(b=0x07)", a),
+                        0x00..=0x03 | 0x06 | 0x07 => Ok(Mode::AbsoluteX),
+                        0x05 => Ok(Mode::AbsoluteY),
+                        _ => Err(illegal),
                     },
-                    _ => panic!("Invalid cc2 mode: {}", b),
+                    _ => Err(illegal),
                 },
-                _ => panic!("Invalid mode: {}", c),
+                _ => Err(illegal),
             },
         }
     }
 
-    /// Execute an arbitrary op. It returns the vm's current `cycle` count.
+    /// Execute an arbitrary op. It returns the number of cycles the op took.
     #[bitmatch]
-    fn step(&mut self) -> u64 {
+    fn step(&mut self) -> VmResult<u64> {
+        self.record_step();
+
+        let start = self.cycles;
+        let sr = self.registers.sr;
+
+        // Interrupts are serviced between instructions.
+        if self.poll_interrupts()? {
+            self.notify_status(sr);
+            return Ok(self.cycles - start);
+        }
+
         // Get current op TODO: Implement internal virtual bounds.
-        let op = self.get_pc_byte();
+        let pc = self.registers.pc;
+        let op = self.get_pc_byte()?;
+        self.opcode = op;
+        self.notify(|o| o.on_fetch(pc, op));
         // Set internal mode.
-        let m = self.mode(op);
+        let m = self.mode(op)?;
+        self.notify(|o| o.on_decode(pc, op, m));
 
-        #[cfg(feature = "show_vm_step")]
-        println!(
-            "step: 0x{:04X}: OP=0x{:02X}, {:?}",
-            self.registers.pc, op, m
-        );
-
-        // Update internal state
+        // Update internal state, and move the PC past the opcode.
         self.addr_mode = m;
+        self.registers.pc = self.registers.pc.wrapping_add(1);
 
         // Increment PC for the OP fetched.
         // Logic says this should be done before, but maybe after?
@@ -428,338 +403,123 @@ impl InstructionController for VirtualMachine {
         #[allow(unused_variables)]
         #[bitmatch]
         match op {
-            "00000000" => {
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("BRK");
-                self.brk()
-            }
-            "01000000" => {
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("RTI");
-                self.rti()
-            }
-            "01100000" => {
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("RTS");
-                self.rts()
-            }
-            "aaabbb01" => {
-                #[cfg(feature = "show_vm_tick_arms")]
-                println!("\taaabbb01 arm, a={:02X}, b={:02X}", a, b);
-
-                match a {
-                    0x00 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tORA");
-                        self.ora()
-                    }
-                    0x01 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tAND");
-                        self.and()
-                    }
-                    0x02 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tEOR");
-                        self.eor()
-                    }
-                    0x03 => {
-                        //#[cfg(feature = "show_vm_instr_tick_match")]
-                        //println!("\t\tADC");
-                        self.adc()
-                    }
-                    0x04 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tSTA");
-                        self.sta()
-                    }
-                    0x05 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tLDA");
-                        self.lda()
-                    }
-                    0x06 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tCMP");
-                        self.cmp()
-                    }
-                    0x07 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tSBC");
-                        self.sbc();
-                    }
-                    _ => self.nop(),
-                }
-            }
-            "aaabbb10" => {
-                #[cfg(feature = "show_vm_tick_arms")]
-                println!("\taaabbb10 arm, a={:02X}, b={:02X}", a, b);
-
-                match a {
-                    0x00 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tASL");
-                        self.asl()
-                    }
-                    0x01 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tROL");
-                        self.rol()
-                    }
-                    0x02 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tLSR");
-                        self.lsr()
-                    }
-                    0x03 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tROR");
-                        self.ror()
-                    }
-                    0x04 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tSTX");
-                        self.stx()
-                    }
-                    0x05 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tLDX");
-                        self.ldx()
-                    }
-                    0x06 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tDEC");
-                        self.dec()
-                    }
-                    0x07 => {
-                        #[cfg(feature = "show_vm_instr_tick_match")]
-                        println!("\t\tINC");
-                        self.inc()
-                    }
-                    _ => self.nop(),
-                }
-            }
+            // `mode` only lets the undocumented ops through when they're enabled.
+            _ if !valid_op(op) => self.execute_undocumented(op)?,
+            "00000000" => self.brk()?,
+            "01000000" => self.rti()?,
+            "01100000" => self.rts()?,
+            // Single byte implied ops, matched before the generic arms they alias.
+            "00001000" => self.php()?,
+            "00101000" => self.plp()?,
+            "01001000" => self.pha()?,
+            "01101000" => self.pla()?,
+            "10001000" => self.dey()?,
+            "10101000" => self.tay()?,
+            "11001000" => self.iny()?,
+            "11101000" => self.inx()?,
+            "00011000" => self.clc()?,
+            "00111000" => self.sec()?,
+            "01011000" => self.cli()?,
+            "01111000" => self.sei()?,
+            "10011000" => self.tya()?,
+            "10111000" => self.clv()?,
+            "11011000" => self.cld()?,
+            "11111000" => self.sed()?,
+            "10001010" => self.txa()?,
+            "10011010" => self.txs()?,
+            "10101010" => self.tax()?,
+            "10111010" => self.tsx()?,
+            "11001010" => self.dex()?,
+            "11101010" => self.nop()?,
+            "aaabbb01" => match a {
+                0x00 => self.ora()?,
+                0x01 => self.and()?,
+                0x02 => self.eor()?,
+                0x03 => self.adc()?,
+                0x04 => self.sta()?,
+                0x05 => self.lda()?,
+                0x06 => self.cmp()?,
+                0x07 => self.sbc()?,
+                _ => self.nop()?,
+            },
+            "aaabbb10" => match a {
+                0x00 => self.asl()?,
+                0x01 => self.rol()?,
+                0x02 => self.lsr()?,
+                0x03 => self.ror()?,
+                0x04 => self.stx()?,
+                0x05 => self.ldx()?,
+                0x06 => self.dec()?,
+                0x07 => self.inc()?,
+                _ => self.nop()?,
+            },
             "aaabbb00" => {
-                #[cfg(feature = "show_vm_tick_arms")]
-                println!("\taaabbb00 arm, a={:02X}, b={:02X}", a, b);
-
                 // This is the only arm that triggers when op maps to Mode::Relative.
                 // Therefore, lets make the assumption that we can do the relative offset calculation here instead of in the fetch function.
                 // We are setting the PC
-                let offset = self.fetch();
-                println!("\t    Relative offset: 0x{:02X}", offset);
-
                 if b == 0b100 {
+                    let offset = self.fetch()?;
+
                     match a {
-                        0x00 => {
-                            self.bpl(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBPL");
-                        }
-                        0x01 => {
-                            self.bmi(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBMI");
-                        }
-                        0x02 => {
-                            self.bvc(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBVC");
-                        }
-                        0x03 => {
-                            self.bvs(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBVS");
-                        }
-                        0x04 => {
-                            self.bcc(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBCC");
-                        }
-                        0x05 => {
-                            self.bcs(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBCS");
-                        }
-                        0x06 => {
-                            self.bne(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBNE");
-                        }
-                        0x07 => {
-                            self.beq(offset);
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBEQ");
-                        }
-                        _ => self.nop(),
+                        0x00 => self.bpl(offset)?,
+                        0x01 => self.bmi(offset)?,
+                        0x02 => self.bvc(offset)?,
+                        0x03 => self.bvs(offset)?,
+                        0x04 => self.bcc(offset)?,
+                        0x05 => self.bcs(offset)?,
+                        0x06 => self.bne(offset)?,
+                        0x07 => self.beq(offset)?,
+                        _ => self.nop()?,
                     }
+                } else if op == 0x20 {
+                    self.jsr()?
                 } else {
                     match a {
-                        0x00 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tBIT");
-                            self.bit()
-                        }
-                        0x01 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tJSR");
-                            self.jsr()
-                        }
-                        0x02 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tJMP");
-                            self.jmp()
-                        }
-                        0x03 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tSTY");
-                            self.sty()
-                        }
-                        0x04 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tLDY");
-                            self.ldy()
-                        }
-                        0x05 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tCPY");
-                            self.cpy()
-                        }
-                        0x06 => {
-                            #[cfg(feature = "show_vm_instr_tick_match")]
-                            println!("\t\tCPX");
-                            self.cpx()
-                        }
-                        _ => self.nop(),
+                        0x01 => self.bit()?,
+                        0x02 | 0x03 => self.jmp()?,
+                        0x04 => self.sty()?,
+                        0x05 => self.ldy()?,
+                        0x06 => self.cpy()?,
+                        0x07 => self.cpx()?,
+                        _ => self.nop()?,
                     }
                 }
             }
-            // conditional jumps = aab10000
-            "xxx10000" => {
-                #[cfg(feature = "show_vm_tick_arms")]
-                println!("\txxx10000 arm, x={:02X}", x);
-            }
-            "00001000" => {
-                self.php();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tPHP");
-            }
-            "00101000" => {
-                self.plp();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tPLP");
-            }
-            "01001000" => {
-                self.pha();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tPHA");
-            }
-            "01101000" => {
-                self.pla();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tPLA");
-            }
-            "10001000" => {
-                self.dey();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tDEY");
-            }
-            "10101000" => {
-                self.tay();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tTAY");
-            }
-            "01001100" => {
-                self.iny();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tINY");
-            }
-            "11101000" => {
-                self.inx();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tINX");
-            }
-            "00011000" => {
-                self.clc();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tCLC");
-            }
-            "00111000" => {
-                self.sec();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tSEC");
-            }
-            "01011000" => {
-                self.cli();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tCLI");
-            }
-            "01111000" => {
-                self.sei();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tSEI");
-            }
-            "10011000" => {
-                self.tya();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tTYA");
-            }
-            "10111000" => {
-                self.clv();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tCLV");
-            }
-            "11011000" => {
-                self.cld();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tCLD");
-            }
-            "11111000" => {
-                self.sed();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tSED");
-            }
-            "10001010" => {
-                self.txa();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tTXA");
-            }
-            "10011010" => {
-                self.txs();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tTXS");
-            }
-            "10101010" => {
-                self.tax();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tTAX");
-            }
-            "10111010" => {
-                self.tsx();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tTSX");
-            }
-            "11001010" => {
-                self.dex();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tDEX");
-            }
-            _ => {
-                self.nop();
-                #[cfg(feature = "show_vm_instr_tick_match")]
-                println!("\t\tNOP");
-            }
+            _ => self.nop()?,
         };
 
-        #[cfg(feature = "show_vm_post_op")]
-        println!("{:?}", self);
+        // Penalties have already been counted by `address` and `relative_jump`.
+        self.cycles += OPCODE_CYCLES[op as usize] as u64;
+        self.notify_status(sr);
 
-        // This should be counting the consumed ops.
-        // Incrementing in vm.get_heap for now.
-        // self.cycles += 1;
-        self.registers.pc = self.registers.pc.wrapping_add(1);
+        Ok(self.cycles - start)
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Get the little endian word at the PC and increment the PC past it.
+    fn next_pc_word(&mut self) -> VmResult<u16> {
+        let ll = self.next_pc_byte()?;
+        let hh = self.next_pc_byte()?;
+
+        Ok(u16::from_le_bytes([ll, hh]))
+    }
+
+    /// Index `base` by `register`, counting the page cross penalty for the current op.
+    fn indexed(&mut self, base: u16, register: u8) -> u16 {
+        let address = base.wrapping_add(register as u16);
+        if page_cross_penalty(self.opcode) && check_page_cross!(base, address) {
+            self.cycles += 1;
+        }
+
+        address
+    }
+
+    /// Read the little endian word at zero page `address`, wrapping within the zero page.
+    fn zero_page_word(&mut self, address: u8) -> u16 {
+        let ll = self.bus_read(address as u16);
+        let hh = self.bus_read(address.wrapping_add(1) as u16);
 
-        // TODO: This should be updated (along with the PC) by the above commands.
-        self.cycles
+        u16::from_le_bytes([ll, hh])
     }
 }
@@ -0,0 +1,270 @@
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::debugger::{
+        Access, Breakpoint, DebugInterface, Debugger, Register, StopReason,
+    };
+}
+
+/// A register that can be compared in a [Breakpoint::Register] condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Ac,
+    X,
+    Y,
+    Sp,
+    Sr,
+}
+
+/// The kind of memory access a watchpoint triggers on, or that triggered it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// A condition that stops execution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Stop before executing the instruction at this address.
+    Pc(u16),
+    /// Stop after an instruction accesses memory in `start..=end`.
+    ///
+    /// Opcode and operand fetches and interrupt vector loads don't trigger watchpoints.
+    Watch {
+        start: u16,
+        end: u16,
+        access: Access,
+    },
+    /// Stop before an instruction while `register` holds `value`.
+    Register { register: Register, value: u8 },
+    /// Stop before an instruction while `flag` is `set`.
+    Flag { flag: Status, set: bool },
+}
+
+/// Why [execute](ProgramController::execute), [run](ProgramController::run) or one of the
+/// other run controls returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The machine halted.
+    Halted,
+    /// The undocumented JAM opcode at `pc` locked up the CPU.
+    Jammed { pc: u16 },
+    /// The PC reached the breakpoint `id`.
+    Breakpoint { id: usize, pc: u16 },
+    /// The watchpoint `id` saw an `access` at `address`.
+    Watchpoint {
+        id: usize,
+        address: u16,
+        access: Access,
+    },
+    /// The register or flag condition `id` was met.
+    Condition { id: usize },
+    /// The time given to [run](ProgramController::run) ran out.
+    TimedOut,
+    /// [step_over](ProgramController::step_over) or [step_out](ProgramController::step_out) finished.
+    StepComplete,
+    /// [run_until_pc](ProgramController::run_until_pc) reached its address.
+    ReachedPc(u16),
+    /// [run_for_instructions](ProgramController::run_for_instructions) ran its instructions.
+    InstructionLimit,
+    /// [run_for_cycles](ProgramController::run_for_cycles) ran its cycles.
+    CycleLimit,
+    /// The [run_until](ProgramController::run_until) predicate was met.
+    Predicate,
+    /// A frontend asked execution to pause.
+    Interrupted,
+}
+
+/// Breakpoint and watchpoint state of a [VirtualMachine].
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    breakpoints: Vec<(usize, Breakpoint)>,
+    next_id: usize,
+    /// Set when a watchpoint is hit during the current instruction.
+    hit: Option<StopReason>,
+    /// The PC execution stopped at, so resuming doesn't stop on the same breakpoint again.
+    resume: Option<u16>,
+}
+
+/// Debugger API over the execution loop.
+///
+/// Every breakpoint, watchpoint and condition gets a unique id which is reported in the
+/// [StopReason] and used to remove it.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let mut vm = VirtualMachine::new();
+/// // LDA #$01, STA $10, BRK
+/// vm.set_program(0x0000, "A901851000").unwrap();
+///
+/// let id = vm.add_breakpoint(Breakpoint::Watch {
+///     start: 0x10,
+///     end: 0x10,
+///     access: Access::Write,
+/// });
+///
+/// let reason = vm.execute().unwrap();
+/// assert_eq!(
+///     reason,
+///     StopReason::Watchpoint { id, address: 0x10, access: Access::Write }
+/// );
+/// assert_eq!(vm.registers.pc, 0x0204);
+/// ```
+pub trait DebugInterface {
+    /// Add a breakpoint, watchpoint or condition, returning its id.
+    fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize;
+    /// Remove the breakpoint `id`, returning whether it existed.
+    fn remove_breakpoint(&mut self, id: usize) -> bool;
+    /// Remove every breakpoint.
+    fn clear_breakpoints(&mut self);
+    /// Every breakpoint with its id, in the order they were added.
+    fn breakpoints(&self) -> &[(usize, Breakpoint)];
+
+    /// Step a single instruction, checking breakpoints before it and watchpoints during it.
+    ///
+    /// Returns `None` if execution can continue.
+    fn debug_step(&mut self) -> VmResult<Option<StopReason>>;
+}
+
+impl<B: Bus> DebugInterface for VirtualMachine<B> {
+    fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        let id = self.debugger.next_id;
+        self.debugger.next_id += 1;
+        self.debugger.breakpoints.push((id, breakpoint));
+
+        id
+    }
+
+    fn remove_breakpoint(&mut self, id: usize) -> bool {
+        let len = self.debugger.breakpoints.len();
+        self.debugger.breakpoints.retain(|(i, _)| *i != id);
+
+        len != self.debugger.breakpoints.len()
+    }
+
+    fn clear_breakpoints(&mut self) {
+        self.debugger.breakpoints.clear();
+    }
+
+    fn breakpoints(&self) -> &[(usize, Breakpoint)] {
+        &self.debugger.breakpoints
+    }
+
+    fn debug_step(&mut self) -> VmResult<Option<StopReason>> {
+        if self.jammed {
+            return Ok(Some(StopReason::Jammed {
+                pc: self.registers.pc,
+            }));
+        } else if self.halted {
+            return Ok(Some(StopReason::Halted));
+        }
+
+        let pc = self.registers.pc;
+        if self.debugger.resume != Some(pc) {
+            if let Some(reason) = self.check_breakpoints() {
+                self.debugger.resume = Some(pc);
+                return Ok(Some(reason));
+            }
+        }
+
+        self.debugger.resume = None;
+        self.debugger.hit = None;
+        self.step()?;
+
+        Ok(self.debugger.hit.take())
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Don't stop on a breakpoint at the current PC before the next instruction executes.
+    pub(crate) fn resume_here(&mut self) {
+        self.debugger.resume = Some(self.registers.pc);
+    }
+
+    /// Find the first PC breakpoint or condition met by the current state.
+    fn check_breakpoints(&self) -> Option<StopReason> {
+        let r = &self.registers;
+
+        self.debugger
+            .breakpoints
+            .iter()
+            .find_map(|(id, breakpoint)| match breakpoint {
+                Breakpoint::Pc(pc) if *pc == r.pc => {
+                    Some(StopReason::Breakpoint { id: *id, pc: *pc })
+                }
+                Breakpoint::Register { register, value } => {
+                    let current = match register {
+                        Register::Ac => r.ac,
+                        Register::X => r.x,
+                        Register::Y => r.y,
+                        Register::Sp => r.sp,
+                        Register::Sr => r.sr,
+                    };
+
+                    (current == *value).then_some(StopReason::Condition { id: *id })
+                }
+                Breakpoint::Flag { flag, set } => {
+                    (self.get_status(*flag) == *set).then_some(StopReason::Condition { id: *id })
+                }
+                _ => None,
+            })
+    }
+
+    /// Record the first watchpoint covering an `access` to `address`.
+    fn watch(&mut self, address: u16, access: Access) {
+        if self.debugger.hit.is_some() {
+            return;
+        }
+
+        self.debugger.hit = self
+            .debugger
+            .breakpoints
+            .iter()
+            .find_map(|(id, breakpoint)| match breakpoint {
+                Breakpoint::Watch {
+                    start,
+                    end,
+                    access: watched,
+                } if (*start..=*end).contains(&address)
+                    && (*watched == access || *watched == Access::ReadWrite) =>
+                {
+                    Some(StopReason::Watchpoint {
+                        id: *id,
+                        address,
+                        access,
+                    })
+                }
+                _ => None,
+            });
+    }
+
+    /// Read data from the bus, triggering read watchpoints.
+    pub(crate) fn bus_read(&mut self, address: u16) -> u8 {
+        if !self.debugger.breakpoints.is_empty() {
+            self.watch(address, Access::Read);
+        }
+
+        let value = self.flatmap.read(address);
+        self.notify(|o| o.on_read(address, value));
+
+        value
+    }
+
+    /// Write data to the bus, triggering write watchpoints.
+    pub(crate) fn bus_write(&mut self, address: u16, value: u8) {
+        if !self.debugger.breakpoints.is_empty() {
+            self.watch(address, Access::Write);
+        }
+
+        if self.history.is_some() {
+            self.record_write(address);
+        }
+
+        self.flatmap.write(address, value);
+        self.notify(|o| o.on_write(address, value));
+    }
+}
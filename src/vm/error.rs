@@ -0,0 +1,130 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::error::VmError;
+    pub use crate::vm::error::VmResult;
+}
+
+/// Result type of every fallible virtual machine operation.
+pub type VmResult<T> = std::result::Result<T, VmError>;
+
+/// Errors raised by the virtual machine.
+///
+/// Every error records the [PC](Registers::pc) and the opcode that was executing
+/// when it happened, so hosts can report or recover from guest faults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    /// The opcode is not a valid instruction, or is used with an unsupported mode.
+    IllegalOpcode { pc: u16, opcode: u8 },
+    /// A memory access fell outside of the machine memory or the heap bounds.
+    OutOfBounds { pc: u16, opcode: u8, address: usize },
+    /// A push was attempted on a full stack.
+    StackOverflow { pc: u16, opcode: u8 },
+    /// A pull was attempted on an empty stack.
+    StackUnderflow { pc: u16, opcode: u8 },
+    /// A program could not be decoded or does not fit in memory.
+    MalformedProgram { pc: u16, opcode: u8, reason: String },
+    /// A program file could not be read.
+    Io { pc: u16, opcode: u8, reason: String },
+    /// A snapshot is truncated, corrupt or of an unsupported version.
+    InvalidSnapshot { pc: u16, opcode: u8, reason: String },
+}
+
+impl VmError {
+    /// The PC at the time of the error.
+    pub fn pc(&self) -> u16 {
+        match self {
+            VmError::IllegalOpcode { pc, .. }
+            | VmError::OutOfBounds { pc, .. }
+            | VmError::StackOverflow { pc, .. }
+            | VmError::StackUnderflow { pc, .. }
+            | VmError::MalformedProgram { pc, .. }
+            | VmError::Io { pc, .. }
+            | VmError::InvalidSnapshot { pc, .. } => *pc,
+        }
+    }
+
+    /// The opcode being executed at the time of the error.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            VmError::IllegalOpcode { opcode, .. }
+            | VmError::OutOfBounds { opcode, .. }
+            | VmError::StackOverflow { opcode, .. }
+            | VmError::StackUnderflow { opcode, .. }
+            | VmError::MalformedProgram { opcode, .. }
+            | VmError::Io { opcode, .. }
+            | VmError::InvalidSnapshot { opcode, .. } => *opcode,
+        }
+    }
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            VmError::IllegalOpcode { .. } => write!(f, "illegal opcode")?,
+            VmError::OutOfBounds { address, .. } => {
+                write!(f, "out of bounds access at 0x{:04X}", address)?
+            }
+            VmError::StackOverflow { .. } => write!(f, "stack overflow")?,
+            VmError::StackUnderflow { .. } => write!(f, "stack underflow")?,
+            VmError::MalformedProgram { reason, .. } => write!(f, "malformed program: {}", reason)?,
+            VmError::Io { reason, .. } => write!(f, "io error: {}", reason)?,
+            VmError::InvalidSnapshot { reason, .. } => write!(f, "invalid snapshot: {}", reason)?,
+        }
+
+        write!(f, " (PC: 0x{:04X}, OP: 0x{:02X})", self.pc(), self.opcode())
+    }
+}
+
+impl std::error::Error for VmError {}
+
+/// Error constructors capturing the current machine state.
+impl<B: Bus> VirtualMachine<B> {
+    pub(crate) fn out_of_bounds(&self, address: usize) -> VmError {
+        VmError::OutOfBounds {
+            pc: self.registers.pc,
+            opcode: self.opcode,
+            address,
+        }
+    }
+
+    pub(crate) fn stack_overflow(&self) -> VmError {
+        VmError::StackOverflow {
+            pc: self.registers.pc,
+            opcode: self.opcode,
+        }
+    }
+
+    pub(crate) fn stack_underflow(&self) -> VmError {
+        VmError::StackUnderflow {
+            pc: self.registers.pc,
+            opcode: self.opcode,
+        }
+    }
+
+    pub(crate) fn malformed_program(&self, reason: impl Into<String>) -> VmError {
+        VmError::MalformedProgram {
+            pc: self.registers.pc,
+            opcode: self.opcode,
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn io_error(&self, reason: impl Into<String>) -> VmError {
+        VmError::Io {
+            pc: self.registers.pc,
+            opcode: self.opcode,
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn invalid_snapshot(&self, reason: impl Into<String>) -> VmError {
+        VmError::InvalidSnapshot {
+            pc: self.registers.pc,
+            opcode: self.opcode,
+            reason: reason.into(),
+        }
+    }
+}
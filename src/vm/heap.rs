@@ -9,53 +9,63 @@ pub mod prelude {
 
 /// Provides a low level interface for accessing the heap.
 ///
-/// It's simply a wrapper around the flatmap, using the internal [heap_bounds.0](VirtualMachine::heap_bounds) to index the heap.
+/// It's simply a wrapper around the [Bus], using the internal [heap_bounds.0](VirtualMachine::heap_bounds) to index the heap.
+/// The PC accessors use physical bus addresses.
 pub trait HeapInterface {
     // Low level iinterface
     /// Returns the value at the heap address given.
-    fn get_heap(&mut self, virt_addr: u16) -> u8;
+    fn get_heap(&mut self, virt_addr: u16) -> VmResult<u8>;
     /// Sets the value at the heap address given.
-    fn set_heap(&mut self, virt_addr: u16, byte: u8);
-    /// Get the byte on the heap at PC + offset.
-    fn get_pc_byte(&mut self) -> u8;
-    fn inc_pc_and_get_byte(&mut self) -> u8;
+    fn set_heap(&mut self, virt_addr: u16, byte: u8) -> VmResult<()>;
+    /// Get the byte at the PC.
+    fn get_pc_byte(&mut self) -> VmResult<u8>;
+    /// Get the byte at the PC and increment the PC past it.
+    ///
+    /// This replaces `inc_pc_and_get_byte`, which incremented the PC first and read the byte
+    /// after it.
+    fn next_pc_byte(&mut self) -> VmResult<u8>;
 
     // Mid level interface
     // return the bytes, 0xHH__ from the PC. More of a convenience/debug function.
     fn get_page_offset(&self) -> u8;
     // Add an the virt_addr to the high byte of the PC - it's a "magic" jump, bypassing modes.
-    fn set_page_offset(&mut self, virt_addr: u8);
+    fn set_page_offset(&mut self, virt_addr: u8) -> VmResult<()>;
 
-    fn bounds_check(&self, virt_addr: usize) -> bool;
+    fn bounds_check(&self, virt_addr: usize) -> VmResult<()>;
 }
 
-impl HeapInterface for VirtualMachine {
-    // TODO: Reimplement proper bounds checking.
-    fn get_heap(&mut self, virt_addr: u16) -> u8 {
+impl<B: Bus> HeapInterface for VirtualMachine<B> {
+    fn get_heap(&mut self, virt_addr: u16) -> VmResult<u8> {
         let addr = virt_addr as usize + self.heap_bounds.0;
 
         #[cfg(feature = "check_heap_bounds")]
-        self.bounds_check(addr);
+        self.bounds_check(addr)?;
 
-        self.flatmap[addr]
+        let addr = u16::try_from(addr).map_err(|_| self.out_of_bounds(addr))?;
+        Ok(self.flatmap.read(addr))
     }
 
-    fn get_pc_byte(&mut self) -> u8 {
-        self.get_heap(self.registers.pc)
+    fn get_pc_byte(&mut self) -> VmResult<u8> {
+        Ok(self.flatmap.read(self.registers.pc))
     }
 
-    fn inc_pc_and_get_byte(&mut self) -> u8 {
+    fn next_pc_byte(&mut self) -> VmResult<u8> {
+        let byte = self.get_pc_byte()?;
         self.registers.pc = self.registers.pc.wrapping_add(1);
-        self.get_heap(self.registers.pc)
+
+        Ok(byte)
     }
 
-    fn set_heap(&mut self, virt_addr: u16, byte: u8) {
+    fn set_heap(&mut self, virt_addr: u16, byte: u8) -> VmResult<()> {
         let addr = virt_addr as usize + self.heap_bounds.0;
 
         #[cfg(feature = "check_heap_bounds")]
-        self.bounds_check(addr);
+        self.bounds_check(addr)?;
+
+        let addr = u16::try_from(addr).map_err(|_| self.out_of_bounds(addr))?;
+        self.flatmap.write(addr, byte);
 
-        self.flatmap[addr] = byte;
+        Ok(())
     }
 
     /// Returns the page offset for the current PC.
@@ -66,39 +76,27 @@ impl HeapInterface for VirtualMachine {
     }
 
     /// Sets the PC to the given page offset.
-    fn set_page_offset(&mut self, virt_addr: u8) {
+    fn set_page_offset(&mut self, virt_addr: u8) -> VmResult<()> {
         let new_pc = (self.registers.pc & 0x00FF) | (virt_addr as u16) << 8;
         #[cfg(feature = "check_heap_bounds")]
-        self.bounds_check(new_pc as usize);
+        self.bounds_check(new_pc as usize)?;
 
         self.registers.pc = new_pc;
+        Ok(())
     }
 
-    /// Checks if the given address is within the heap bounds. TODO: Reimplement.
-    fn bounds_check(&self, virt_addr: usize) -> bool {
-        if virt_addr < self.heap_bounds.0 {
-            #[cfg(feature = "passthrough_failure")]
-            panic!("Attempted to access heap before heap bounds!");
-            #[cfg(not(feature = "passthrough_failure"))]
-            {
-                println!("Attempted access before virtual heap bounds!");
-                false
-            }
-        } else if virt_addr > self.heap_bounds.1 {
-            #[cfg(feature = "passthrough_failure")]
-            panic!("Attempted to access heap after heap bounds!");
-
-            #[cfg(not(feature = "passthrough_failure"))]
-            {
-                println!("Attempted access after virtual heap bounds!");
-                false
-            }
+    /// Checks if the given flatmap address is within the heap bounds.
+    fn bounds_check(&self, virt_addr: usize) -> VmResult<()> {
+        if virt_addr < self.heap_bounds.0 || virt_addr > self.heap_bounds.1 {
+            Err(self.out_of_bounds(virt_addr))
         } else {
-            true
+            Ok(())
         }
     }
 }
 
+// Not implemented yet.
+#[allow(dead_code)]
 pub trait HeapController {
     // High level interface
     fn alloc(&mut self);
@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::history::HistoryInterface;
+}
+
+/// The state needed to undo a single step.
+struct Entry {
+    registers: Registers,
+    addr_mode: Mode,
+    opcode: u8,
+    cycles: u64,
+    halted: bool,
+    jammed: bool,
+    nmi_pending: bool,
+    /// The old value of every byte written, in write order.
+    writes: Vec<(u16, u8)>,
+}
+
+/// A bounded undo log of executed steps.
+pub(crate) struct History {
+    capacity: usize,
+    entries: VecDeque<Entry>,
+}
+
+/// Reverse execution over a bounded history of steps.
+///
+/// While enabled, every [step](InstructionController::step) records the registers and the
+/// old value of each byte it writes. Only the most recent `capacity` steps are kept.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let mut vm = VirtualMachine::new();
+/// vm.enable_history(1024);
+/// // LDA #$42, STA $10
+/// vm.set_program(0x0000, "A9428510").unwrap();
+/// vm.step().unwrap();
+/// vm.step().unwrap();
+///
+/// assert!(vm.step_back());
+/// assert_eq!(vm.flatmap[0x10], 0x00);
+/// assert_eq!(vm.registers.pc, 0x0202);
+/// ```
+pub trait HistoryInterface {
+    /// Start recording up to `capacity` steps, discarding any existing history.
+    fn enable_history(&mut self, capacity: usize);
+    /// Stop recording and discard the history.
+    fn disable_history(&mut self);
+    /// The number of steps that can be undone.
+    fn history_len(&self) -> usize;
+
+    /// Undo the last step, returning false if there is no history left.
+    fn step_back(&mut self) -> bool;
+    /// Undo steps until the PC is `pc`, returning false if the history ran out first.
+    ///
+    /// At least one step is undone.
+    fn run_back_to_pc(&mut self, pc: u16) -> bool;
+    /// Undo steps until at least `cycles` cycles have been rewound, or the history runs out.
+    ///
+    /// Returns the number of cycles rewound.
+    fn rewind_cycles(&mut self, cycles: u64) -> u64;
+}
+
+impl<B: Bus> HistoryInterface for VirtualMachine<B> {
+    fn enable_history(&mut self, capacity: usize) {
+        self.history = Some(History {
+            capacity,
+            entries: VecDeque::with_capacity(capacity.min(0x10000)),
+        });
+    }
+
+    fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, |h| h.entries.len())
+    }
+
+    fn step_back(&mut self) -> bool {
+        let Some(entry) = self.history.as_mut().and_then(|h| h.entries.pop_back()) else {
+            return false;
+        };
+
+        for (address, value) in entry.writes.into_iter().rev() {
+            self.flatmap.write(address, value);
+        }
+
+        self.registers = entry.registers;
+        self.addr_mode = entry.addr_mode;
+        self.opcode = entry.opcode;
+        self.cycles = entry.cycles;
+        self.halted = entry.halted;
+        self.jammed = entry.jammed;
+        self.nmi_pending = entry.nmi_pending;
+
+        true
+    }
+
+    fn run_back_to_pc(&mut self, pc: u16) -> bool {
+        while self.step_back() {
+            if self.registers.pc == pc {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn rewind_cycles(&mut self, cycles: u64) -> u64 {
+        let start = self.cycles;
+        let target = start.saturating_sub(cycles);
+
+        while self.cycles > target && self.step_back() {}
+
+        start - self.cycles
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Discard the recorded steps, keeping recording enabled.
+    pub(crate) fn clear_history(&mut self) {
+        if let Some(history) = self.history.as_mut() {
+            history.entries.clear();
+        }
+    }
+
+    /// Begin a history entry for the step about to execute.
+    pub(crate) fn record_step(&mut self) {
+        let Some(history) = self.history.as_mut() else {
+            return;
+        };
+
+        if history.capacity == 0 {
+            return;
+        } else if history.entries.len() == history.capacity {
+            history.entries.pop_front();
+        }
+
+        history.entries.push_back(Entry {
+            registers: self.registers,
+            addr_mode: self.addr_mode,
+            opcode: self.opcode,
+            cycles: self.cycles,
+            halted: self.halted,
+            jammed: self.jammed,
+            nmi_pending: self.nmi_pending,
+            writes: Vec::new(),
+        });
+    }
+
+    /// Record the old value at `address` before the current step writes to it.
+    pub(crate) fn record_write(&mut self, address: u16) {
+        let old = self.flatmap.peek(address);
+
+        if let Some(entry) = self.history.as_mut().and_then(|h| h.entries.back_mut()) {
+            entry.writes.push((address, old));
+        }
+    }
+}
@@ -8,537 +8,680 @@ pub mod prelude {
 ///
 /// This is placed in a separate trait due to the inherent number of instructions.
 pub trait Instructions {
-    fn abs_zp_acc_op(&mut self, operation: fn(u8) -> u8) -> u8;
+    fn abs_zp_acc_op(&mut self, operation: fn(u8, bool) -> (u8, bool)) -> VmResult<u8>;
     /// Add with carry
-    fn adc(&mut self);
+    fn adc(&mut self) -> VmResult<()>;
     /// Logical AND
-    fn and(&mut self);
+    fn and(&mut self) -> VmResult<()>;
     /// Arithmetic shift left
-    fn asl(&mut self);
+    fn asl(&mut self) -> VmResult<()>;
 
     // Conditional instructions
     /// Branch on carry clear
-    fn bcc(&mut self, offset: u8);
+    fn bcc(&mut self, offset: u8) -> VmResult<()>;
     /// Branch on carry set
-    fn bcs(&mut self, offset: u8);
+    fn bcs(&mut self, offset: u8) -> VmResult<()>;
     /// Branch on equal (zero set)
-    fn beq(&mut self, offset: u8);
+    fn beq(&mut self, offset: u8) -> VmResult<()>;
     /// Branch on minus (negative set)
-    fn bne(&mut self, offset: u8);
+    fn bne(&mut self, offset: u8) -> VmResult<()>;
     /// Branch on plus (negative clear)
-    fn bpl(&mut self, offset: u8);
+    fn bpl(&mut self, offset: u8) -> VmResult<()>;
     // Branch on Minus (negative set)
-    fn bmi(&mut self, offset: u8);
+    fn bmi(&mut self, offset: u8) -> VmResult<()>;
     /// Branch on overflow clear
-    fn bvc(&mut self, offset: u8);
+    fn bvc(&mut self, offset: u8) -> VmResult<()>;
     /// Branch on overflow set
-    fn bvs(&mut self, offset: u8);
+    fn bvs(&mut self, offset: u8) -> VmResult<()>;
     /// Bit test
-    fn bit(&mut self);
+    fn bit(&mut self) -> VmResult<()>;
     /// Break
-    fn brk(&mut self);
+    fn brk(&mut self) -> VmResult<()>;
     /// Clear carry flag
-    fn clc(&mut self);
+    fn clc(&mut self) -> VmResult<()>;
     /// Clear decimal mode
-    fn cld(&mut self);
+    fn cld(&mut self) -> VmResult<()>;
     /// Clear interrupt disable bit
-    fn cli(&mut self);
+    fn cli(&mut self) -> VmResult<()>;
     /// Clear overflow flag
-    fn clv(&mut self);
+    fn clv(&mut self) -> VmResult<()>;
     /// Compare
-    fn cmp(&mut self);
+    fn cmp(&mut self) -> VmResult<()>;
     /// Compare X register
-    fn cpx(&mut self);
+    fn cpx(&mut self) -> VmResult<()>;
     /// Compare Y register
-    fn cpy(&mut self);
+    fn cpy(&mut self) -> VmResult<()>;
     /// Decrement memory
-    fn dec(&mut self);
+    fn dec(&mut self) -> VmResult<()>;
     /// Decrement X register
-    fn dex(&mut self);
+    fn dex(&mut self) -> VmResult<()>;
     /// Decrement Y register
-    fn dey(&mut self);
+    fn dey(&mut self) -> VmResult<()>;
     /// Exclusive OR
-    fn eor(&mut self);
+    fn eor(&mut self) -> VmResult<()>;
     /// Increment memory
-    fn inc(&mut self);
+    fn inc(&mut self) -> VmResult<()>;
     /// Increment X register
-    fn inx(&mut self);
+    fn inx(&mut self) -> VmResult<()>;
     /// Increment Y register
-    fn iny(&mut self);
+    fn iny(&mut self) -> VmResult<()>;
     /// Jump
-    fn jmp(&mut self);
+    fn jmp(&mut self) -> VmResult<()>;
     /// Jump to subroutine
-    fn jsr(&mut self);
+    fn jsr(&mut self) -> VmResult<()>;
     /// Load accumulator
-    fn lda(&mut self);
+    fn lda(&mut self) -> VmResult<()>;
     /// Load X register
-    fn ldx(&mut self);
+    fn ldx(&mut self) -> VmResult<()>;
     /// Load Y register
-    fn ldy(&mut self);
+    fn ldy(&mut self) -> VmResult<()>;
     /// Logical shift right
-    fn lsr(&mut self);
+    fn lsr(&mut self) -> VmResult<()>;
     /// No operation
-    fn nop(&mut self);
+    fn nop(&mut self) -> VmResult<()>;
     /// Logical inclusive OR
-    fn ora(&mut self);
+    fn ora(&mut self) -> VmResult<()>;
     /// Push accumulator
-    fn pha(&mut self);
+    fn pha(&mut self) -> VmResult<()>;
     /// Push processor status (SR)
-    fn php(&mut self);
+    fn php(&mut self) -> VmResult<()>;
     /// Pull accumulator
-    fn pla(&mut self);
+    fn pla(&mut self) -> VmResult<()>;
     /// Pull processor status (SR)
-    fn plp(&mut self);
+    fn plp(&mut self) -> VmResult<()>;
     /// Rotate left
-    fn rol(&mut self);
+    fn rol(&mut self) -> VmResult<()>;
     /// Rotate right
-    fn ror(&mut self);
+    fn ror(&mut self) -> VmResult<()>;
     /// Return from interrupt
-    fn rti(&mut self);
+    fn rti(&mut self) -> VmResult<()>;
     /// Return from subroutine
-    fn rts(&mut self);
+    fn rts(&mut self) -> VmResult<()>;
     /// Subtract with carry
-    fn sbc(&mut self);
+    fn sbc(&mut self) -> VmResult<()>;
     /// Set carry flag
-    fn sec(&mut self);
+    fn sec(&mut self) -> VmResult<()>;
     /// Set decimal mode
-    fn sed(&mut self);
+    fn sed(&mut self) -> VmResult<()>;
     /// Set interrupt disable status
-    fn sei(&mut self);
+    fn sei(&mut self) -> VmResult<()>;
     /// Store accumulator
-    fn sta(&mut self);
+    fn sta(&mut self) -> VmResult<()>;
     /// Store X register
-    fn stx(&mut self);
+    fn stx(&mut self) -> VmResult<()>;
     /// Store Y register
-    fn sty(&mut self);
+    fn sty(&mut self) -> VmResult<()>;
     /// Transfer accumulator to X
-    fn tax(&mut self);
+    fn tax(&mut self) -> VmResult<()>;
     /// Transfer accumulator to Y
-    fn tay(&mut self);
+    fn tay(&mut self) -> VmResult<()>;
     /// Transfer stack pointer to X
-    fn tsx(&mut self);
+    fn tsx(&mut self) -> VmResult<()>;
     /// Transfer X to accumulator
-    fn txa(&mut self);
+    fn txa(&mut self) -> VmResult<()>;
     /// Transfer X to stack pointer
-    fn txs(&mut self);
+    fn txs(&mut self) -> VmResult<()>;
     /// Transfer Y to accumulator
-    fn tya(&mut self);
+    fn tya(&mut self) -> VmResult<()>;
 }
 
-impl Instructions for VirtualMachine {
-    fn brk(&mut self) {
+impl<B: Bus> Instructions for VirtualMachine<B> {
+    fn brk(&mut self) -> VmResult<()> {
         // Stop vm execution if we try incrementing from 0xFFFF
         // Not spec compliant.
         self.halted = true;
 
-        // This is emulating overflow.
-        self.registers.pc = self.registers.pc.wrapping_add(1);
-
-        self.push((self.registers.pc >> 8) as u8);
-        // Skipping the next bytes for the return address
+        // Skipping the padding byte for the return address
         // https://retrocomputingforum.com/t/reading-the-6502-break-mark-and-how-fast-was-the-6502-back-in-the-day/2618
-        self.push((self.registers.pc + 2) as u8);
+        self.registers.pc = self.registers.pc.wrapping_add(1);
 
         // Set the break flag inline, as it's not actually set in the status register.
-        self.push(self.registers.sr | 0x10);
-        self.set_status(Status::Interrupt, true);
-
         // Load the interrupt vector from 0xFFFE and 0xFFFF.
-        // Subtracting heap offset to get the actual address.
-        let jump = ((self.get_heap(0xFFFF - self.heap_bounds.0 as u16) as u16) << 8)
-            | self.get_heap(0xFFFE - self.heap_bounds.0 as u16) as u16;
-
-        self.registers.pc = jump;
+        self.interrupt(self.irq_bounds, true)
     }
 
-    fn adc(&mut self) {
-        let value = self.fetch(); // Fetch is directed by the internal mode.
+    /// Add with carry. In decimal mode this follows the NMOS 6502, where N, V and Z are
+    /// set from the intermediate binary result, unless the `cmos` feature is enabled.
+    fn adc(&mut self) -> VmResult<()> {
+        let value = self.fetch()?; // Fetch is directed by the internal mode.
+        self.add_with_carry(value);
 
-        let result = self.registers.ac as u16 + value as u16;
-        let (carry, carried) = (result > 0xFF, result & 0xFF);
-
-        self.registers.ac = carried as u8;
-        self.set_status(Status::Carry, carry);
+        Ok(())
     }
 
-    fn sbc(&mut self) {
-        let value = self.fetch(); // Fetch is directed by the internal mode.
-
-        let result = self.registers.ac as u16 - value as u16;
-        let (carry, carried) = (result > 0xFF, result & 0xFF);
+    /// Subtract with borrow. In decimal mode this follows the NMOS 6502, where every flag is
+    /// set from the binary result, unless the `cmos` feature is enabled.
+    fn sbc(&mut self) -> VmResult<()> {
+        let value = self.fetch()?; // Fetch is directed by the internal mode.
+        self.subtract_with_borrow(value);
 
-        self.registers.ac = carried as u8;
-        self.set_status(Status::Carry, carry);
+        Ok(())
     }
 
-    fn and(&mut self) {
-        let value = self.fetch();
-
-        #[cfg(feature = "show_vm_instr")]
-        eprintln!(
-            "AND: {:02X} & fetched({:02X}), mode: {:?}",
-            self.registers.ac, value, self.addr_mode
-        );
+    fn and(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
 
         self.registers.ac &= value;
 
         self.set_status(Status::Zero, self.registers.ac == 0);
         self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn eor(&mut self) {
-        let value = self.fetch();
+    fn eor(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
         self.registers.ac ^= value;
 
         self.set_status(Status::Zero, self.registers.ac == 0);
         self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn ora(&mut self) {
-        let data = self.fetch();
+    fn ora(&mut self) -> VmResult<()> {
+        let data = self.fetch()?;
         self.registers.ac |= data;
 
         self.set_status(Status::Zero, self.registers.ac == 0);
         self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn bcc(&mut self, offset: u8) {
+    fn bcc(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, !self.get_status(Status::Carry));
+        Ok(())
     }
 
-    fn bcs(&mut self, offset: u8) {
+    fn bcs(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, self.get_status(Status::Carry));
+        Ok(())
     }
 
-    fn beq(&mut self, offset: u8) {
+    fn beq(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, self.get_status(Status::Zero));
+        Ok(())
     }
 
-    fn bne(&mut self, offset: u8) {
+    fn bne(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, !self.get_status(Status::Zero));
+        Ok(())
     }
 
-    fn bpl(&mut self, offset: u8) {
+    fn bpl(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, !self.get_status(Status::Negative));
+        Ok(())
     }
 
-    fn bvc(&mut self, offset: u8) {
+    fn bvc(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, !self.get_status(Status::Overflow));
+        Ok(())
     }
 
-    fn bvs(&mut self, offset: u8) {
+    fn bvs(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, self.get_status(Status::Overflow));
+        Ok(())
     }
 
-    fn bmi(&mut self, offset: u8) {
+    fn bmi(&mut self, offset: u8) -> VmResult<()> {
         self.relative_jump(offset, self.get_status(Status::Negative));
+        Ok(())
     }
 
     // Incrementing OPs.
-    fn dec(&mut self) {
-        let addr = self.fetch();
+    fn dec(&mut self) -> VmResult<()> {
+        let addr = self.address()?;
         let operation = |value: u8| value.wrapping_sub(1);
-        self.apply(addr as u16, operation);
+        let result = self.apply(addr, operation)?;
+
+        self.set_status(Status::Zero, result == 0);
+        self.set_status(Status::Negative, result & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn inc(&mut self) {
-        let addr = self.fetch();
+    fn inc(&mut self) -> VmResult<()> {
+        let addr = self.address()?;
         let operation = |value: u8| value.wrapping_add(1);
-        self.apply(addr as u16, operation);
+        let result = self.apply(addr, operation)?;
+
+        self.set_status(Status::Zero, result == 0);
+        self.set_status(Status::Negative, result & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn dex(&mut self) {
+    fn dex(&mut self) -> VmResult<()> {
         self.registers.x = self.registers.x.wrapping_sub(1);
         self.set_status(Status::Zero, self.registers.x == 0);
         self.set_status(Status::Negative, self.registers.x & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn dey(&mut self) {
+    fn dey(&mut self) -> VmResult<()> {
         self.registers.y = self.registers.y.wrapping_sub(1);
         self.set_status(Status::Zero, self.registers.y == 0);
         self.set_status(Status::Negative, self.registers.y & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn inx(&mut self) {
+    fn inx(&mut self) -> VmResult<()> {
         self.registers.x = self.registers.x.wrapping_add(1);
         self.set_status(Status::Zero, self.registers.x == 0);
         self.set_status(Status::Negative, self.registers.x & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn iny(&mut self) {
+    fn iny(&mut self) -> VmResult<()> {
         self.registers.y = self.registers.y.wrapping_add(1);
         self.set_status(Status::Zero, self.registers.y == 0);
         self.set_status(Status::Negative, self.registers.y & 0x80 != 0);
+
+        Ok(())
     }
 
     /// Load Instructions;
-    fn lda(&mut self) {
-        let data = self.fetch();
+    fn lda(&mut self) -> VmResult<()> {
+        let data = self.fetch()?;
         self.registers.ac = data;
 
         self.set_status(Status::Zero, data == 0);
         self.set_status(Status::Negative, data & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn ldx(&mut self) {
-        let data = self.fetch();
+    fn ldx(&mut self) -> VmResult<()> {
+        let data = self.fetch()?;
         self.registers.x = data;
 
         self.set_status(Status::Zero, data == 0);
         self.set_status(Status::Negative, data & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn ldy(&mut self) {
-        let data = self.fetch();
+    fn ldy(&mut self) -> VmResult<()> {
+        let data = self.fetch()?;
         self.registers.y = data;
 
         self.set_status(Status::Zero, data == 0);
         self.set_status(Status::Negative, data & 0x80 != 0);
+
+        Ok(())
     }
 
     // Comparison OPs
-    fn bit(&mut self) {
-        let value = self.fetch();
+    fn bit(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
         let result = self.registers.ac & value;
 
         self.set_status(Status::Zero, result == 0);
         self.set_status(Status::Negative, value & 0x80 != 0);
         self.set_status(Status::Overflow, value & 0x40 != 0);
+
+        Ok(())
     }
 
-    fn cmp(&mut self) {
-        let value = self.fetch();
-        let result = self.registers.ac as u16 - value as u16;
+    fn cmp(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
+        let result = (self.registers.ac as u16).wrapping_sub(value as u16);
 
         self.set_status(Status::Carry, result < 0x100);
         self.set_status(Status::Zero, result == 0);
         self.set_status(Status::Negative, result & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn cpx(&mut self) {
-        let value = self.fetch();
-        let result = self.registers.x as u16 - value as u16;
+    fn cpx(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
+        let result = (self.registers.x as u16).wrapping_sub(value as u16);
 
         self.set_status(Status::Carry, result < 0x100);
         self.set_status(Status::Zero, result == 0);
         self.set_status(Status::Negative, result & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn cpy(&mut self) {
-        let value = self.fetch();
-        let result = self.registers.y as u16 - value as u16;
+    fn cpy(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
+        let result = (self.registers.y as u16).wrapping_sub(value as u16);
 
         self.set_status(Status::Carry, result < 0x100);
         self.set_status(Status::Zero, result == 0);
         self.set_status(Status::Negative, result & 0x80 != 0);
+
+        Ok(())
     }
 
     // TODO: Move to separate mod, general_instructions?
     /// Accumulator <-> ZeroPage, Absolute, ZeroPageX and AbsoluteX
-    fn abs_zp_acc_op(&mut self, operation: fn(u8) -> u8) -> u8 {
-        let data = self.fetch();
+    ///
+    /// `operation` takes the value and carry in, and returns the result and carry out.
+    fn abs_zp_acc_op(&mut self, operation: fn(u8, bool) -> (u8, bool)) -> VmResult<u8> {
+        let address = self.address()?;
+        let value = match self.addr_mode {
+            Mode::Accumulator => self.registers.ac,
+            _ => self.bus_read(address),
+        };
 
         // Performs an operation and moves it to destination.
-        let result = self.apply(data as u16, operation);
+        let (result, carry) = operation(value, self.get_status(Status::Carry));
+        match self.addr_mode {
+            Mode::Accumulator => self.registers.ac = result,
+            _ => self.bus_write(address, result),
+        }
 
-        self.set_status(Status::Carry, result & 0x01 != 0);
+        self.set_status(Status::Carry, carry);
         self.set_status(Status::Zero, result == 0);
         self.set_status(Status::Negative, result & 0x80 != 0);
 
-        data
+        Ok(result)
     }
 
-    fn lsr(&mut self) {
-        self.abs_zp_acc_op(|d| d >> 1);
+    fn lsr(&mut self) -> VmResult<()> {
+        self.abs_zp_acc_op(|d, _| (d >> 1, d & 0x01 != 0))?;
+        Ok(())
     }
 
-    fn asl(&mut self) {
-        self.abs_zp_acc_op(|d| d << 1);
+    fn asl(&mut self) -> VmResult<()> {
+        self.abs_zp_acc_op(|d, _| (d << 1, d & 0x80 != 0))?;
+        Ok(())
     }
 
-    fn rol(&mut self) {
-        let data = self.abs_zp_acc_op(|d| d << 1);
-        if self.get_status(Status::Carry) {
-            self.apply(data as u16, |d| d | 0x01);
-        };
+    fn rol(&mut self) -> VmResult<()> {
+        self.abs_zp_acc_op(|d, c| (d << 1 | c as u8, d & 0x80 != 0))?;
+        Ok(())
     }
 
-    fn ror(&mut self) {
-        let data = self.abs_zp_acc_op(|d| d >> 1);
-        let sts = self.get_status(Status::Carry);
-        if sts {
-            match self.addr_mode {
-                Mode::Accumulator => self.registers.ac |= 0x80,
-                Mode::ZeroPage => self.flatmap[data as usize] |= 0x80,
-                Mode::ZeroPageX => self.flatmap[(data + self.registers.x) as usize] |= 0x80,
-                Mode::Absolute => self.flatmap[data as usize] |= 0x80,
-                Mode::AbsoluteX => self.flatmap[(data + self.registers.x) as usize] |= 0x80,
-
-                _ => panic!("Invalid addressing mode for ROL"),
-            }
-        };
+    fn ror(&mut self) -> VmResult<()> {
+        self.abs_zp_acc_op(|d, c| (d >> 1 | (c as u8) << 7, d & 0x01 != 0))?;
+        Ok(())
     }
 
     // Jumping/Procedure OPs
-    fn jmp(&mut self) {
-        let haddr = self.fetch();
-        let laddr = self.fetch();
+    fn jmp(&mut self) -> VmResult<()> {
+        self.registers.pc = self.address()?;
 
-        self.registers.pc = (haddr as u16) << 8 | laddr as u16;
+        Ok(())
     }
 
-    fn jsr(&mut self) {
-        let haddr = self.fetch();
-        let laddr = self.fetch();
+    fn jsr(&mut self) -> VmResult<()> {
+        let target = self.address()?;
+
+        let pc = self.registers.pc.wrapping_sub(1);
+        self.push((pc >> 8) as u8)?;
+        self.push(pc as u8)?;
 
-        let pc = self.registers.pc - 1;
-        self.push((pc >> 8) as u8);
-        self.push(pc as u8);
+        self.registers.pc = target;
 
-        self.registers.pc = (haddr as u16) << 8 | laddr as u16;
+        Ok(())
     }
 
-    fn rti(&mut self) {
-        let sts = self.pop();
+    fn rti(&mut self) -> VmResult<()> {
+        let sts = self.pop()?;
         // Pull SR and ignore BRK and bit 5.
-        self.registers.sr = sts & 0b0011_1111;
+        self.registers.sr = sts & 0b1100_1111;
         // Pull PC
-        self.registers.pc = self.pop() as u16;
-        self.registers.pc |= (self.pop() as u16) << 8;
+        self.registers.pc = self.pop()? as u16;
+        self.registers.pc |= (self.pop()? as u16) << 8;
+
+        Ok(())
     }
 
-    fn rts(&mut self) {
+    fn rts(&mut self) -> VmResult<()> {
         // Pull PC from stack.
-        let addr = self.pop() as u16;
-        let addr = addr | (self.pop() as u16) << 8;
+        let addr = self.pop()? as u16;
+        let addr = addr | (self.pop()? as u16) << 8;
 
-        self.registers.pc = addr + 1;
+        self.registers.pc = addr.wrapping_add(1);
+
+        Ok(())
     }
 
     // Flag set OPs
-    fn clc(&mut self) {
+    fn clc(&mut self) -> VmResult<()> {
         self.set_status(Status::Carry, false);
+        Ok(())
     }
 
-    fn cld(&mut self) {
+    fn cld(&mut self) -> VmResult<()> {
         self.set_status(Status::Decimal, false);
+        Ok(())
     }
 
-    fn cli(&mut self) {
+    fn cli(&mut self) -> VmResult<()> {
         self.set_status(Status::Interrupt, false);
+        Ok(())
     }
 
-    fn clv(&mut self) {
+    fn clv(&mut self) -> VmResult<()> {
         self.set_status(Status::Overflow, false);
+        Ok(())
     }
 
-    fn sec(&mut self) {
+    fn sec(&mut self) -> VmResult<()> {
         self.set_status(Status::Carry, true);
+        Ok(())
     }
 
-    fn sed(&mut self) {
+    fn sed(&mut self) -> VmResult<()> {
         self.set_status(Status::Decimal, true);
+        Ok(())
     }
 
-    fn sei(&mut self) {
+    fn sei(&mut self) -> VmResult<()> {
         // Technically interrupt disable.
         self.set_status(Status::Interrupt, true);
+
+        Ok(())
     }
 
     // Store Operations
-    fn sta(&mut self) {
-        let data = self.fetch();
-        // TODO: This code duplication can likely be refactored, similarly to ROL/ROR, but more general?
-        // I've made some glaring assumptions about the 6502 that are catching up now.
-        match self.addr_mode {
-            Mode::ZeroPage => self.flatmap[data as usize] = self.registers.ac,
-            Mode::ZeroPageX => self.flatmap[(data + self.registers.x) as usize] = self.registers.ac,
-            Mode::Absolute => self.flatmap[data as usize] = self.registers.ac,
-            Mode::AbsoluteX => self.flatmap[(data + self.registers.x) as usize] = self.registers.ac,
-            Mode::AbsoluteY => self.flatmap[(data + self.registers.y) as usize] = self.registers.ac,
-            Mode::IndirectX => self.flatmap[(data + self.registers.x) as usize] = self.registers.ac,
-            Mode::IndirectY => self.flatmap[(data + self.registers.y) as usize] = self.registers.ac,
-            _ => panic!("Invalid addressing mode for STA"),
-        }
+    fn sta(&mut self) -> VmResult<()> {
+        let address = self.address()?;
+        self.bus_write(address, self.registers.ac);
+
+        Ok(())
     }
 
-    fn stx(&mut self) {
-        let data = self.fetch();
-        match self.addr_mode {
-            Mode::ZeroPage => self.flatmap[data as usize] = self.registers.x,
-            Mode::ZeroPageY => self.flatmap[(data + self.registers.y) as usize] = self.registers.x,
-            Mode::Absolute => self.flatmap[data as usize] = self.registers.x,
-            _ => panic!("Invalid addressing mode for STX"),
-        }
+    fn stx(&mut self) -> VmResult<()> {
+        let address = self.address()?;
+        self.bus_write(address, self.registers.x);
+
+        Ok(())
     }
 
-    fn sty(&mut self) {
-        let data = self.fetch();
-        match self.addr_mode {
-            Mode::ZeroPage => self.flatmap[data as usize] = self.registers.y,
-            Mode::ZeroPageX => self.flatmap[(data + self.registers.x) as usize] = self.registers.y,
-            Mode::Absolute => self.flatmap[data as usize] = self.registers.y,
-            _ => panic!("Invalid addressing mode for STY"),
-        }
+    fn sty(&mut self) -> VmResult<()> {
+        let address = self.address()?;
+        self.bus_write(address, self.registers.y);
+
+        Ok(())
     }
 
     // Transfer register Ops.
-    fn tax(&mut self) {
+    fn tax(&mut self) -> VmResult<()> {
         self.registers.x = self.registers.ac;
         // TODO: We can definitely create a helper for the transfer instructions.
         self.set_status(Status::Zero, self.registers.x == 0);
         self.set_status(Status::Negative, self.registers.x & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn txa(&mut self) {
+    fn txa(&mut self) -> VmResult<()> {
         self.registers.ac = self.registers.x;
         self.set_status(Status::Zero, self.registers.ac == 0);
         self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn tay(&mut self) {
+    fn tay(&mut self) -> VmResult<()> {
         self.registers.y = self.registers.ac;
         self.set_status(Status::Zero, self.registers.y == 0);
         self.set_status(Status::Negative, self.registers.y & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn tya(&mut self) {
+    fn tya(&mut self) -> VmResult<()> {
         self.registers.ac = self.registers.y;
         self.set_status(Status::Zero, self.registers.ac == 0);
         self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn tsx(&mut self) {
+    fn tsx(&mut self) -> VmResult<()> {
         self.registers.x = self.registers.sp;
         self.set_status(Status::Zero, self.registers.x == 0);
         self.set_status(Status::Negative, self.registers.x & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn txs(&mut self) {
+    fn txs(&mut self) -> VmResult<()> {
         self.registers.sp = self.registers.x;
+        Ok(())
     }
 
     // Register Push/Pull Ops
-    fn pha(&mut self) {
-        self.push(self.registers.ac);
+    fn pha(&mut self) -> VmResult<()> {
+        self.push(self.registers.ac)?;
+        Ok(())
     }
 
-    fn php(&mut self) {
-        self.push(self.registers.sr);
+    fn php(&mut self) -> VmResult<()> {
+        self.push(self.registers.sr)?;
+        Ok(())
     }
 
-    fn pla(&mut self) {
-        let sts = self.pop();
+    fn pla(&mut self) -> VmResult<()> {
+        let sts = self.pop()?;
         self.registers.ac = sts;
 
         self.set_status(Status::Zero, sts == 0);
         self.set_status(Status::Negative, sts & 0x80 != 0);
+
+        Ok(())
     }
 
-    fn plp(&mut self) {
-        let sts = self.pop();
+    fn plp(&mut self) -> VmResult<()> {
+        let sts = self.pop()?;
         self.registers.sr = sts;
+
+        Ok(())
     }
 
-    fn nop(&mut self) {}
+    fn nop(&mut self) -> VmResult<()> {
+        Ok(())
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Add `value` and the carry to the accumulator, see [adc](Instructions::adc).
+    pub(crate) fn add_with_carry(&mut self, value: u8) {
+        let (ac, carry) = (self.registers.ac, self.get_status(Status::Carry) as u16);
+
+        let binary = ac as u16 + value as u16 + carry;
+        let overflow = |result: u16| !(ac ^ value) as u16 & (ac as u16 ^ result) & 0x80 != 0;
+
+        if !self.get_status(Status::Decimal) {
+            self.registers.ac = binary as u8;
+            self.set_status(Status::Carry, binary > 0xFF);
+            self.set_status(Status::Overflow, overflow(binary));
+            self.set_status(Status::Zero, self.registers.ac == 0);
+            self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+            return;
+        }
+
+        // Add each BCD digit, adjusting them back into 0..=9.
+        let mut low = (ac & 0x0F) as u16 + (value & 0x0F) as u16 + carry;
+        if low >= 0x0A {
+            low = ((low + 0x06) & 0x0F) + 0x10;
+        }
+
+        let mut result = (ac & 0xF0) as u16 + (value & 0xF0) as u16 + low;
+        let (negative, overflow) = (result & 0x80 != 0, overflow(result));
+        if result >= 0xA0 {
+            result += 0x60;
+        }
+
+        self.registers.ac = result as u8;
+        self.set_status(Status::Carry, result > 0xFF);
+        self.set_status(Status::Overflow, overflow);
+
+        if cfg!(feature = "cmos") {
+            // The 65C02 takes an extra cycle to correct the flags.
+            self.cycles += 1;
+            self.set_status(Status::Zero, self.registers.ac == 0);
+            self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+        } else {
+            self.set_status(Status::Zero, binary & 0xFF == 0);
+            self.set_status(Status::Negative, negative);
+        }
+    }
+
+    /// Subtract `value` and the borrow from the accumulator, see [sbc](Instructions::sbc).
+    pub(crate) fn subtract_with_borrow(&mut self, value: u8) {
+        let (ac, carry) = (self.registers.ac, self.get_status(Status::Carry));
+
+        // Binary subtraction is addition of the ones complement.
+        let binary = ac as u16 + !value as u16 + carry as u16;
+        let overflow = (ac ^ value) & (ac ^ binary as u8) & 0x80 != 0;
+
+        self.set_status(Status::Carry, binary > 0xFF);
+        self.set_status(Status::Overflow, overflow);
+
+        if !self.get_status(Status::Decimal) {
+            self.registers.ac = binary as u8;
+            self.set_status(Status::Zero, self.registers.ac == 0);
+            self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+            return;
+        }
+
+        let borrow = !carry as i16;
+        let low = (ac & 0x0F) as i16 - (value & 0x0F) as i16 - borrow;
+
+        if cfg!(feature = "cmos") {
+            let mut result = ac as i16 - value as i16 - borrow;
+            if result < 0 {
+                result -= 0x60;
+            }
+            if low < 0 {
+                result -= 0x06;
+            }
+
+            // The 65C02 takes an extra cycle to correct the flags.
+            self.cycles += 1;
+            self.registers.ac = result as u8;
+            self.set_status(Status::Zero, self.registers.ac == 0);
+            self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+        } else {
+            // Subtract each BCD digit, adjusting them back into 0..=9.
+            let low = if low < 0 {
+                ((low - 0x06) & 0x0F) - 0x10
+            } else {
+                low
+            };
+
+            let mut result = (ac & 0xF0) as i16 - (value & 0xF0) as i16 + low;
+            if result < 0 {
+                result -= 0x60;
+            }
+
+            self.registers.ac = result as u8;
+            self.set_status(Status::Zero, binary & 0xFF == 0);
+            self.set_status(Status::Negative, binary & 0x80 != 0);
+        }
+    }
 }
@@ -0,0 +1,97 @@
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::interrupts::InterruptController;
+}
+
+/// Hardware interrupt lines.
+///
+/// IRQ is level triggered, it's serviced between instructions for as long as it's
+/// asserted and [Status::Interrupt] is clear. NMI is edge triggered, asserting it
+/// latches a single interrupt which is serviced regardless of [Status::Interrupt].
+pub trait InterruptController {
+    /// Assert the IRQ line.
+    fn assert_irq(&mut self);
+    /// Release the IRQ line.
+    fn release_irq(&mut self);
+    /// Assert the NMI line, latching an NMI if it was released.
+    fn assert_nmi(&mut self);
+    /// Release the NMI line.
+    fn release_nmi(&mut self);
+
+    /// Service a pending NMI or IRQ, returning whether one was taken.
+    ///
+    /// This is called by [step](InstructionController::step) before each instruction.
+    fn poll_interrupts(&mut self) -> VmResult<bool>;
+    /// Push the PC and SR, set [Status::Interrupt], and jump through the vector at `vector`.
+    ///
+    /// `brk` sets the B flag in the pushed SR.
+    fn interrupt(&mut self, vector: (usize, usize), brk: bool) -> VmResult<()>;
+}
+
+impl<B: Bus> InterruptController for VirtualMachine<B> {
+    fn assert_irq(&mut self) {
+        self.irq = true;
+    }
+
+    fn release_irq(&mut self) {
+        self.irq = false;
+    }
+
+    fn assert_nmi(&mut self) {
+        if !self.nmi {
+            self.nmi_pending = true;
+        }
+
+        self.nmi = true;
+    }
+
+    fn release_nmi(&mut self) {
+        self.nmi = false;
+    }
+
+    fn poll_interrupts(&mut self) -> VmResult<bool> {
+        let vector = if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt_bounds
+        } else if self.irq && !self.get_status(Status::Interrupt) {
+            self.irq_bounds
+        } else {
+            return Ok(false);
+        };
+
+        self.interrupt(vector, false)?;
+        // Interrupt entry takes as long as a BRK.
+        self.cycles += OPCODE_CYCLES[0x00] as u64;
+
+        Ok(true)
+    }
+
+    fn interrupt(&mut self, vector: (usize, usize), brk: bool) -> VmResult<()> {
+        self.push((self.registers.pc >> 8) as u8)?;
+        self.push(self.registers.pc as u8)?;
+
+        // The B flag only exists on the stack, and bit 5 is always pushed set.
+        let sr = self.registers.sr | 0x20;
+        self.push(if brk { sr | 0x10 } else { sr & !0x10 })?;
+        self.set_status(Status::Interrupt, true);
+
+        // Like reset, vector loads don't go through watchpoints or the observer.
+        self.registers.pc = u16::from_le_bytes([
+            self.flatmap.read(vector.0 as u16),
+            self.flatmap.read(vector.1 as u16),
+        ]);
+
+        let kind = if brk {
+            InterruptKind::Brk
+        } else if vector == self.interrupt_bounds {
+            InterruptKind::Nmi
+        } else {
+            InterruptKind::Irq
+        };
+        let pc = self.registers.pc;
+        self.notify(|o| o.on_interrupt(kind, pc));
+
+        Ok(())
+    }
+}
@@ -1,16 +1,23 @@
 use core::fmt::{Debug, Formatter, Result};
 
 use bytes::BytesMut;
-use derivative::Derivative;
 
 use crate::prelude::*;
 
+mod bus;
 mod control;
+mod debugger;
+mod error;
 mod heap;
+mod history;
 mod instructions;
+mod interrupts;
+mod observer;
 mod registers;
+mod snapshot;
 mod stack;
 mod status;
+mod undocumented;
 
 /// Uses everything necessary for the full 6502 vm to run.
 pub mod prelude {
@@ -18,16 +25,34 @@ pub mod prelude {
     pub use crate::program::prelude::*;
     pub use crate::vm::VirtualMachine;
 
+    // Virtual machine memory bus.
+    pub use crate::vm::bus::prelude::*;
+
     // Virtual machine control functionality.
     pub use crate::vm::control::prelude::*;
 
+    // Breakpoints, watchpoints and stop reasons.
+    pub use crate::vm::debugger::prelude::*;
+
+    // Virtual machine errors.
+    pub use crate::vm::error::prelude::*;
+
     // Virtual machine instructions set.
     pub use crate::vm::instructions::prelude::*;
 
+    // Hardware interrupt lines.
+    pub use crate::vm::interrupts::prelude::*;
+
+    // Runtime event observers.
+    pub use crate::vm::observer::prelude::*;
+
     pub use crate::vm::heap::prelude::*;
+    pub use crate::vm::history::prelude::*;
     pub use crate::vm::registers::prelude::*;
+    pub use crate::vm::snapshot::prelude::*;
     pub use crate::vm::stack::prelude::*;
     pub use crate::vm::status::prelude::*;
+    pub use crate::vm::undocumented::prelude::*;
 }
 
 /// The virtual machine implementation
@@ -37,57 +62,74 @@ pub mod prelude {
 ///
 /// See [Masswerk's 6502 Instruction Set](https://www.masswerk.at/6502/6502_instruction_set.html) for more info on the spec.
 // TODO vstack and vheap so that you don't have to index yourself.
-#[derive(Derivative)]
-#[derivative(Default)]
-pub struct VirtualMachine {
+pub struct VirtualMachine<B: Bus = BytesMut> {
     /// Machine registers struct.
-    #[derivative(Default(value = "Registers::new()"))]
     pub registers: Registers,
-    /// The machine memory in a linear layout.
-    /// We set the size to 64k+1 to allow easy indexing.
-    #[derivative(Default(value = "BytesMut::zeroed(0x10000)"))]
-    pub flatmap: BytesMut,
+    /// The machine memory bus, see [Bus].
+    /// By default this is 64k of flat memory to allow easy indexing.
+    pub flatmap: B,
 
     /// Machine zero page bounds.
     /// This is a tuple of (start, end) addresses.
-    #[derivative(Default(value = "(0x0000, 0x0100)"))]
     pub zero_bounds: (usize, usize),
 
     /// Machine stack page bounds.
     /// The stack grows downwards from 0x01FF to 0x0100.
-    #[derivative(Default(value = "(0x0100, 0x01FF)"))]
     pub stack_bounds: (usize, usize),
 
     /// Machine heap(dynamic memory) bounds.
     /// This is the only memory that can be dynamically allocated.
     /// Accessing memory outside of these bounds is undefined behavior.
-    // TODO: FIX: #[derivative(Default(value = "(0x0200, 0xFFFF)"))]
-    #[derivative(Default(value = "(0x0200, 0xFFFF)"))]
     pub heap_bounds: (usize, usize),
 
-    #[derivative(Default(value = "(0x0000, 0xFFFF)"))]
     pub vheap_bounds: (usize, usize),
 
     /// Interrupt vector table bounds. Placed at end of heap.
     ///
-    /// Three vectors are used: reset, irq, nmi. Each vector is 2 bytes.
-    #[derivative(Default(value = "(0xFFFA, 0xFFFB)"))]
+    /// Three vectors are used: nmi, reset, irq/brk. Each vector is 2 bytes.
     pub interrupt_bounds: (usize, usize),
-    #[derivative(Default(value = "(0xFFFC, 0xFFFD)"))]
     pub reset_bounds: (usize, usize),
-    #[derivative(Default(value = "(0xFFFE, 0xFFFF)"))]
     pub irq_bounds: (usize, usize),
 
     /// Current mode state, this is generally set internally by [step](InstructionController::step).
-    #[derivative(Default(value = "Mode::Absolute"))]
     pub addr_mode: Mode,
 
+    /// The opcode currently being executed, this is set by [step](InstructionController::step).
+    pub opcode: u8,
+
     /// The current cycle count of the vm. This is incremented by [step](InstructionController::step).
-    #[derivative(Default(value = "0"))]
     pub cycles: u64,
 
-    #[derivative(Default(value = "false"))]
     pub halted: bool,
+
+    /// Whether a JAM opcode has locked up the CPU. Only a [reset](ProgramController::reset)
+    /// recovers it.
+    pub jammed: bool,
+
+    /// Whether the undocumented NMOS opcodes execute, see [UndocumentedInstructions].
+    /// Otherwise they're [illegal](VmError::IllegalOpcode).
+    ///
+    /// Defaults to on with the `undocumented` feature.
+    pub undocumented: bool,
+
+    /// The level of the IRQ line, see [InterruptController].
+    pub irq: bool,
+    /// The level of the NMI line, see [InterruptController].
+    pub nmi: bool,
+    /// Whether an NMI edge has been latched and not yet serviced.
+    pub nmi_pending: bool,
+
+    /// Breakpoints and watchpoints, see [DebugInterface].
+    pub debugger: Debugger,
+
+    /// Symbol names and source lines, see [SymbolTable].
+    pub symbols: SymbolTable,
+
+    /// The attached event observer, see [Observer].
+    observer: Option<Box<dyn Observer>>,
+
+    /// The reverse execution log, see [HistoryInterface].
+    history: Option<history::History>,
 }
 
 impl VirtualMachine {
@@ -96,10 +138,50 @@ impl VirtualMachine {
     }
 }
 
-impl Debug for VirtualMachine {
+impl Default for VirtualMachine {
+    fn default() -> Self {
+        VirtualMachine::with_bus(BytesMut::zeroed(0x10000))
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Create a virtual machine with `bus` as its memory.
+    pub fn with_bus(bus: B) -> Self {
+        VirtualMachine {
+            registers: Registers::new(),
+            flatmap: bus,
+            zero_bounds: (0x0000, 0x0100),
+            stack_bounds: (0x0100, 0x01FF),
+            heap_bounds: (0x0200, 0xFFFF),
+            vheap_bounds: (0x0000, 0xFFFF),
+            interrupt_bounds: (0xFFFA, 0xFFFB),
+            reset_bounds: (0xFFFC, 0xFFFD),
+            irq_bounds: (0xFFFE, 0xFFFF),
+            addr_mode: Mode::Absolute,
+            opcode: 0x00,
+            cycles: 0,
+            halted: false,
+            jammed: false,
+            undocumented: cfg!(feature = "undocumented"),
+            irq: false,
+            nmi: false,
+            nmi_pending: false,
+            debugger: Debugger::default(),
+            symbols: SymbolTable::new(),
+            observer: None,
+            history: None,
+        }
+    }
+}
+
+impl<B: Bus> Debug for VirtualMachine<B> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        let hexfmt = |s: &[u8]| -> String {
-            hex::encode(s)
+        let hexfmt = |start: usize, end: usize| -> String {
+            let bytes = (start..=end)
+                .map(|a| self.flatmap.peek(a as u16))
+                .collect::<Vec<u8>>();
+
+            hex::encode(bytes)
                 .to_uppercase()
                 .chars()
                 .collect::<Vec<char>>()
@@ -109,21 +191,28 @@ impl Debug for VirtualMachine {
                 .join("\n\t\t")
         };
 
-        let current_page = (self.get_page_offset() as u16) << 2;
-        let page_bounds = (current_page as usize, (current_page + 0xFF) as usize);
-
-        let pc_offset = self.heap_bounds.0 + self.registers.pc as usize;
+        let disassembly = self
+            .disassemble_around(self.registers.pc, 4, 8)
+            .iter()
+            .map(|i| {
+                let marker = if i.address == self.registers.pc {
+                    ">"
+                } else {
+                    " "
+                };
+                format!("{} {}", marker, i.listing())
+            })
+            .collect::<Vec<String>>()
+            .join("\n\t\t");
 
-        let pc_byte = format!("\n\t\tByte at PC: 0x{:02X}", &self.flatmap[pc_offset]);
         write!(
             f,
-            "VirtualMachine {{\n\tregisters:\n\t\t{:?}\n\tzero page:\n\t\t{}\n\tstack:\n\t\t{}\n\theap[..0xFF]:\n\t\t{}\n\tpage:\n\t\t{}\n{}\n}}",
+            "VirtualMachine {{\n\tregisters:\n\t\t{:?}\n\tzero page:\n\t\t{}\n\tstack:\n\t\t{}\n\theap[..0xFF]:\n\t\t{}\n\tinstructions:\n\t\t{}\n}}",
             self.registers,
-            hexfmt(&self.flatmap[..=0x0FF]),
-            hexfmt(&self.flatmap[self.stack_bounds.0..=self.stack_bounds.1]),
-            hexfmt(&self.flatmap[self.heap_bounds.0..=self.heap_bounds.0 + 0xFF]),
-            hexfmt(&self.flatmap[page_bounds.0..=page_bounds.1]),
-            pc_byte
+            hexfmt(self.zero_bounds.0, self.zero_bounds.0 + 0xFF),
+            hexfmt(self.stack_bounds.0, self.stack_bounds.1),
+            hexfmt(self.heap_bounds.0, self.heap_bounds.0 + 0xFF),
+            disassembly
         )
     }
 }
@@ -0,0 +1,172 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::opcode_name;
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::observer::{InterruptKind, Logger, Observer};
+}
+
+/// The source of an interrupt sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptKind {
+    Nmi,
+    Irq,
+    Brk,
+    Reset,
+}
+
+/// Receives events from a running [VirtualMachine].
+///
+/// Attach one with [set_observer](VirtualMachine::set_observer) for logging, tracing or UIs.
+/// Every method has an empty default, so implementors only handle the events they need.
+/// When no observer is attached the events aren't even built.
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// use vm6502::prelude::*;
+///
+/// #[derive(Default)]
+/// struct Writes(Vec<(u16, u8)>);
+///
+/// impl Observer for Writes {
+///     fn on_write(&mut self, address: u16, value: u8) {
+///         self.0.push((address, value));
+///     }
+/// }
+///
+/// let writes = Rc::new(RefCell::new(Writes::default()));
+///
+/// let mut vm = VirtualMachine::new();
+/// vm.set_observer(writes.clone());
+/// // LDA #$42, STA $10
+/// vm.set_program(0x0000, "A9428510").unwrap();
+/// vm.step().unwrap();
+/// vm.step().unwrap();
+///
+/// assert_eq!(writes.borrow().0, vec![(0x0010, 0x42)]);
+/// ```
+#[allow(unused_variables)]
+pub trait Observer {
+    /// An opcode was fetched from `pc`.
+    fn on_fetch(&mut self, pc: u16, opcode: u8) {}
+    /// The opcode at `pc` was decoded to `mode`.
+    fn on_decode(&mut self, pc: u16, opcode: u8, mode: Mode) {}
+    /// An instruction read `value` from `address`. Opcode and operand fetches aren't reported.
+    fn on_read(&mut self, address: u16, value: u8) {}
+    /// An instruction wrote `value` to `address`.
+    fn on_write(&mut self, address: u16, value: u8) {}
+    /// The status register changed from `old` to `new` over an instruction or interrupt.
+    fn on_status(&mut self, old: u8, new: u8) {}
+    /// `value` was pushed to the stack at `address`.
+    fn on_push(&mut self, address: u16, value: u8) {}
+    /// `value` was pulled from the stack at `address`.
+    fn on_pop(&mut self, address: u16, value: u8) {}
+    /// An interrupt sequence jumped to `vector`.
+    fn on_interrupt(&mut self, kind: InterruptKind, vector: u16) {}
+}
+
+/// Shares an observer with the host, so it can be inspected while attached.
+impl<O: Observer + ?Sized> Observer for Rc<RefCell<O>> {
+    fn on_fetch(&mut self, pc: u16, opcode: u8) {
+        self.borrow_mut().on_fetch(pc, opcode)
+    }
+
+    fn on_decode(&mut self, pc: u16, opcode: u8, mode: Mode) {
+        self.borrow_mut().on_decode(pc, opcode, mode)
+    }
+
+    fn on_read(&mut self, address: u16, value: u8) {
+        self.borrow_mut().on_read(address, value)
+    }
+
+    fn on_write(&mut self, address: u16, value: u8) {
+        self.borrow_mut().on_write(address, value)
+    }
+
+    fn on_status(&mut self, old: u8, new: u8) {
+        self.borrow_mut().on_status(old, new)
+    }
+
+    fn on_push(&mut self, address: u16, value: u8) {
+        self.borrow_mut().on_push(address, value)
+    }
+
+    fn on_pop(&mut self, address: u16, value: u8) {
+        self.borrow_mut().on_pop(address, value)
+    }
+
+    fn on_interrupt(&mut self, kind: InterruptKind, vector: u16) {
+        self.borrow_mut().on_interrupt(kind, vector)
+    }
+}
+
+/// Prints every event to stdout, replacing the old `show_*` debug features.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Logger;
+
+impl Observer for Logger {
+    fn on_fetch(&mut self, pc: u16, opcode: u8) {
+        println!("step: 0x{:04X}: OP=0x{:02X}", pc, opcode);
+    }
+
+    fn on_decode(&mut self, _pc: u16, opcode: u8, mode: Mode) {
+        println!("\t{} {:?}", opcode_name!(opcode), mode);
+    }
+
+    fn on_read(&mut self, address: u16, value: u8) {
+        println!("\t\tread 0x{:02X} from 0x{:04X}", value, address);
+    }
+
+    fn on_write(&mut self, address: u16, value: u8) {
+        println!("\t\twrite 0x{:02X} to 0x{:04X}", value, address);
+    }
+
+    fn on_status(&mut self, old: u8, new: u8) {
+        println!("\t\tNV-BDIZC\n\t\t{:08b} -> {:08b}", old, new);
+    }
+
+    fn on_push(&mut self, address: u16, value: u8) {
+        println!("\t\tpushed 0x{:02X} at 0x{:04X}", value, address);
+    }
+
+    fn on_pop(&mut self, address: u16, value: u8) {
+        println!("\t\tpopped 0x{:02X} at 0x{:04X}", value, address);
+    }
+
+    fn on_interrupt(&mut self, kind: InterruptKind, vector: u16) {
+        println!("{:?} interrupt, jumping to 0x{:04X}", kind, vector);
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Attach `observer`, replacing any attached observer.
+    pub fn set_observer(&mut self, observer: impl Observer + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Detach and return the attached observer.
+    pub fn take_observer(&mut self) -> Option<Box<dyn Observer>> {
+        self.observer.take()
+    }
+
+    /// Report a change of the status register from `old`.
+    pub(crate) fn notify_status(&mut self, old: u8) {
+        let new = self.registers.sr;
+        if old != new {
+            self.notify(|o| o.on_status(old, new));
+        }
+    }
+
+    /// Send an event to the attached observer, if there is one.
+    #[inline]
+    pub(crate) fn notify(&mut self, event: impl FnOnce(&mut dyn Observer)) {
+        if let Some(observer) = self.observer.as_deref_mut() {
+            event(observer);
+        }
+    }
+}
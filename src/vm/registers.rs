@@ -7,12 +7,13 @@ pub mod prelude {
     pub use crate::vm::registers::Registers;
 }
 
-///
-///
-///
+/// The 6502 register file.
 #[derive(Clone, Copy)]
 pub struct Registers {
     /// Program counter
+    ///
+    /// This is a bus address, not an offset into the heap: a program at heap offset 0
+    /// starts at `heap_bounds.0`, 0x0200.
     pub pc: u16,
     /// Accumulator
     pub ac: u8,
@@ -43,7 +44,8 @@ impl Registers {
             x: 0x00,
             y: 0x00,
             sr: 0x00,
-            sp: 0x00,
+            // Empty stack, the first push goes to 0x01FF.
+            sp: 0xFF,
         }
     }
 }
@@ -0,0 +1,197 @@
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::snapshot::{SnapshotInterface, SNAPSHOT_VERSION};
+}
+
+/// Snapshot file magic.
+const MAGIC: &[u8; 4] = b"V65S";
+
+/// Current snapshot format version. Snapshots of any other version are rejected.
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/// Header, registers, bounds, execution state and 64K of memory.
+const SNAPSHOT_LEN: usize = 6 + 7 + 7 * 8 + 16 + 0x10000;
+
+/// Addressing modes in snapshot encoding order.
+const MODES: [Mode; 13] = [
+    Mode::Accumulator,
+    Mode::Implied,
+    Mode::Immediate,
+    Mode::ZeroPage,
+    Mode::ZeroPageX,
+    Mode::ZeroPageY,
+    Mode::Relative,
+    Mode::Absolute,
+    Mode::AbsoluteX,
+    Mode::AbsoluteY,
+    Mode::Indirect,
+    Mode::IndirectX,
+    Mode::IndirectY,
+];
+
+/// Saving and restoring the complete machine state.
+///
+/// A snapshot is a versioned binary blob holding the [Registers], every bounds tuple,
+/// the addressing mode, opcode, cycle count, halted, jammed and interrupt line state, whether
+/// [undocumented](VirtualMachine::undocumented) opcodes are enabled, and the full 64K address
+/// space as seen through [peek](Bus::peek).
+/// Restoring writes memory back with [poke](Bus::poke), so a [MemoryMap] only restores its
+/// RAM, and its devices and ROMs keep their own state.
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let mut vm = VirtualMachine::new();
+/// vm.set_program(0x0000, "69016902").unwrap();
+/// vm.step().unwrap();
+/// let snapshot = vm.snapshot();
+///
+/// vm.step().unwrap();
+/// assert_eq!(vm.registers.ac, 0x03);
+///
+/// vm.restore(&snapshot).unwrap();
+/// assert_eq!(vm.registers.ac, 0x01);
+/// ```
+pub trait SnapshotInterface {
+    /// Serialize the machine state.
+    fn snapshot(&self) -> Vec<u8>;
+    /// Restore the machine state from `snapshot`.
+    ///
+    /// Fails with [VmError::InvalidSnapshot], leaving the machine untouched, if the
+    /// snapshot is truncated or was written by a different format version.
+    fn restore(&mut self, snapshot: &[u8]) -> VmResult<()>;
+}
+
+impl<B: Bus> SnapshotInterface for VirtualMachine<B> {
+    fn snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(SNAPSHOT_LEN);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+
+        let r = &self.registers;
+        out.extend_from_slice(&r.pc.to_le_bytes());
+        out.extend_from_slice(&[r.ac, r.x, r.y, r.sr, r.sp]);
+
+        for (start, end) in self.bounds() {
+            out.extend_from_slice(&(start as u32).to_le_bytes());
+            out.extend_from_slice(&(end as u32).to_le_bytes());
+        }
+
+        let mode = MODES.iter().position(|m| *m == self.addr_mode).unwrap_or(0);
+        out.extend_from_slice(&[mode as u8, self.opcode]);
+        out.extend_from_slice(&self.cycles.to_le_bytes());
+        out.extend_from_slice(&[
+            self.halted as u8,
+            self.irq as u8,
+            self.nmi as u8,
+            self.nmi_pending as u8,
+            self.jammed as u8,
+            self.undocumented as u8,
+        ]);
+
+        out.extend((0..=u16::MAX).map(|address| self.flatmap.peek(address)));
+
+        out
+    }
+
+    fn restore(&mut self, snapshot: &[u8]) -> VmResult<()> {
+        if snapshot.len() < 6 || &snapshot[..4] != MAGIC {
+            return Err(self.invalid_snapshot("missing snapshot header"));
+        }
+
+        let version = u16::from_le_bytes([snapshot[4], snapshot[5]]);
+        if version != SNAPSHOT_VERSION {
+            return Err(self.invalid_snapshot(format!(
+                "unsupported version {}, expected {}",
+                version, SNAPSHOT_VERSION
+            )));
+        } else if snapshot.len() != SNAPSHOT_LEN {
+            return Err(self.invalid_snapshot(format!(
+                "expected {} bytes, got {}",
+                SNAPSHOT_LEN,
+                snapshot.len()
+            )));
+        }
+
+        let mut cursor = &snapshot[6..];
+        let mut take = |n: usize| {
+            let (head, tail) = cursor.split_at(n);
+            cursor = tail;
+            head
+        };
+
+        let pc = take(2);
+        let regs = take(5);
+        let registers = Registers {
+            pc: u16::from_le_bytes([pc[0], pc[1]]),
+            ac: regs[0],
+            x: regs[1],
+            y: regs[2],
+            sr: regs[3],
+            sp: regs[4],
+        };
+
+        let mut bounds = [(0, 0); 7];
+        for bound in bounds.iter_mut() {
+            let b = take(8);
+            *bound = (
+                u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize,
+                u32::from_le_bytes([b[4], b[5], b[6], b[7]]) as usize,
+            );
+        }
+
+        let state = take(2);
+        let Some(addr_mode) = MODES.get(state[0] as usize).copied() else {
+            return Err(self.invalid_snapshot(format!("unknown addressing mode {}", state[0])));
+        };
+        let cycles = u64::from_le_bytes(take(8).try_into().unwrap());
+        let lines = take(6);
+        let memory = take(0x10000);
+
+        self.registers = registers;
+        [
+            self.zero_bounds,
+            self.stack_bounds,
+            self.heap_bounds,
+            self.vheap_bounds,
+            self.interrupt_bounds,
+            self.reset_bounds,
+            self.irq_bounds,
+        ] = bounds;
+        self.addr_mode = addr_mode;
+        self.opcode = state[1];
+        self.cycles = cycles;
+        self.halted = lines[0] != 0;
+        self.irq = lines[1] != 0;
+        self.nmi = lines[2] != 0;
+        self.nmi_pending = lines[3] != 0;
+        self.jammed = lines[4] != 0;
+        self.undocumented = lines[5] != 0;
+
+        // The history can't be undone across a restore.
+        self.clear_history();
+
+        for (address, byte) in memory.iter().enumerate() {
+            self.flatmap.poke(address as u16, *byte);
+        }
+
+        Ok(())
+    }
+}
+
+impl<B: Bus> VirtualMachine<B> {
+    /// Every bounds tuple, in snapshot order.
+    fn bounds(&self) -> [(usize, usize); 7] {
+        [
+            self.zero_bounds,
+            self.stack_bounds,
+            self.heap_bounds,
+            self.vheap_bounds,
+            self.interrupt_bounds,
+            self.reset_bounds,
+            self.irq_bounds,
+        ]
+    }
+}
@@ -5,34 +5,57 @@ pub mod prelude {
 }
 
 pub trait StackInterface {
-    fn pop(&mut self) -> u8;
+    fn pop(&mut self) -> VmResult<u8>;
     fn peek(&mut self) -> u8; // Not congruent with spec.
 
-    fn push(&mut self, value: u8);
+    /// Push `value` to 0x0100 + SP and decrement SP.
+    ///
+    /// Fails with [VmError::StackOverflow] when SP wraps from 0x00 to 0xFF, after the
+    /// value has been written.
+    fn push(&mut self, value: u8) -> VmResult<()>;
 }
 
-impl StackInterface for VirtualMachine {
-    fn pop(&mut self) -> u8 {
-        let value = self.flatmap[self.stack_bounds.1 - self.registers.sp as usize];
-
-        if cfg!(debug_assertions) {
-            println!("Popped value: {}. SP: {}", value, self.registers.sp);
+impl<B: Bus> StackInterface for VirtualMachine<B> {
+    fn pop(&mut self) -> VmResult<u8> {
+        if self.registers.sp == u8::MAX {
+            return Err(self.stack_underflow());
         }
 
-        // Move to next stack entry, TODO: Check the wraps.
-        self.registers.sp = self.registers.sp.saturating_sub(1);
+        // Move back up to the last pushed entry.
+        self.registers.sp += 1;
+
+        let address = self.stack_address(self.registers.sp);
+        let value = self.bus_read(address);
+        self.notify(|o| o.on_pop(address, value));
 
-        value
+        Ok(value)
     }
 
     // Debug / Not Spec
     fn peek(&mut self) -> u8 {
-        self.flatmap[self.stack_bounds.1 - self.registers.sp as usize]
+        self.flatmap
+            .peek(self.stack_address(self.registers.sp.saturating_add(1)))
     }
 
-    fn push(&mut self, value: u8) {
-        self.flatmap[self.stack_bounds.1 - (self.registers.sp as usize)] = value;
+    fn push(&mut self, value: u8) -> VmResult<()> {
+        let address = self.stack_address(self.registers.sp);
+        self.bus_write(address, value);
+        self.notify(|o| o.on_push(address, value));
+
+        // Like the hardware, SP wraps around to 0xFF once 0x0100 has been written.
+        let (sp, wrapped) = self.registers.sp.overflowing_sub(1);
+        self.registers.sp = sp;
+        if wrapped {
+            return Err(self.stack_overflow());
+        }
+
+        Ok(())
+    }
+}
 
-        self.registers.sp = self.registers.sp.saturating_add(1);
+impl<B: Bus> VirtualMachine<B> {
+    /// Bus address of the stack entry at `sp`. The stack grows down from 0x01FF.
+    fn stack_address(&self, sp: u8) -> u16 {
+        (self.stack_bounds.0 + sp as usize) as u16
     }
 }
@@ -21,7 +21,7 @@ pub trait StatusInterface {
 ///
 /// This is the intended API access for frontends to use the VM.
 ///
-impl StatusInterface for VirtualMachine {
+impl<B: Bus> StatusInterface for VirtualMachine<B> {
     fn flip_status(&mut self, flag: Status) {
         let status = self.registers.sr;
 
@@ -29,9 +29,6 @@ impl StatusInterface for VirtualMachine {
     }
 
     fn set_status(&mut self, flag: Status, value: bool) {
-        #[cfg(feature = "show_status_set")]
-        println!("\t\t\tsetting status: {:?} to {}", flag, value);
-
         let status = self.registers.sr;
 
         if value {
@@ -39,21 +36,11 @@ impl StatusInterface for VirtualMachine {
         } else {
             self.registers.sr = status & !status!(flag);
         }
-
-        #[cfg(feature = "show_status")]
-        println!("\t\t\tNV-BDIZC\n\t\t\t{:08b}", self.registers.sr);
     }
 
     fn get_status(&self, flag: Status) -> bool {
         let status = self.registers.sr;
 
-        #[cfg(feature = "show_status_get")]
-        println!(
-            "\t\t\tgetting status: {:?} = {}",
-            flag,
-            status & status!(flag) != 0
-        );
-
         status & status!(flag) != 0
     }
 
@@ -62,7 +49,7 @@ impl StatusInterface for VirtualMachine {
     }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Clone, Copy)]
 pub enum Status {
     Negative,
     Overflow,
@@ -0,0 +1,243 @@
+use crate::prelude::*;
+
+pub mod prelude {
+    pub use crate::vm::undocumented::UndocumentedInstructions;
+}
+
+/// The stable undocumented NMOS 6502 instructions.
+///
+/// These only execute while [undocumented](VirtualMachine::undocumented) is set, otherwise
+/// [step](InstructionController::step) treats them as [illegal](VmError::IllegalOpcode).
+/// Their modes are listed in [UNDOCUMENTED_OPCODES] and [UNDOCUMENTED_MODES], and their
+/// cycles in [OPCODE_CYCLES].
+///
+/// # Example
+/// ```
+/// use vm6502::prelude::*;
+///
+/// let mut vm = VirtualMachine::new();
+/// vm.undocumented = true;
+///
+/// // LAX #$10 isn't stable, but LAX $10 is.
+/// vm.set_program(0x0000, "A710").unwrap();
+/// vm.flatmap[0x0010] = 0x42;
+///
+/// vm.step().unwrap();
+/// assert_eq!((vm.registers.ac, vm.registers.x), (0x42, 0x42));
+/// ```
+pub trait UndocumentedInstructions {
+    /// Execute the undocumented `op`, once its mode has been decoded.
+    fn execute_undocumented(&mut self, op: u8) -> VmResult<()>;
+
+    /// AND immediate, then copy N into the carry
+    fn anc(&mut self) -> VmResult<()>;
+    /// AND immediate, then logical shift right the accumulator
+    fn alr(&mut self) -> VmResult<()>;
+    /// AND immediate, then rotate right the accumulator
+    fn arr(&mut self) -> VmResult<()>;
+    /// Decrement memory, then compare
+    fn dcp(&mut self) -> VmResult<()>;
+    /// Increment memory, then subtract with carry
+    fn isc(&mut self) -> VmResult<()>;
+    /// Lock up the CPU until it's reset
+    fn jam(&mut self) -> VmResult<()>;
+    /// Load accumulator and X register
+    fn lax(&mut self) -> VmResult<()>;
+    /// Rotate left memory, then logical AND
+    fn rla(&mut self) -> VmResult<()>;
+    /// Rotate right memory, then add with carry
+    fn rra(&mut self) -> VmResult<()>;
+    /// Store accumulator AND X register
+    fn sax(&mut self) -> VmResult<()>;
+    /// Subtract immediate from accumulator AND X register, into X
+    fn sbx(&mut self) -> VmResult<()>;
+    /// No operation, reading and discarding the operand
+    fn skip(&mut self) -> VmResult<()>;
+    /// Arithmetic shift left memory, then logical inclusive OR
+    fn slo(&mut self) -> VmResult<()>;
+    /// Logical shift right memory, then exclusive OR
+    fn sre(&mut self) -> VmResult<()>;
+}
+
+impl<B: Bus> UndocumentedInstructions for VirtualMachine<B> {
+    fn execute_undocumented(&mut self, op: u8) -> VmResult<()> {
+        // The unstable opcodes have already been rejected by `mode`.
+        match op {
+            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xB2 | 0xD2 | 0xF2 => {
+                self.jam()
+            }
+            0x0B | 0x2B => self.anc(),
+            0x4B => self.alr(),
+            0x6B => self.arr(),
+            0xCB => self.sbx(),
+            0xEB => self.sbc(),
+            0x83 | 0x87 | 0x8F | 0x97 => self.sax(),
+            0xA3 | 0xA7 | 0xAF | 0xB3 | 0xB7 | 0xBF => self.lax(),
+            // The read-modify-write ops combine the column's shift or increment with its ALU op.
+            _ if op & 0x03 == 0x03 => match op >> 5 {
+                0x00 => self.slo(),
+                0x01 => self.rla(),
+                0x02 => self.sre(),
+                0x03 => self.rra(),
+                0x06 => self.dcp(),
+                _ => self.isc(),
+            },
+            _ => self.skip(),
+        }
+    }
+
+    fn anc(&mut self) -> VmResult<()> {
+        self.and()?;
+        self.set_status(Status::Carry, self.registers.ac & 0x80 != 0);
+
+        Ok(())
+    }
+
+    fn alr(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
+        let anded = self.registers.ac & value;
+        self.registers.ac = anded >> 1;
+
+        self.set_status(Status::Carry, anded & 0x01 != 0);
+        self.set_status(Status::Zero, self.registers.ac == 0);
+        self.set_status(Status::Negative, false);
+
+        Ok(())
+    }
+
+    /// AND immediate, then rotate right the accumulator. C and V come from bits 6 and 5 of
+    /// the result, and in decimal mode each digit is BCD corrected like the NMOS ADC.
+    fn arr(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
+        let anded = self.registers.ac & value;
+        let carry = self.get_status(Status::Carry);
+        let mut result = anded >> 1 | (carry as u8) << 7;
+
+        self.set_status(Status::Zero, result == 0);
+        self.set_status(Status::Negative, carry);
+
+        if !self.get_status(Status::Decimal) {
+            self.set_status(Status::Carry, result & 0x40 != 0);
+            self.set_status(Status::Overflow, (result ^ result << 1) & 0x40 != 0);
+            self.registers.ac = result;
+
+            return Ok(());
+        }
+
+        self.set_status(Status::Overflow, (result ^ anded) & 0x40 != 0);
+        if (anded & 0x0F) + (anded & 0x01) > 0x05 {
+            result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
+        }
+
+        let high = (anded & 0xF0) as u16 + (anded & 0x10) as u16;
+        self.set_status(Status::Carry, high > 0x50);
+        if high > 0x50 {
+            result = result.wrapping_add(0x60);
+        }
+
+        self.registers.ac = result;
+
+        Ok(())
+    }
+
+    fn dcp(&mut self) -> VmResult<()> {
+        let address = self.address()?;
+        let value = self.apply(address, |value| value.wrapping_sub(1))?;
+        let result = (self.registers.ac as u16).wrapping_sub(value as u16);
+
+        self.set_status(Status::Carry, result < 0x100);
+        self.set_status(Status::Zero, result == 0);
+        self.set_status(Status::Negative, result & 0x80 != 0);
+
+        Ok(())
+    }
+
+    fn isc(&mut self) -> VmResult<()> {
+        let address = self.address()?;
+        let value = self.apply(address, |value| value.wrapping_add(1))?;
+        self.subtract_with_borrow(value);
+
+        Ok(())
+    }
+
+    fn jam(&mut self) -> VmResult<()> {
+        // The PC stays on the opcode, and nothing but a reset gets the CPU going again.
+        self.registers.pc = self.registers.pc.wrapping_sub(1);
+        self.jammed = true;
+        self.halted = true;
+
+        Ok(())
+    }
+
+    fn lax(&mut self) -> VmResult<()> {
+        let data = self.fetch()?;
+        self.registers.ac = data;
+        self.registers.x = data;
+
+        self.set_status(Status::Zero, data == 0);
+        self.set_status(Status::Negative, data & 0x80 != 0);
+
+        Ok(())
+    }
+
+    fn rla(&mut self) -> VmResult<()> {
+        let value = self.abs_zp_acc_op(|d, c| (d << 1 | c as u8, d & 0x80 != 0))?;
+        self.registers.ac &= value;
+
+        self.set_status(Status::Zero, self.registers.ac == 0);
+        self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
+    }
+
+    fn rra(&mut self) -> VmResult<()> {
+        let value = self.abs_zp_acc_op(|d, c| (d >> 1 | (c as u8) << 7, d & 0x01 != 0))?;
+        self.add_with_carry(value);
+
+        Ok(())
+    }
+
+    fn sax(&mut self) -> VmResult<()> {
+        let address = self.address()?;
+        self.bus_write(address, self.registers.ac & self.registers.x);
+
+        Ok(())
+    }
+
+    fn sbx(&mut self) -> VmResult<()> {
+        let value = self.fetch()?;
+        let anded = self.registers.ac & self.registers.x;
+        self.registers.x = anded.wrapping_sub(value);
+
+        self.set_status(Status::Carry, anded >= value);
+        self.set_status(Status::Zero, self.registers.x == 0);
+        self.set_status(Status::Negative, self.registers.x & 0x80 != 0);
+
+        Ok(())
+    }
+
+    fn skip(&mut self) -> VmResult<()> {
+        self.fetch()?;
+        Ok(())
+    }
+
+    fn slo(&mut self) -> VmResult<()> {
+        let value = self.abs_zp_acc_op(|d, _| (d << 1, d & 0x80 != 0))?;
+        self.registers.ac |= value;
+
+        self.set_status(Status::Zero, self.registers.ac == 0);
+        self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
+    }
+
+    fn sre(&mut self) -> VmResult<()> {
+        let value = self.abs_zp_acc_op(|d, _| (d >> 1, d & 0x01 != 0))?;
+        self.registers.ac ^= value;
+
+        self.set_status(Status::Zero, self.registers.ac == 0);
+        self.set_status(Status::Negative, self.registers.ac & 0x80 != 0);
+
+        Ok(())
+    }
+}
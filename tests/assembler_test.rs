@@ -0,0 +1,123 @@
+use vm6502::prelude::*;
+
+#[test]
+fn test_assemble_addressing_modes() {
+    let source = "
+        LDA #$BB
+        LDA $10
+        LDA $10,X
+        LDX $10,Y
+        LDA $1234
+        LDA $1234,X
+        LDA $1234,Y
+        LDA ($10,X)
+        LDA ($10),Y
+        JMP ($1234)
+        ASL A
+        ASL
+        CLC
+    ";
+    let bytes = assemble(source).unwrap();
+
+    assert_eq!(
+        bytes,
+        vec![
+            0xA9, 0xBB, 0xA5, 0x10, 0xB5, 0x10, 0xB6, 0x10, 0xAD, 0x34, 0x12, 0xBD, 0x34, 0x12,
+            0xB9, 0x34, 0x12, 0xA1, 0x10, 0xB1, 0x10, 0x6C, 0x34, 0x12, 0x0A, 0x0A, 0x18
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_labels_and_branches() {
+    let source = "
+        start:  LDX #$05
+        loop:   DEX
+                BNE loop
+                BEQ done
+                JMP start
+        done:   BRK
+    ";
+    let mut asm = Assembler::with_origin(0x0600);
+    let bytes = asm.assemble(source).unwrap();
+
+    assert_eq!(
+        bytes,
+        vec![0xA2, 0x05, 0xCA, 0xD0, 0xFD, 0xF0, 0x03, 0x4C, 0x00, 0x06, 0x00]
+    );
+    assert_eq!(asm.label("loop"), Some(0x0602));
+    assert_eq!(asm.label("done"), Some(0x060A));
+}
+
+#[test]
+fn test_assemble_forward_reference_is_absolute() {
+    // `var` is unknown during the first pass, so the absolute form is kept.
+    let bytes = assemble("LDA var\nBRK\nvar = $10").unwrap();
+    assert_eq!(bytes, vec![0xAD, 0x10, 0x00, 0x00]);
+
+    let bytes = assemble("var = $10\nLDA var").unwrap();
+    assert_eq!(bytes, vec![0xA5, 0x10]);
+}
+
+#[test]
+fn test_assemble_directives() {
+    let source = "
+        .org $0300
+        table: .byte $01, 2, %11, 'A', \"hi\"
+               .word table, $BEEF
+               .byte <table, >table
+               .byte ' ', '+', '-', 'A' + 1, '-'-1
+               LDA #' '
+               CMP #'+'
+               CMP #'-'
+    ";
+    let mut asm = Assembler::new();
+    let bytes = asm.assemble(source).unwrap();
+
+    assert_eq!(asm.origin(), 0x0300);
+    assert_eq!(
+        bytes,
+        vec![
+            0x01, 0x02, 0x03, 0x41, 0x68, 0x69, 0x00, 0x03, 0xEF, 0xBE, 0x00, 0x03, 0x20, 0x2B,
+            0x2D, 0x42, 0x2C, 0xA9, 0x20, 0xC9, 0x2B, 0xC9, 0x2D
+        ]
+    );
+}
+
+#[test]
+fn test_assemble_errors() {
+    let err = assemble("NOP\nFOO #$01").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert_eq!(
+        err.kind,
+        AssemblerErrorKind::UnknownMnemonic("FOO".to_string())
+    );
+
+    let err = assemble("STA #$01").unwrap_err();
+    assert_eq!(
+        err.kind,
+        AssemblerErrorKind::InvalidMode("STA".to_string(), Mode::Immediate)
+    );
+
+    let err = assemble("JMP nowhere").unwrap_err();
+    assert_eq!(
+        err.kind,
+        AssemblerErrorKind::UndefinedLabel("nowhere".to_string())
+    );
+
+    let err = assemble("BNE far\n.org $0100\nfar: BRK").unwrap_err();
+    assert_eq!(err.kind, AssemblerErrorKind::BranchOutOfRange(254));
+}
+
+#[test]
+fn test_assembled_program_runs() {
+    let mut vm = VirtualMachine::new();
+    let bytes = assemble("ADC #$01\nADC #$01").unwrap();
+
+    vm.insert_bytes(0x0000, bytes).unwrap();
+    vm.registers.pc = vm.heap_bounds.0 as u16;
+    vm.step().unwrap();
+    vm.step().unwrap();
+
+    assert_eq!(vm.registers.ac, 0x02);
+}
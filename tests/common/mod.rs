@@ -0,0 +1,30 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use vm6502::prelude::*;
+
+/// The carry, zero, overflow and negative flags, in that order.
+pub fn flags(vm: &VirtualMachine) -> (bool, bool, bool, bool) {
+    (
+        vm.get_status(Status::Carry),
+        vm.get_status(Status::Zero),
+        vm.get_status(Status::Overflow),
+        vm.get_status(Status::Negative),
+    )
+}
+
+/// A machine with the hex encoded `program` at 0x0200, and the PC on it.
+pub fn vm(program: &str) -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, program).unwrap();
+    vm
+}
+
+/// A path in the temp directory for the file `name`, unique to this process.
+pub fn temp(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("vm6502_{}_{}", name, std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_string()
+}
@@ -0,0 +1,368 @@
+#![cfg(feature = "json")]
+
+use std::io::Cursor;
+
+use serde_json::{json, Value};
+use vm6502::prelude::*;
+
+// 0x0200 JSR $0206
+// 0x0203 BRK
+// 0x0204 NOP
+// 0x0205 NOP
+// 0x0206 LDA #$01
+// 0x0208 RTS
+const PROGRAM: &str = "20060200EAEAA90160";
+
+/// Frame `requests` as a client would send them, numbering them from 1.
+fn frame(requests: &[Value]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for (seq, request) in requests.iter().enumerate() {
+        let mut request = request.clone();
+        request["seq"] = json!(seq + 1);
+        request["type"] = json!("request");
+        let content = request.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).bytes());
+    }
+    input
+}
+
+/// Split the server's output back into messages.
+fn unframe(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while let Some(rest) = output.strip_prefix("Content-Length: ") {
+        let (length, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let length = length.parse::<usize>().unwrap();
+        messages.push(serde_json::from_str(&rest[..length]).unwrap());
+        output = &rest[length..];
+    }
+    messages
+}
+
+fn session(vm: &mut VirtualMachine, server: &mut DapServer, requests: &[Value]) -> Vec<Value> {
+    let mut output = Vec::new();
+    server
+        .serve(vm, Cursor::new(frame(requests)), &mut output)
+        .unwrap();
+    unframe(&String::from_utf8(output).unwrap())
+}
+
+fn response<'a>(messages: &'a [Value], command: &str) -> &'a Value {
+    messages
+        .iter()
+        .find(|m| m["type"] == "response" && m["command"] == command)
+        .unwrap_or_else(|| panic!("no {} response", command))
+}
+
+fn events(messages: &[Value]) -> Vec<(String, Value)> {
+    messages
+        .iter()
+        .filter(|m| m["type"] == "event")
+        .map(|m| (m["event"].as_str().unwrap().to_string(), m["body"].clone()))
+        .collect()
+}
+
+fn vm() -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, PROGRAM).unwrap();
+    vm
+}
+
+#[test]
+fn test_breakpoint_session() {
+    let mut vm = vm();
+    let mut server = DapServer::new();
+    server.add_symbol("sub", 0x0206);
+
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "initialize", "arguments": { "adapterID": "vm6502" } }),
+            json!({ "command": "launch", "arguments": { "stopOnEntry": true } }),
+            json!({ "command": "setFunctionBreakpoints", "arguments": { "breakpoints": [{ "name": "sub" }, { "name": "nowhere" }] } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": 1 } }),
+            json!({ "command": "stepOut", "arguments": { "threadId": 1 } }),
+            json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    assert_eq!(
+        response(&messages, "initialize")["body"]["supportsFunctionBreakpoints"],
+        true
+    );
+
+    let breakpoints = &response(&messages, "setFunctionBreakpoints")["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[0]["instructionReference"], "0x0206");
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    let frames = &response(&messages, "stackTrace")["body"]["stackFrames"];
+    assert_eq!(frames.as_array().unwrap().len(), 2);
+    assert_eq!(frames[0]["name"], "sub");
+    assert_eq!(frames[0]["instructionPointerReference"], "0x0206");
+    assert_eq!(frames[1]["name"], "main");
+    assert_eq!(frames[1]["instructionPointerReference"], "0x0200");
+
+    let events = events(&messages);
+    let names = events
+        .iter()
+        .map(|(e, _)| e.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        names,
+        [
+            "initialized",
+            "stopped",
+            "stopped",
+            "stopped",
+            "exited",
+            "terminated"
+        ]
+    );
+    assert_eq!(events[1].1["reason"], "entry");
+    assert_eq!(events[2].1["reason"], "breakpoint");
+    assert_eq!(events[2].1["hitBreakpointIds"][0], breakpoints[0]["id"]);
+    assert_eq!(events[3].1["reason"], "step");
+
+    // Every request gets exactly one response, in order.
+    let responses = messages
+        .iter()
+        .filter(|m| m["type"] == "response")
+        .map(|m| m["request_seq"].as_u64().unwrap())
+        .collect::<Vec<u64>>();
+    assert_eq!(responses, (1..=9).collect::<Vec<u64>>());
+}
+
+#[test]
+fn test_stepping() {
+    let mut vm = vm();
+    let mut server = DapServer::new();
+
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "launch", "arguments": { "stopOnEntry": true } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "stepIn", "arguments": { "threadId": 1 } }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    let frames = &response(&messages, "stackTrace")["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "$0206");
+    assert_eq!(frames[0]["instructionPointerReference"], "0x0206");
+    assert_eq!(vm.registers.pc, 0x0206);
+
+    let mut vm = self::vm();
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "launch", "arguments": { "stopOnEntry": true } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "next", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    assert_eq!(events(&messages).last().unwrap().1["reason"], "step");
+    assert_eq!(vm.registers.pc, 0x0203);
+    assert_eq!(vm.registers.ac, 0x01);
+}
+
+#[test]
+fn test_inspection() {
+    let mut vm = vm();
+    vm.registers.ac = 0x42;
+    vm.flatmap.write(0x0010, 0x99);
+    vm.set_status(Status::Carry, true);
+    let mut server = DapServer::new();
+
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "threads" }),
+            json!({ "command": "scopes", "arguments": { "frameId": 0 } }),
+            json!({ "command": "variables", "arguments": { "variablesReference": 1 } }),
+            json!({ "command": "variables", "arguments": { "variablesReference": 2 } }),
+            json!({ "command": "variables", "arguments": { "variablesReference": 3 } }),
+            json!({ "command": "readMemory", "arguments": { "memoryReference": "0x0200", "count": 4 } }),
+            json!({ "command": "readMemory", "arguments": { "memoryReference": "0xFFFE", "count": 4 } }),
+            json!({ "command": "disassemble", "arguments": { "memoryReference": "0x0200", "instructionCount": 2 } }),
+            json!({ "command": "setBreakpoints", "arguments": { "breakpoints": [{ "line": 1 }] } }),
+            json!({ "command": "evaluate", "arguments": { "expression": "A" } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    assert_eq!(
+        response(&messages, "threads")["body"]["threads"][0]["name"],
+        "6502"
+    );
+
+    let scopes = &response(&messages, "scopes")["body"]["scopes"];
+    assert_eq!(scopes[2]["name"], "Zero Page");
+
+    let variables = messages
+        .iter()
+        .filter(|m| m["command"] == "variables")
+        .map(|m| m["body"]["variables"].clone())
+        .collect::<Vec<Value>>();
+    assert_eq!(
+        variables[0][0],
+        json!({ "name": "A", "value": "$42", "variablesReference": 0 })
+    );
+    assert_eq!(variables[0][4]["value"], "$0200");
+    assert_eq!(
+        variables[1][5],
+        json!({ "name": "C", "value": "1", "variablesReference": 0 })
+    );
+    assert_eq!(variables[2].as_array().unwrap().len(), 256);
+    assert_eq!(variables[2][0x10]["value"], "$99");
+
+    let reads = messages
+        .iter()
+        .filter(|m| m["command"] == "readMemory")
+        .map(|m| m["body"].clone())
+        .collect::<Vec<Value>>();
+    assert_eq!(
+        reads[0],
+        json!({ "address": "0x0200", "data": "IAYCAA==", "unreadableBytes": 0 })
+    );
+    assert_eq!(reads[1]["unreadableBytes"], 2);
+
+    let instructions = &response(&messages, "disassemble")["body"]["instructions"];
+    assert_eq!(instructions[0]["address"], "0x0200");
+    assert_eq!(instructions[0]["instructionBytes"], "20 06 02");
+    assert_eq!(instructions[0]["instruction"], "JSR $0206");
+    assert_eq!(instructions[1]["instruction"], "BRK");
+
+    assert_eq!(
+        response(&messages, "setBreakpoints")["body"]["breakpoints"][0]["verified"],
+        false
+    );
+    assert_eq!(response(&messages, "evaluate")["success"], false);
+}
+
+#[test]
+fn test_disassemble_count() {
+    let mut vm = vm();
+    let mut server = DapServer::new();
+
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "disassemble", "arguments": { "memoryReference": "0x0001", "instructionOffset": -4, "instructionCount": 6 } }),
+            json!({ "command": "disassemble", "arguments": { "memoryReference": "0x0001", "instructionOffset": -4, "instructionCount": 2 } }),
+            json!({ "command": "disassemble", "arguments": { "memoryReference": "0x0200", "instructionOffset": -1_000_000_000_000i64, "instructionCount": 3 } }),
+            json!({ "command": "disassemble", "arguments": { "memoryReference": "0x0200", "offset": 1_000_000_000_000i64, "instructionOffset": 1_000_000_000_000i64, "instructionCount": 3 } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    let listings = messages
+        .iter()
+        .filter(|m| m["command"] == "disassemble")
+        .map(|m| m["body"]["instructions"].as_array().unwrap().clone())
+        .collect::<Vec<Vec<Value>>>();
+    let addresses = |listing: &[Value]| {
+        listing
+            .iter()
+            .map(|i| match i["presentationHint"] == "invalid" {
+                true => "??".to_string(),
+                false => i["address"].as_str().unwrap().to_string(),
+            })
+            .collect::<Vec<String>>()
+    };
+
+    // Nothing comes before 0x0000, so the start is padded.
+    assert_eq!(
+        addresses(&listings[0]),
+        vec!["??", "??", "??", "0x0000", "0x0001", "0x0002"]
+    );
+    assert_eq!(addresses(&listings[1]), vec!["??", "??"]);
+    assert_eq!(addresses(&listings[2]), vec!["??", "??", "??"]);
+    assert_eq!(listings[3].len(), 3);
+}
+
+#[test]
+fn test_source_mapping() {
+    let mut vm = vm();
+    vm.symbols.insert("sub", 0x0206);
+    for (address, len, line) in [
+        (0x0200, 3, 1),
+        (0x0203, 1, 2),
+        (0x0206, 2, 5),
+        (0x0208, 1, 6),
+    ] {
+        let file = "src/prog.s".to_string();
+        vm.symbols
+            .insert_line(address, len, SourceLine { file, line });
+    }
+    let mut server = DapServer::new();
+
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "launch", "arguments": { "stopOnEntry": true } }),
+            json!({ "command": "setBreakpoints", "arguments": { "source": { "path": "/work/src/prog.s" }, "breakpoints": [{ "line": 6 }, { "line": 3 }] } }),
+            json!({ "command": "setFunctionBreakpoints", "arguments": { "breakpoints": [{ "name": "sub+2" }] } }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "continue", "arguments": { "threadId": 1 } }),
+            json!({ "command": "stackTrace", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disassemble", "arguments": { "memoryReference": "0x0200", "instructionCount": 5 } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    let breakpoints = &response(&messages, "setBreakpoints")["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[0]["instructionReference"], "0x0208");
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    // The line and function breakpoints are both at the RTS.
+    let frames = &response(&messages, "stackTrace")["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "sub");
+    assert_eq!(frames[0]["instructionPointerReference"], "0x0208");
+    assert_eq!(frames[0]["line"], 6);
+    assert_eq!(frames[0]["source"]["path"], "src/prog.s");
+    assert_eq!(frames[1]["line"], 1);
+
+    let instructions = &response(&messages, "disassemble")["body"]["instructions"];
+    assert_eq!(instructions[0]["instruction"], "JSR sub");
+    assert_eq!(instructions[0]["line"], 1);
+    assert_eq!(instructions[2].get("symbol"), None);
+    assert_eq!(instructions[4]["symbol"], "sub");
+    assert_eq!(instructions[4]["location"]["path"], "src/prog.s");
+}
+
+#[test]
+fn test_pause() {
+    // 0x0200 JMP $0200
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, "4C0002").unwrap();
+    let mut server = DapServer::new();
+
+    let messages = session(
+        &mut vm,
+        &mut server,
+        &[
+            json!({ "command": "launch" }),
+            json!({ "command": "configurationDone" }),
+            json!({ "command": "pause", "arguments": { "threadId": 1 } }),
+            json!({ "command": "disconnect" }),
+        ],
+    );
+
+    assert_eq!(response(&messages, "pause")["success"], true);
+    assert_eq!(events(&messages).last().unwrap().1["reason"], "pause");
+    assert_eq!(vm.registers.pc, 0x0200);
+}
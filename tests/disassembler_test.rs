@@ -0,0 +1,96 @@
+use vm6502::prelude::*;
+
+#[test]
+fn test_disassemble_modes() {
+    let bytes = [
+        0xA9, 0xBB, 0xB5, 0x10, 0xB6, 0x10, 0xBD, 0x34, 0x12, 0xA1, 0x10, 0xB1, 0x10, 0x6C, 0x34,
+        0x12, 0x0A, 0x18,
+    ];
+    let text = disassemble(&bytes, 0x0000)
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>();
+
+    assert_eq!(
+        text,
+        vec![
+            "LDA #$BB",
+            "LDA $10,X",
+            "LDX $10,Y",
+            "LDA $1234,X",
+            "LDA ($10,X)",
+            "LDA ($10),Y",
+            "JMP ($1234)",
+            "ASL A",
+            "CLC"
+        ]
+    );
+}
+
+#[test]
+fn test_disassemble_fields() {
+    let listing = disassemble(&[0x8D, 0x00, 0x03, 0xF0, 0xFB, 0x02, 0x4C], 0x0600);
+
+    assert_eq!(listing.len(), 4);
+
+    assert_eq!(listing[0].address, 0x0600);
+    assert_eq!(listing[0].opcode, 0x8D);
+    assert_eq!(listing[0].mnemonic, "STA");
+    assert_eq!(listing[0].mode, Mode::Absolute);
+    assert_eq!(listing[0].operand, Some(0x0300));
+    assert_eq!(listing[0].len, 3);
+
+    assert_eq!(listing[1].target(), Some(0x0600));
+    assert_eq!(listing[1].listing(), "0603  F0 FB     BEQ $0600");
+
+    // Invalid opcodes and truncated instructions are data.
+    assert!(!listing[2].is_valid());
+    assert_eq!(listing[2].to_string(), ".byte $02");
+    assert_eq!(listing[3].len, 1);
+    assert_eq!(listing[3].address, 0x0606);
+}
+
+#[test]
+fn test_disassemble_round_trip() {
+    let source = "
+        start: LDX #$08
+        loop:  LDA $0300,X
+               STA ($20),Y
+               DEX
+               BPL loop
+               JSR start
+               RTS
+    ";
+    let bytes = Assembler::with_origin(0x0400).assemble(source).unwrap();
+
+    let text = disassemble(&bytes, 0x0400)
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+    let reassembled = Assembler::with_origin(0x0400).assemble(&text).unwrap();
+
+    assert_eq!(bytes, reassembled);
+}
+
+#[test]
+fn test_vm_disassemble() {
+    let mut vm = VirtualMachine::new();
+    vm.insert_bytes(0x0010, vec![0xA9, 0x01, 0x69, 0x02, 0xAA, 0xE8, 0x00])
+        .unwrap();
+
+    let range = vm.disassemble(0x0210, 0x0215);
+    assert_eq!(range.len(), 4);
+    assert_eq!(range[3].to_string(), "INX");
+
+    let around = vm.disassemble_around(0x0214, 2, 2);
+    let addresses = around.iter().map(|i| i.address).collect::<Vec<u16>>();
+    assert_eq!(addresses, vec![0x0210, 0x0212, 0x0214, 0x0215]);
+
+    // Asking for more than exists stops at the start of memory.
+    for before in [0x5556, usize::MAX] {
+        let around = vm.disassemble_around(0x0214, before, 1);
+        assert_eq!(around.last().unwrap().address, 0x0214);
+        assert!(around.len() <= 0x0215);
+    }
+}
@@ -4,8 +4,8 @@ use vm6502::prelude::*;
 #[test]
 fn test_square_ints_program() {
     let mut vm = VirtualMachine::new();
-    vm.load_program(0x0000, "binaries/square_ints.a65");
+    vm.load_program(0x0000, "binaries/square_ints.a65").unwrap();
 
-    let cycles = vm.execute();
-    eprintln!("Cycles: {}", cycles);
+    let reason = vm.execute().unwrap();
+    eprintln!("Stopped: {:?}, cycles: {}", reason, vm.cycles);
 }
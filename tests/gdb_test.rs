@@ -0,0 +1,169 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use vm6502::prelude::*;
+
+mod common;
+
+// 0x0200 LDX #$05
+// 0x0202 STX $10
+// 0x0204 DEX
+// 0x0205 BNE $0204
+// 0x0207 BRK
+const PROGRAM: &str = "A2058610CAD0FD00";
+
+fn handle(stub: &mut GdbStub, vm: &mut VirtualMachine, packet: &str) -> String {
+    stub.handle(vm, packet, &mut || false).unwrap()
+}
+
+#[test]
+fn test_registers() {
+    let mut stub = GdbStub::new();
+    let mut vm = common::vm(PROGRAM);
+    vm.registers.ac = 0x12;
+
+    assert_eq!(handle(&mut stub, &mut vm, "g"), "12000000ff0002");
+    assert_eq!(handle(&mut stub, &mut vm, "p5"), "0002");
+
+    assert_eq!(handle(&mut stub, &mut vm, "G010203040506ff"), "OK");
+    assert_eq!(vm.registers.x, 0x02);
+    assert_eq!(vm.registers.pc, 0xFF06);
+
+    assert_eq!(handle(&mut stub, &mut vm, "P1=aa"), "OK");
+    assert_eq!(vm.registers.x, 0xAA);
+    assert_eq!(handle(&mut stub, &mut vm, "P9=aa"), "E01");
+}
+
+#[test]
+fn test_memory() {
+    let mut stub = GdbStub::new();
+    let mut vm = common::vm(PROGRAM);
+
+    assert_eq!(handle(&mut stub, &mut vm, "m200,3"), "a20586");
+    assert_eq!(handle(&mut stub, &mut vm, "M10,2:beef"), "OK");
+    assert_eq!(vm.flatmap[0x11], 0xEF);
+    assert_eq!(handle(&mut stub, &mut vm, "M10,2:be"), "E01");
+}
+
+#[test]
+fn test_unknown_packets() {
+    let mut stub = GdbStub::new();
+    let mut vm = common::vm(PROGRAM);
+
+    // Anything unsupported gets an empty reply, even when it isn't ASCII.
+    for packet in ["", "vMustReplyEmpty", "é", "ém200,3", "qé"] {
+        assert_eq!(handle(&mut stub, &mut vm, packet), "", "{:?}", packet);
+    }
+    assert_eq!(handle(&mut stub, &mut vm, "mé"), "E01");
+}
+
+#[test]
+fn test_step_and_breakpoints() {
+    let mut stub = GdbStub::new();
+    let mut vm = common::vm(PROGRAM);
+
+    assert_eq!(handle(&mut stub, &mut vm, "s"), "S05");
+    assert_eq!(vm.registers.pc, 0x0202);
+
+    assert_eq!(handle(&mut stub, &mut vm, "Z0,204,1"), "OK");
+    assert_eq!(handle(&mut stub, &mut vm, "c"), "S05");
+    assert_eq!(vm.registers.pc, 0x0204);
+
+    // Continuing from the breakpoint runs the loop around to it again.
+    assert_eq!(handle(&mut stub, &mut vm, "c"), "S05");
+    assert_eq!(vm.registers.x, 0x04);
+
+    assert_eq!(handle(&mut stub, &mut vm, "z0,204,1"), "OK");
+    assert!(vm.breakpoints().is_empty());
+    assert_eq!(handle(&mut stub, &mut vm, "c"), "S05");
+    assert!(vm.halted);
+}
+
+#[test]
+fn test_watchpoint_and_faults() {
+    let mut stub = GdbStub::new();
+    let mut vm = common::vm(PROGRAM);
+
+    assert_eq!(handle(&mut stub, &mut vm, "Z2,10,1"), "OK");
+    assert_eq!(handle(&mut stub, &mut vm, "c"), "T05watch:0010;");
+
+    let mut vm = common::vm("FF");
+    vm.undocumented = false;
+    assert_eq!(handle(&mut stub, &mut vm, "s"), "S04");
+
+    assert_eq!(handle(&mut stub, &mut vm, "vMustReplyEmpty"), "");
+    assert!(stub.handle(&mut vm, "D", &mut || false).is_none());
+}
+
+#[test]
+fn test_interrupted_continue() {
+    let mut stub = GdbStub::new();
+    // JMP $0200
+    let mut vm = common::vm("4C0002");
+
+    assert_eq!(stub.handle(&mut vm, "c", &mut || true).unwrap(), "S02");
+}
+
+fn packet(payload: &str) -> String {
+    let sum = payload.bytes().fold(0u8, |s, b| s.wrapping_add(b));
+    format!("${}#{:02x}", payload, sum)
+}
+
+/// Send a packet and read the ack and reply.
+fn exchange(stream: &mut TcpStream, payload: &str) -> String {
+    stream.write_all(packet(payload).as_bytes()).unwrap();
+
+    let mut reply = Vec::new();
+    let mut byte = [0];
+    while reply.iter().filter(|b| **b == b'#').count() == 0 {
+        stream.read_exact(&mut byte).unwrap();
+        reply.push(byte[0]);
+    }
+    let mut checksum = [0; 2];
+    stream.read_exact(&mut checksum).unwrap();
+
+    let reply = String::from_utf8(reply).unwrap();
+    assert!(reply.starts_with("+$"), "{}", reply);
+    reply[2..reply.len() - 1].to_string()
+}
+
+#[test]
+fn test_tcp_session() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let replies = ["qSupported:swbreak+", "?", "Z0,207,1", "c", "g", "m10,1"]
+            .iter()
+            .map(|p| exchange(&mut stream, p))
+            .collect::<Vec<String>>();
+
+        // A corrupted packet is rejected.
+        stream.write_all(b"$g#00").unwrap();
+        let mut nack = [0];
+        stream.read_exact(&mut nack).unwrap();
+        assert_eq!(&nack, b"-");
+
+        assert_eq!(exchange(&mut stream, "k"), "OK");
+        replies
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let mut vm = common::vm(PROGRAM);
+    GdbStub::new().serve(&mut vm, stream).unwrap();
+
+    let replies = client.join().unwrap();
+    assert_eq!(
+        replies,
+        vec![
+            "PacketSize=1000;QStartNoAckMode+",
+            "S05",
+            "OK",
+            "S05",
+            "00000002ff0702",
+            "05"
+        ]
+    );
+}
@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+
+use vm6502::prelude::*;
+
+mod common;
+use common::temp;
+
+#[test]
+fn test_parse_ihex() {
+    let text = "\
+:02020000A90152
+:02020200851065
+
+:0203000060009B
+:020000040000FA
+:0400000500000202F3
+:00000001FF
+:ZZ
+";
+    let image = parse_ihex(text).unwrap();
+
+    // Contiguous records merge into one segment.
+    assert_eq!(
+        image.segments,
+        vec![
+            Segment {
+                address: 0x0200,
+                bytes: vec![0xA9, 0x01, 0x85, 0x10]
+            },
+            Segment {
+                address: 0x0300,
+                bytes: vec![0x60, 0x00]
+            },
+        ]
+    );
+    assert_eq!(image.entry, Some(0x0202));
+}
+
+#[test]
+fn test_ihex_extended_addresses() {
+    // An extended segment base of $0F00 puts offset $0010 at $F010.
+    let image = parse_ihex(":020000020F00ED\n:01001000EA05\n:00000001FF").unwrap();
+    assert_eq!(image.segments[0].address, 0xF010);
+
+    // Start segment address CS:IP = $0010:$0004.
+    let image = parse_ihex(":0400000300100004E5\n:00000001FF").unwrap();
+    assert_eq!(image.entry, Some(0x0104));
+
+    // Beyond 64K through an extended linear base.
+    let err = parse_ihex(":020000040001F9\n:01000000EA15\n").unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Line(2),
+            kind: LoadErrorKind::AddressOutOfRange(0x10000)
+        }
+    );
+}
+
+#[test]
+fn test_ihex_errors() {
+    let err = parse_ihex(":02020000A90155\n").unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Line(1),
+            kind: LoadErrorKind::Checksum {
+                expected: 0x52,
+                actual: 0x55
+            }
+        }
+    );
+    assert_eq!(err.to_string(), "line 1: checksum is $55, expected $52");
+
+    let err = parse_ihex("\n:02020000A90152\n02020000A90152").unwrap_err();
+    assert_eq!(err.position, Position::Line(3));
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+
+    // The byte count says 3 bytes, but only 2 follow.
+    let err = parse_ihex(":03020000A90153").unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+
+    let err = parse_ihex(":00000006FA").unwrap_err();
+    assert_eq!(err.kind, LoadErrorKind::UnsupportedRecord("06".to_string()));
+}
+
+#[test]
+fn test_parse_srec() {
+    let text = "\
+S00600004844521B
+S1060200A901004D
+S105030060EAAD
+S5030002FA
+S9030202F8
+S1050400EAEA22
+";
+    let image = parse_srec(text).unwrap();
+
+    assert_eq!(image.segments.len(), 2);
+    assert_eq!(image.segments[1].address, 0x0300);
+    assert_eq!(image.segments[1].bytes, vec![0x60, 0xEA]);
+    // Nothing after the termination record is loaded.
+    assert_eq!(image.entry, Some(0x0202));
+
+    let image = parse_srec("S2070002006000EAAC\nS804000200F9").unwrap();
+    assert_eq!(image.segments[0].address, 0x0200);
+    assert_eq!(image.entry, Some(0x0200));
+}
+
+#[test]
+fn test_srec_errors() {
+    let err = parse_srec("S1060200A901004D\nS1060200A901004E").unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Line(2),
+            kind: LoadErrorKind::Checksum {
+                expected: 0x4D,
+                actual: 0x4E
+            }
+        }
+    );
+
+    let err = parse_srec("S1060200A901004D\nS5030002FA").unwrap_err();
+    assert_eq!(
+        err.kind,
+        LoadErrorKind::RecordCount {
+            expected: 1,
+            actual: 2
+        }
+    );
+
+    let err = parse_srec("S3080001000060EA00AC").unwrap_err();
+    assert_eq!(err.kind, LoadErrorKind::AddressOutOfRange(0x10000));
+
+    let err = parse_srec("S4030000FC").unwrap_err();
+    assert_eq!(err.kind, LoadErrorKind::UnsupportedRecord("S4".to_string()));
+
+    let err = parse_srec("X1060200A901004D").unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+}
+
+#[test]
+fn test_round_trip() {
+    let bytes = (0..=40).collect::<Vec<u8>>();
+
+    for (write, parse) in [
+        (
+            write_ihex as fn(u16, &[u8], Option<u16>) -> String,
+            parse_ihex as fn(&str) -> Result<ProgramImage, LoadError>,
+        ),
+        (write_srec, parse_srec),
+    ] {
+        let image = parse(&write(0xC000, &bytes, Some(0xC010))).unwrap();
+        assert_eq!(
+            image.segments,
+            vec![Segment {
+                address: 0xC000,
+                bytes: bytes.clone()
+            }]
+        );
+        assert_eq!(image.entry, Some(0xC010));
+
+        // Memory past 0xFFFF wraps to 0x0000.
+        let image = parse(&write(0xFFF8, &bytes[..12], None)).unwrap();
+        assert_eq!(image.segments[0].address, 0xFFF8);
+        assert_eq!(image.segments[0].bytes.len(), 8);
+        assert_eq!(image.segments[1].address, 0x0000);
+        assert_eq!(image.segments[1].bytes, bytes[8..12].to_vec());
+    }
+}
+
+#[test]
+fn test_load_and_save() {
+    let mut vm = VirtualMachine::new();
+    vm.insert_bytes(0x0000, vec![0xA9, 0x42, 0x00]).unwrap();
+    vm.registers.pc = 0x0200;
+
+    let (hex, srec) = (temp("hex"), temp("srec"));
+    vm.save_ihex(&hex, 0x0200, 0x0202).unwrap();
+    vm.save_srec(&srec, 0x0200, 0x0202).unwrap();
+
+    for (path, load) in [
+        (
+            &hex,
+            VirtualMachine::load_ihex as fn(&mut VirtualMachine, &str) -> VmResult<()>,
+        ),
+        (&srec, VirtualMachine::load_srec),
+    ] {
+        let mut loaded = VirtualMachine::new();
+        load(&mut loaded, path).unwrap();
+        assert_eq!(loaded.registers.pc, 0x0200);
+        assert_eq!(loaded.execute().unwrap(), StopReason::Halted);
+        assert_eq!(loaded.registers.ac, 0x42);
+    }
+
+    std::fs::write(&hex, ":0100000000FF\n:01000000FF01\n").unwrap();
+    let err = vm.load_ihex(&hex).unwrap_err();
+    assert!(matches!(err, VmError::MalformedProgram { .. }));
+    assert!(err.to_string().contains("line 2: checksum"));
+
+    std::fs::remove_file(&hex).unwrap();
+    std::fs::remove_file(&srec).unwrap();
+    assert!(matches!(vm.load_srec(&srec), Err(VmError::Io { .. })));
+}
+
+#[test]
+fn test_load_image() {
+    let mut image = ProgramImage::new();
+    image
+        .add_segment(0x0200, vec![0xA9, 0x42])
+        .add_segment(0x0202, vec![0x00])
+        .add_segment(0xC000, vec![0x40]);
+    image.entry = Some(0x0200);
+    image.nmi = Some(0xC000);
+    image.irq = Some(0xC000);
+    image.symbols.insert("start".to_string(), 0x0200);
+    assert_eq!(image.segments.len(), 2);
+
+    let mut vm = VirtualMachine::new();
+    vm.load_image(&image).unwrap();
+
+    assert_eq!(vm.registers.pc, 0x0200);
+    assert_eq!(vm.flatmap.peek(0x0202), 0x00);
+    assert_eq!(vm.flatmap.peek(0xC000), 0x40);
+    assert_eq!(vm.flatmap.peek(0xFFFA), 0x00);
+    assert_eq!(vm.flatmap.peek(0xFFFB), 0xC0);
+    // The reset vector wasn't set, so it's left alone.
+    assert_eq!(vm.flatmap.peek(0xFFFD), 0x00);
+    assert_eq!(vm.flatmap.peek(0xFFFF), 0xC0);
+}
+
+#[test]
+fn test_load_image_validation() {
+    let mut vm = VirtualMachine::new();
+
+    // Nothing is written when any part of the image is invalid.
+    let mut overlapping = ProgramImage::new();
+    overlapping
+        .add_segment(0x0300, vec![0xEA; 4])
+        .add_segment(0x0200, vec![0xEA; 0x101]);
+    overlapping.entry = Some(0x0300);
+    let err = vm.load_image(&overlapping).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "malformed program: segments at $0200-$0300 and $0300-$0303 overlap (PC: 0x0000, OP: 0x00)"
+    );
+    assert_eq!(vm.flatmap.peek(0x0200), 0x00);
+    assert_eq!(vm.registers.pc, 0x0000);
+
+    let mut past_end = ProgramImage::new();
+    past_end
+        .add_segment(0x0200, vec![0xEA])
+        .add_segment(0xFFF0, vec![0xEA; 0x11]);
+    assert!(matches!(
+        vm.load_image(&past_end),
+        Err(VmError::OutOfBounds {
+            address: 0x10000,
+            ..
+        })
+    ));
+    assert_eq!(vm.flatmap.peek(0x0200), 0x00);
+
+    // A vector over a segment that covers the vector table.
+    let mut vectors = ProgramImage::new();
+    vectors.add_segment(0xFFF0, vec![0xEA; 0x10]);
+    vectors.reset = Some(0x0200);
+    assert!(matches!(
+        vm.load_image(&vectors),
+        Err(VmError::MalformedProgram { .. })
+    ));
+
+    vectors.reset = None;
+    vm.load_image(&vectors).unwrap();
+    assert_eq!(vm.flatmap.peek(0xFFFF), 0xEA);
+}
+
+#[test]
+fn test_parse_prg() {
+    let image = parse_prg(&[0x00, 0xC0, 0xA9, 0x42, 0x00]).unwrap();
+    assert_eq!(
+        image.segments,
+        vec![Segment {
+            address: 0xC000,
+            bytes: vec![0xA9, 0x42, 0x00]
+        }]
+    );
+    assert_eq!(image.entry, Some(0xC000));
+
+    let err = parse_prg(&[0x01]).unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Offset(1),
+            kind: LoadErrorKind::Truncated
+        }
+    );
+    assert_eq!(err.to_string(), "offset $1: unexpected end of file");
+
+    let err = parse_prg(&[0xFE, 0xFF, 0xEA, 0xEA, 0xEA]).unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Offset(4),
+            kind: LoadErrorKind::AddressOutOfRange(0x10000)
+        }
+    );
+}
+
+#[test]
+fn test_parse_xex() {
+    let xex = [
+        0xFF, 0xFF, 0x00, 0x20, 0x01, 0x20, 0xA9, 0x01, // $2000-$2001
+        0x02, 0x20, 0x02, 0x20, 0x00, // $2002, contiguous and without a marker
+        0xFF, 0xFF, 0xE2, 0x02, 0xE3, 0x02, 0x00, 0x30, // INITAD = $3000
+        0x00, 0x30, 0x00, 0x30, 0x60, // $3000
+        0xE2, 0x02, 0xE3, 0x02, 0x10, 0x30, // INITAD = $3010
+        0xE1, 0x02, 0xE1, 0x02, 0x40, // RUNAD high byte only
+    ];
+    let image = parse_xex(&xex).unwrap();
+
+    assert_eq!(
+        image.segments,
+        vec![
+            Segment {
+                address: 0x2000,
+                bytes: vec![0xA9, 0x01, 0x00]
+            },
+            Segment {
+                address: 0x3000,
+                bytes: vec![0x60]
+            },
+        ]
+    );
+    assert_eq!(image.inits, vec![0x3000, 0x3010]);
+    // Without a full RUNAD, the low byte comes from the first segment.
+    assert_eq!(image.entry, Some(0x4000));
+
+    // RUNAD and INITAD in a single segment.
+    let image = parse_xex(&[0xFF, 0xFF, 0xE0, 0x02, 0xE3, 0x02, 0x00, 0x20, 0x00, 0x30]).unwrap();
+    assert!(image.segments.is_empty());
+    assert_eq!(image.entry, Some(0x2000));
+    assert_eq!(image.inits, vec![0x3000]);
+}
+
+#[test]
+fn test_xex_errors() {
+    let err = parse_xex(&[0x00, 0x20, 0x00, 0x20, 0xEA]).unwrap_err();
+    assert_eq!(err.position, Position::Offset(0));
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+
+    let err = parse_xex(&[0xFF, 0xFF, 0x00, 0x20, 0x01, 0x20, 0xEA]).unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Offset(7),
+            kind: LoadErrorKind::Truncated
+        }
+    );
+
+    let err = parse_xex(&[0xFF, 0xFF, 0x00, 0x20, 0x00, 0x20, 0xEA, 0x10, 0x20, 0x00]).unwrap_err();
+    assert_eq!(err.kind, LoadErrorKind::Truncated);
+
+    let err = parse_xex(&[0xFF, 0xFF, 0x01, 0x20, 0x00, 0x20, 0xEA]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "offset $0: syntax error: segment ends at $2000 before its start"
+    );
+}
+
+#[test]
+fn test_load_prg_and_xex() {
+    let (prg, xex) = (temp("prg"), temp("xex"));
+    std::fs::write(&prg, [0x01, 0x08, 0xA9, 0x42, 0x00]).unwrap();
+    std::fs::write(
+        &xex,
+        [
+            0xFF, 0xFF, 0x00, 0x30, 0x00, 0x30, 0x00, // $3000
+            0x00, 0x20, 0x01, 0x20, 0xA9, 0x42, // $2000-$2001
+            0xE0, 0x02, 0xE1, 0x02, 0x00, 0x20, // RUNAD = $2000
+            0x02, 0x20, 0x03, 0x20, 0x4C, 0x00, // $2002-$2003, JMP $3000
+            0x04, 0x20, 0x04, 0x20, 0x30,
+        ],
+    )
+    .unwrap();
+
+    let mut vm = VirtualMachine::new();
+    let image = vm.load_prg(&prg).unwrap();
+    assert_eq!(image.entry, Some(0x0801));
+    assert_eq!(vm.registers.pc, 0x0801);
+    assert_eq!(vm.execute().unwrap(), StopReason::Halted);
+    assert_eq!(vm.registers.ac, 0x42);
+
+    let mut vm = VirtualMachine::new();
+    vm.load_xex(&xex).unwrap();
+    assert_eq!(vm.registers.pc, 0x2000);
+    // RUNAD isn't written to memory.
+    assert_eq!(vm.flatmap.peek(0x02E0), 0x00);
+    assert_eq!(vm.execute().unwrap(), StopReason::Halted);
+    assert_eq!(vm.registers.ac, 0x42);
+
+    // Overlapping segments are rejected like any other image.
+    std::fs::write(
+        &xex,
+        [
+            0xFF, 0xFF, 0x00, 0x20, 0x00, 0x20, 0xEA, 0x00, 0x20, 0x00, 0x20, 0xEA,
+        ],
+    )
+    .unwrap();
+    assert!(matches!(
+        vm.load_xex(&xex),
+        Err(VmError::MalformedProgram { .. })
+    ));
+
+    std::fs::write(&prg, [0x01]).unwrap();
+    let err = vm.load_prg(&prg).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("offset $1: unexpected end of file"));
+
+    std::fs::remove_file(&prg).unwrap();
+    std::fs::remove_file(&xex).unwrap();
+    assert!(matches!(vm.load_prg(&prg), Err(VmError::Io { .. })));
+}
+
+/// An o65 header for `mode`, then `fields` (tbase, tlen, dbase, dlen, bbase, blen, zbase,
+/// zlen, stack) and no options.
+fn o65_header(mode: u16, fields: [u16; 9]) -> Vec<u8> {
+    let mut bytes = vec![0x01, 0x00, b'o', b'6', b'5', 0x00];
+    bytes.extend(mode.to_le_bytes());
+    for field in fields {
+        bytes.extend(field.to_le_bytes());
+    }
+    bytes.push(0x00);
+
+    bytes
+}
+
+/// A module assembled at $1000 that loads from its data, calls an import and exports symbols.
+fn o65_module() -> Vec<u8> {
+    // Bss is zeroed.
+    let mut bytes = o65_header(0x0200, [0x1000, 9, 0x2000, 3, 0x3000, 4, 0x80, 2, 0]);
+    bytes.extend([
+        0xAD, 0x00, 0x20, // LDA $2000
+        0x20, 0x00, 0x00, // JSR ext
+        0xA9, 0x10, // LDA #>$1005
+        0x60, // RTS
+    ]);
+    bytes.extend([0x42, 0x05, 0x10]); // Data, a pointer to $1005
+    bytes.extend([0x01, 0x00, b'e', b'x', b't', 0x00]);
+    // Text: a data word at 1, an import word at 4 and a text high byte at 7.
+    bytes.extend([0x02, 0x83, 0x03, 0x80, 0x00, 0x00, 0x03, 0x42, 0x05, 0x00]);
+    // Data: a text word at 1.
+    bytes.extend([0x02, 0x82, 0x00]);
+    bytes.extend([0x03, 0x00]);
+    for (name, segment, value) in [
+        ("start", 2, 0x1000u16),
+        ("value", 3, 0x2000),
+        ("io", 1, 0xD000),
+    ] {
+        bytes.extend(name.bytes());
+        bytes.extend([0x00, segment]);
+        bytes.extend(value.to_le_bytes());
+    }
+
+    bytes
+}
+
+#[test]
+fn test_parse_o65() {
+    let object = parse_o65(&o65_module()).unwrap();
+    assert_eq!(object.text_base, 0x1000);
+    assert_eq!(object.data, vec![0x42, 0x05, 0x10]);
+    assert_eq!(object.bss_len, 4);
+    assert_eq!(object.undefined, vec!["ext".to_string()]);
+
+    let layout = object.layout_at(0x4000);
+    assert_eq!(
+        layout,
+        O65Layout {
+            text: 0x4000,
+            data: 0x4009,
+            bss: 0x400C,
+            zero: 0x80
+        }
+    );
+
+    let imports = HashMap::from([("ext".to_string(), 0x6000)]);
+    let image = object.relocate(&layout, &imports).unwrap();
+    assert_eq!(
+        image.segments,
+        vec![Segment {
+            address: 0x4000,
+            bytes: vec![
+                0xAD, 0x09, 0x40, 0x20, 0x00, 0x60, 0xA9, 0x40, 0x60, // Text
+                0x42, 0x05, 0x40, // Data
+                0x00, 0x00, 0x00, 0x00, // Bss
+            ]
+        }]
+    );
+    assert_eq!(image.entry, Some(0x4000));
+    assert_eq!(
+        image.symbols,
+        HashMap::from([
+            ("start".to_string(), 0x4000),
+            ("value".to_string(), 0x4009),
+            ("io".to_string(), 0xD000),
+        ])
+    );
+
+    let err = object.relocate(&layout, &HashMap::new()).unwrap_err();
+    assert_eq!(err.kind, LoadErrorKind::UndefinedSymbol("ext".to_string()));
+    assert_eq!(err.to_string(), "offset $2F: undefined symbol ext");
+}
+
+#[test]
+fn test_o65_relocations() {
+    // A pagewise object, with a skip entry to reach a low byte at 300.
+    let mut bytes = o65_header(0x4000, [0x1000, 301, 0, 0, 0, 0, 0, 0, 0]);
+    let mut text = vec![0xEA; 301];
+    text[0] = 0x10;
+    text[300] = 0x34;
+    bytes.extend(text);
+    bytes.extend([0x00, 0x00]);
+    bytes.extend([0x01, 0x42, 0xFF, 0x2B, 0x22, 0x00, 0x00, 0x00, 0x00]);
+
+    let object = parse_o65(&bytes).unwrap();
+    let image = object
+        .relocate(&object.layout_at(0x2000), &HashMap::new())
+        .unwrap();
+    assert_eq!(image.segments[0].bytes[0], 0x20);
+    assert_eq!(image.segments[0].bytes[300], 0x34);
+
+    // Pagewise segments stay page aligned.
+    let mut layout = object.layout_at(0x2001);
+    assert_eq!(layout.text, 0x2100);
+    layout.text = 0x2001;
+    let err = object.relocate(&layout, &HashMap::new()).unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::Relocation(_)));
+
+    // A relocation past the end of its segment.
+    let mut bytes = o65_header(0x0000, [0x1000, 2, 0, 0, 0, 0, 0, 0, 0]);
+    bytes.extend([0xEA, 0xEA, 0x00, 0x00, 0x02, 0x82, 0x00, 0x00, 0x00, 0x00]);
+    let err = parse_o65(&bytes).unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::Relocation(_)));
+}
+
+#[test]
+fn test_o65_errors() {
+    let err = parse_o65(b"\x01\x00o64\x00").unwrap_err();
+    assert_eq!(err.position, Position::Offset(0));
+
+    // 65816 code.
+    let err = parse_o65(&o65_header(0x8000, [0; 9])).unwrap_err();
+    assert_eq!(
+        err.kind,
+        LoadErrorKind::UnsupportedRecord("mode $8000".to_string())
+    );
+
+    let bytes = o65_module();
+    let err = parse_o65(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Offset(bytes.len() - 1),
+            kind: LoadErrorKind::Truncated
+        }
+    );
+}
+
+#[test]
+fn test_load_o65() {
+    // A library that the module imports `ext` from: LDX #$01; RTS.
+    let mut library = o65_header(0x0000, [0x0000, 3, 0, 0, 0, 0, 0, 0, 0]);
+    library.extend([0xA2, 0x01, 0x60, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00]);
+    library.extend(b"ext\x00\x02\x00\x00");
+
+    let (library_path, module_path) = (temp("library.o65"), temp("module.o65"));
+    std::fs::write(&library_path, library).unwrap();
+    std::fs::write(&module_path, o65_module()).unwrap();
+
+    let mut vm = VirtualMachine::new();
+    let library = vm.load_o65(&library_path, 0x6000, &HashMap::new()).unwrap();
+    assert_eq!(library.symbols["ext"], 0x6000);
+    let module = vm.load_o65(&module_path, 0x4000, &library.symbols).unwrap();
+    assert_eq!(vm.registers.pc, 0x4000);
+
+    // Call the module's export from a stub.
+    vm.insert_bytes(0x0000, vec![0x20, 0x00, 0x40, 0x00])
+        .unwrap();
+    assert_eq!(module.symbols["start"], 0x4000);
+    vm.registers.pc = 0x0200;
+    assert_eq!(vm.execute().unwrap(), StopReason::Halted);
+    assert_eq!(vm.registers.ac, 0x40);
+    assert_eq!(vm.registers.x, 0x01);
+
+    let err = vm
+        .load_o65(&module_path, 0x4000, &HashMap::new())
+        .unwrap_err();
+    assert!(matches!(err, VmError::MalformedProgram { .. }));
+    assert!(err.to_string().contains("undefined symbol ext"));
+
+    std::fs::remove_file(&library_path).unwrap();
+    std::fs::remove_file(&module_path).unwrap();
+}
@@ -52,7 +52,7 @@ fn test_stuff_program_at_end() {
     let mut vm = VirtualMachine::new();
 
     let prog = "6901";
-    stuff_program_at_end!(vm, prog);
+    stuff_program_at_end!(vm, prog).unwrap();
 
     assert_eq!(vm.flatmap[vm.heap_bounds.1 - (prog.len() / 2) - 1], 0x69);
     assert_eq!(vm.flatmap[vm.heap_bounds.1 - (prog.len() / 2)], 0x01);
@@ -0,0 +1,223 @@
+use vm6502::prelude::*;
+
+fn run(monitor: &mut Monitor, vm: &mut VirtualMachine, line: &str) -> String {
+    match monitor.execute(vm, line).unwrap() {
+        Response::Output(output) => output,
+        Response::Quit => panic!("unexpected quit"),
+    }
+}
+
+#[test]
+fn test_examine_and_deposit() {
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+
+    assert_eq!(run(&mut monitor, &mut vm, ">0200 41 42 $43"), "");
+    assert_eq!(vm.flatmap.peek(0x0202), 0x43);
+
+    let dump = run(&mut monitor, &mut vm, "m 0200 0212");
+    let lines = dump.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("0200  41 42 43 00"));
+    assert!(lines[0].ends_with("ABC............."));
+    assert_eq!(lines[1], format!("{:<55}...", "0210  00 00 00"));
+
+    // Without a start, `m` continues where it left off.
+    assert!(run(&mut monitor, &mut vm, "m").starts_with("0220"));
+}
+
+#[test]
+fn test_assemble_and_disassemble() {
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+
+    assert_eq!(
+        run(&mut monitor, &mut vm, "a 0200 LDA #$01"),
+        "0200  A9 01     LDA #$01"
+    );
+    run(&mut monitor, &mut vm, "a $0202 BNE $0200");
+
+    assert_eq!(
+        run(&mut monitor, &mut vm, "d 0200 0202"),
+        "0200  A9 01     LDA #$01\n0202  D0 FC     BNE $0200"
+    );
+    assert_eq!(run(&mut monitor, &mut vm, "d").lines().count(), 16);
+
+    assert!(matches!(
+        monitor.execute(&mut vm, "a 0200 LDA ($01"),
+        Err(MonitorError::Assembler(_))
+    ));
+}
+
+#[test]
+fn test_registers() {
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+
+    assert_eq!(
+        run(&mut monitor, &mut vm, "r a=12 x=$34 pc=0300"),
+        "PC=0300 A=12 X=34 Y=00 SP=FF NV-BDIZC=00000000 CYC=0"
+    );
+    assert_eq!(vm.registers.x, 0x34);
+
+    assert!(matches!(
+        monitor.execute(&mut vm, "r a=100"),
+        Err(MonitorError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        monitor.execute(&mut vm, "r q=1"),
+        Err(MonitorError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn test_breakpoints_and_stepping() {
+    // 0x0200 JSR $0210
+    // 0x0203 BRK
+    // 0x0210 LDA #$41
+    // 0x0212 STA $10
+    // 0x0214 RTS
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+    run(&mut monitor, &mut vm, "> 0200 20 10 02 00");
+    run(&mut monitor, &mut vm, "> 0210 a9 41 85 10 60");
+
+    assert_eq!(run(&mut monitor, &mut vm, "b 0212"), "breakpoint 0");
+    assert_eq!(run(&mut monitor, &mut vm, "w 10 w"), "watchpoint 1");
+    assert_eq!(
+        run(&mut monitor, &mut vm, "b"),
+        "0: pc $0212\n1: watch $0010-$0010 Write"
+    );
+
+    let stop = run(&mut monitor, &mut vm, "g 0200");
+    assert!(stop.starts_with("breakpoint 0\nPC=0212 A=41"));
+    assert!(stop.ends_with("0212  85 10     STA $10"));
+
+    assert!(run(&mut monitor, &mut vm, "g").starts_with("watchpoint 1: Write $0010"));
+    assert!(run(&mut monitor, &mut vm, "ret").starts_with("PC=0203"));
+
+    run(&mut monitor, &mut vm, "del 0");
+    run(&mut monitor, &mut vm, "del 1");
+    assert!(vm.breakpoints().is_empty());
+    assert!(monitor.execute(&mut vm, "del 1").is_err());
+
+    run(&mut monitor, &mut vm, "r pc=0200");
+    assert!(run(&mut monitor, &mut vm, "n").starts_with("PC=0203"));
+
+    run(&mut monitor, &mut vm, "r pc=0200");
+    assert!(run(&mut monitor, &mut vm, "z 2").starts_with("PC=0212"));
+}
+
+#[test]
+fn test_load_and_save() {
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+    let path = std::env::temp_dir().join(format!("vm6502_monitor_{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    run(&mut monitor, &mut vm, "> c000 01 02 03 04");
+    assert_eq!(
+        run(&mut monitor, &mut vm, &format!("s \"{}\" c001 c003", path)),
+        "saved $C001-$C003"
+    );
+    assert_eq!(std::fs::read(path).unwrap(), vec![0x02, 0x03, 0x04]);
+
+    assert_eq!(
+        run(&mut monitor, &mut vm, &format!("l {} 0300", path)),
+        "loaded $0300-$0302"
+    );
+    assert_eq!(vm.flatmap.peek(0x0302), 0x04);
+
+    assert!(matches!(
+        monitor.execute(&mut vm, &format!("l {} fffe", path)),
+        Err(MonitorError::Vm(VmError::OutOfBounds { .. }))
+    ));
+    std::fs::remove_file(path).unwrap();
+
+    assert!(matches!(
+        monitor.execute(&mut vm, &format!("l {} 0300", path)),
+        Err(MonitorError::Io(_))
+    ));
+}
+
+#[test]
+fn test_load_into_rom() {
+    let mut bus = MemoryMap::new();
+    bus.map_rom(0xE000, vec![0xEA; 0x2000]);
+    let mut vm = VirtualMachine::with_bus(bus);
+    let mut monitor = Monitor::new();
+    let path = std::env::temp_dir().join(format!("vm6502_monitor_rom_{}.bin", std::process::id()));
+    let path = path.to_str().unwrap();
+    std::fs::write(path, [0x01, 0x02]).unwrap();
+
+    assert!(matches!(
+        monitor.execute(&mut vm, &format!("l {} e000", path)),
+        Err(MonitorError::Vm(VmError::MalformedProgram { .. }))
+    ));
+    assert!(matches!(
+        monitor.execute(&mut vm, "a e000 lda #$01"),
+        Err(MonitorError::Vm(VmError::MalformedProgram { .. }))
+    ));
+    assert_eq!(vm.flatmap.peek(0xE000), 0xEA);
+
+    assert_eq!(
+        monitor.execute(&mut vm, &format!("l {} 0300", path)),
+        Ok(Response::Output("loaded $0300-$0301".to_string()))
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_commands() {
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+
+    assert_eq!(run(&mut monitor, &mut vm, "   "), "");
+    assert_eq!(monitor.execute(&mut vm, "x"), Ok(Response::Quit));
+    assert_eq!(
+        monitor.execute(&mut vm, "frobnicate"),
+        Err(MonitorError::UnknownCommand("frobnicate".to_string()))
+    );
+    assert_eq!(
+        monitor.execute(&mut vm, "g 1 2"),
+        Err(MonitorError::Usage("g [addr]"))
+    );
+    assert!(run(&mut monitor, &mut vm, "help").contains("step over"));
+}
+
+#[test]
+fn test_symbols() {
+    let mut vm = VirtualMachine::new();
+    let mut monitor = Monitor::new();
+    let path = std::env::temp_dir()
+        .join(format!("vm6502_monitor_symbols_{}", std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_string();
+    std::fs::write(&path, "al C:0200 .main\nal C:0204 .sub\n").unwrap();
+
+    assert_eq!(
+        run(&mut monitor, &mut vm, &format!("sym {}", path)),
+        "loaded 2 symbols"
+    );
+    assert_eq!(run(&mut monitor, &mut vm, "sym"), "$0200  main\n$0204  sub");
+    std::fs::remove_file(&path).unwrap();
+
+    // JSR sub, BRK, NOP, LDA #$01, RTS
+    run(&mut monitor, &mut vm, "> .main 20 04 02 00 a9 01 60");
+    assert_eq!(
+        run(&mut monitor, &mut vm, "d .main .sub+2"),
+        "main:\n0200  20 04 02  JSR sub\n0203  00        BRK\nsub:\n0204  A9 01     LDA #$01\n0206  60        RTS"
+    );
+
+    assert_eq!(run(&mut monitor, &mut vm, "b .sub+2"), "breakpoint 0");
+    assert_eq!(run(&mut monitor, &mut vm, "b"), "0: pc $0206 (sub+2)");
+    let stop = run(&mut monitor, &mut vm, "g .main");
+    assert!(stop.contains("PC=0206 (sub+2) A=01"));
+    assert!(stop.ends_with("0206  60        RTS"));
+
+    assert!(matches!(
+        monitor.execute(&mut vm, "g .nowhere"),
+        Err(MonitorError::InvalidArgument(_))
+    ));
+}
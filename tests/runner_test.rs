@@ -0,0 +1,274 @@
+use std::process::Command;
+
+use vm6502::prelude::*;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+/// Write `bytes` to a temporary file named after `name`, returning its path.
+fn binary(name: &str, bytes: &[u8]) -> String {
+    let path =
+        std::env::temp_dir().join(format!("vm6502_runner_{}_{}.bin", name, std::process::id()));
+    std::fs::write(&path, bytes).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+// 0x0200 LDA #$42
+// 0x0202 STA $0300
+// 0x0205 BRK
+const STORE: &[u8] = &[0xA9, 0x42, 0x8D, 0x00, 0x03, 0x00];
+
+#[test]
+fn test_expectation_parsing() {
+    let expectation = "A=$42".parse::<Expectation>().unwrap();
+    assert_eq!(
+        expectation,
+        Expectation {
+            target: Target::Register(Register::Ac),
+            value: 0x42
+        }
+    );
+
+    let parsed = [
+        "mem[$0300]=255",
+        "MEM[0x0300]=0xFF",
+        "pc=$0205",
+        "c=1",
+        "cycles=13",
+        "sp=253",
+    ]
+    .map(|e| e.parse::<Expectation>().unwrap());
+    assert_eq!(parsed[0], parsed[1]);
+    assert_eq!(parsed[0].target, Target::Memory(0x0300));
+    assert_eq!(parsed[2].target, Target::Pc);
+    assert_eq!(parsed[3].target, Target::Flag(Status::Carry));
+    assert_eq!(parsed[4].value, 13);
+    assert_eq!(parsed[5].to_string(), "SP=$FD");
+
+    for invalid in [
+        "A",
+        "A=256",
+        "C=2",
+        "Q=1",
+        "mem[zz]=1",
+        "mem[$0300=1",
+        "A=$GG",
+    ] {
+        assert!(invalid.parse::<Expectation>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_argument_parsing() {
+    let runner = Runner::from_args(&args(&[
+        "a.bin",
+        "b.bin@$C000",
+        "--entry",
+        "0x0210",
+        "--max-cycles",
+        "1000",
+        "--stop-at",
+        "0240",
+        "--expect",
+        "X=1",
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        runner.loads,
+        vec![("a.bin".to_string(), 0x0200), ("b.bin".to_string(), 0xC000)]
+    );
+    assert_eq!(runner.entry, Entry::Pc(0x0210));
+    assert_eq!(runner.max_cycles, Some(1000));
+    assert_eq!(runner.max_instructions, None);
+    assert_eq!(runner.stop_at, Some(0x0240));
+    assert_eq!(runner.expectations.len(), 1);
+
+    assert!(Runner::from_args(&args(&[])).is_err());
+    assert!(Runner::from_args(&args(&["a.bin", "--entry"])).is_err());
+    assert!(Runner::from_args(&args(&["a.bin", "--verbose"])).is_err());
+    assert!(Runner::from_args(&args(&["a.bin@nowhere"])).is_err());
+}
+
+#[test]
+fn test_run() {
+    let path = binary("run", STORE);
+    let runner = Runner::from_args(&args(&[
+        &format!("{}@0200", path),
+        "--expect",
+        "A=$42",
+        "--expect",
+        "mem[$0300]=$42",
+        "--expect",
+        "Z=1",
+    ]))
+    .unwrap();
+
+    let report = runner.run().unwrap();
+    assert_eq!(report.stop, Ok(StopReason::Halted));
+    assert_eq!(report.instructions, 3);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].to_string(), "expected Z=1, got Z=0");
+    assert_eq!(
+        report.failures[0].expectation.to_string().parse(),
+        Ok(report.failures[0].expectation)
+    );
+    assert!(!report.passed());
+
+    #[cfg(feature = "json")]
+    {
+        let json = report.to_json();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["stop"], "halted");
+        assert_eq!(json["registers"]["a"], 0x42);
+        assert_eq!(json["failures"][0]["expected"], "Z=1");
+        assert_eq!(json["failures"][0]["actual"], 0);
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_limits() {
+    // 0x0200 INX
+    // 0x0201 JMP $0200
+    let path = binary("limits", &[0xE8, 0x4C, 0x00, 0x02]);
+
+    let run = |extra: &[&str]| {
+        let mut a = args(&[&path]);
+        a.extend(args(extra));
+        Runner::from_args(&a).unwrap().run().unwrap()
+    };
+
+    let report = run(&["--max-instructions", "10"]);
+    assert_eq!(report.stop, Ok(StopReason::InstructionLimit));
+    assert_eq!(report.vm.registers.x, 5);
+
+    let report = run(&["--max-cycles", "50"]);
+    assert_eq!(report.stop, Ok(StopReason::CycleLimit));
+    assert_eq!(report.vm.cycles, 50);
+
+    let report = run(&["--stop-at", "$0201", "--max-instructions", "$100"]);
+    assert_eq!(report.stop, Ok(StopReason::ReachedPc(0x0201)));
+    assert_eq!(report.instructions, 1);
+    assert!(report.passed());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_undocumented() {
+    // 0x0200 LDA #$42
+    // 0x0202 TAX
+    // 0x0203 SAX $10
+    // 0x0205 JAM
+    let path = binary("undocumented", &[0xA9, 0x42, 0xAA, 0x87, 0x10, 0x02]);
+
+    let runner = Runner::from_args(&args(&[&path, "--expect", "mem[$10]=$42"])).unwrap();
+    let report = runner.run().unwrap();
+    if !cfg!(feature = "undocumented") {
+        assert!(matches!(
+            report.stop,
+            Err(VmError::IllegalOpcode { pc: 0x0203, .. })
+        ));
+    }
+
+    let runner = Runner::from_args(&args(&[
+        &path,
+        "--undocumented",
+        "--expect",
+        "mem[$10]=$42",
+    ]))
+    .unwrap();
+    assert!(runner.undocumented);
+
+    let report = runner.run().unwrap();
+    assert_eq!(report.stop, Ok(StopReason::Jammed { pc: 0x0205 }));
+    assert!(report.passed());
+    #[cfg(feature = "json")]
+    assert_eq!(report.to_json()["stop"], "jammed at $0205");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_load_errors() {
+    let runner = Runner::from_args(&args(&["/nonexistent/file.bin"])).unwrap();
+    assert!(matches!(runner.run(), Err(VmError::Io { .. })));
+
+    let path = binary("past_end", STORE);
+    let runner = Runner::from_args(&args(&[&format!("{}@FFFE", path)])).unwrap();
+    assert!(matches!(runner.run(), Err(VmError::OutOfBounds { .. })));
+
+    let runner = Runner::from_args(&args(&[&path, &format!("{}@0203", path)])).unwrap();
+    assert!(matches!(
+        runner.run(),
+        Err(VmError::MalformedProgram { .. })
+    ));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_reset_from_rom() {
+    // 0xC000 LDA $80
+    // 0xC002 STA $10
+    // 0xC004 JMP $C004
+    let code = binary("rom_code", &[0xA5, 0x80, 0x85, 0x10, 0x4C, 0x04, 0xC0]);
+    let zero_page = binary("rom_zero_page", &[0x42]);
+    // NMI, reset and IRQ all point at $C000.
+    let vectors = binary("rom_vectors", &[0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0]);
+
+    let runner = Runner::from_args(&args(&[
+        &format!("{}@C000", code),
+        &format!("{}@0080", zero_page),
+        &format!("{}@FFFA", vectors),
+        "--reset",
+        "--stop-at",
+        "$C004",
+        "--expect",
+        "mem[$10]=$42",
+        "--expect",
+        "sp=$FD",
+    ]))
+    .unwrap();
+
+    let report = runner.run().unwrap();
+    assert_eq!(report.stop, Ok(StopReason::ReachedPc(0xC004)));
+    assert!(report.passed(), "{:?}", report.failures);
+    assert_eq!(report.vm.flatmap.peek(0xFFFD), 0xC0);
+
+    for path in [code, zero_page, vectors] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_binary_exit_codes() {
+    let path = binary("cli", STORE);
+    let vm6502 = |a: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_vm6502"))
+            .arg("run")
+            .args(a)
+            .output()
+            .unwrap()
+    };
+
+    let pass = vm6502(&[&path, "--expect", "A=$42"]);
+    assert_eq!(pass.status.code(), Some(0));
+    #[cfg(feature = "json")]
+    {
+        let json: serde_json::Value = serde_json::from_slice(&pass.stdout).unwrap();
+        assert_eq!(json["registers"]["a"], 0x42);
+        assert_eq!(json["passed"], true);
+    }
+
+    let fail = vm6502(&[&path, "--expect", "A=$41"]);
+    assert_eq!(fail.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&fail.stderr).contains("expected A=$41, got A=$42"));
+
+    assert_eq!(vm6502(&["--expect", "A=1"]).status.code(), Some(2));
+    assert_eq!(vm6502(&["/nonexistent.bin"]).status.code(), Some(2));
+
+    std::fs::remove_file(path).unwrap();
+}
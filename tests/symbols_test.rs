@@ -0,0 +1,245 @@
+use vm6502::prelude::*;
+
+mod common;
+use common::temp;
+
+// 0x0200 JSR delay      main.s:3
+// 0x0203 BRK            main.s:4
+// 0x0204 LDX #$03       main.s:6, delay
+// 0x0206 DEX            main.s:8, delay::loop
+// 0x0207 BNE loop       main.s:9, from a macro at line 2
+// 0x0209 RTS            main.s:10
+const PROGRAM: &str = "20040200A203CAD0FD60";
+
+const DBG: &str = r#"version	major=2,minor=0
+info	csym=0,file=1,lib=0,line=8,mod=1,scope=2,seg=1,span=7,sym=5,type=2
+file	id=0,name="src/main.s",size=120,mtime=0x5F000000,mod=0
+line	id=0,file=0,line=3,span=0
+line	id=1,file=0,line=4,span=1
+line	id=2,file=0,line=6,span=2
+line	id=3,file=0,line=8,span=3
+line	id=4,file=0,line=2,type=2,span=6
+line	id=5,file=0,line=9,span=4
+line	id=6,file=0,line=10,span=5
+mod	id=0,name="main.o",file=0
+scope	id=0,name="",mod=0,size=10,span=0+1
+scope	id=1,name="delay",mod=0,type=scope,size=6,parent=0,sym=1,span=2+3+4+5
+seg	id=0,name="CODE",start=0x000200,size=0x000A,addrsize=absolute,type=ro,oname="main.bin",ooffs=0
+span	id=0,seg=0,start=0,size=3,type=0
+span	id=1,seg=0,start=3,size=1
+span	id=2,seg=0,start=4,size=2
+span	id=3,seg=0,start=6,size=1
+span	id=4,seg=0,start=7,size=2
+span	id=5,seg=0,start=9,size=1
+span	id=6,seg=0,start=7,size=2
+sym	id=0,name="main",addrsize=absolute,scope=0,def=0,ref=1,val=0x200,seg=0,type=lab
+sym	id=1,name="delay",addrsize=absolute,scope=0,def=2,val=0x204,seg=0,type=lab
+sym	id=2,name="loop",addrsize=absolute,scope=1,def=3,val=0x206,seg=0,type=lab
+sym	id=3,name="@skip",addrsize=absolute,scope=1,def=4,val=0x207,seg=0,type=lab,parent=2
+sym	id=4,name="COUNT",addrsize=zeropage,scope=0,def=5,val=0x3,type=equ
+"#;
+
+#[test]
+fn test_symbol_table() {
+    let mut table = SymbolTable::new();
+    table.insert_line(
+        0x0200,
+        2,
+        SourceLine {
+            file: "main.s".to_string(),
+            line: 1,
+        },
+    );
+    assert!(table.is_empty());
+    assert_eq!(table.len(), 0);
+    table.insert("main", 0x0200);
+    table.insert("start", 0x0200);
+    table.insert("data", 0x0300);
+
+    // The first name of an address labels it.
+    assert_eq!(table.name(0x0200), Some("main"));
+    assert_eq!(table.get("start"), Some(0x0200));
+    assert_eq!(table.describe(0x0200), "main");
+    assert_eq!(table.describe(0x0203), "main+3");
+    assert_eq!(table.describe(0x03FF), "data+255");
+    assert_eq!(table.describe(0x0400), "$0400");
+    assert_eq!(table.describe(0x0100), "$0100");
+
+    assert_eq!(table.resolve("main+3"), Some(0x0203));
+    assert_eq!(table.resolve("data+$10"), Some(0x0310));
+    assert_eq!(table.resolve("nope"), None);
+    assert_eq!(table.resolve("main+x"), None);
+
+    // Moving a name moves its label.
+    table.insert("data", 0x0380);
+    assert_eq!(table.name(0x0300), None);
+    assert_eq!(table.name(0x0380), Some("data"));
+    assert_eq!(
+        table.iter().collect::<Vec<(&str, u16)>>(),
+        vec![("main", 0x0200), ("start", 0x0200), ("data", 0x0380)]
+    );
+
+    // A remaining alias takes over the label of an address its name moved away from.
+    table.insert("main", 0x0210);
+    assert_eq!(table.name(0x0200), Some("start"));
+    assert_eq!(table.describe(0x0203), "start+3");
+    assert_eq!(table.name(0x0210), Some("main"));
+}
+
+#[test]
+fn test_parse_vice_labels() {
+    let table =
+        parse_vice_labels("al C:0200 .main\n\nal 000204 .delay\nal 8:0300 .drive\n").unwrap();
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.get("delay"), Some(0x0204));
+    assert_eq!(table.get("drive"), None);
+
+    let err = parse_vice_labels("al C:0200 .main\nbreak 0200").unwrap_err();
+    assert_eq!(
+        err,
+        LoadError {
+            position: Position::Line(2),
+            kind: LoadErrorKind::UnsupportedRecord("break".to_string())
+        }
+    );
+
+    let err = parse_vice_labels("al C:02G0 .main").unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+}
+
+#[test]
+fn test_parse_symbol_list() {
+    let text = "\
+; Entry points
+main = $0200
+delay = 0x0204 ; the delay loop
+loop=518
+";
+    let table = parse_symbol_list(text).unwrap();
+    assert_eq!(table.get("main"), Some(0x0200));
+    assert_eq!(table.get("delay"), Some(0x0204));
+    assert_eq!(table.get("loop"), Some(0x0206));
+
+    let err = parse_symbol_list("main = $0200\nmain $0200").unwrap_err();
+    assert_eq!(err.position, Position::Line(2));
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+
+    let err = parse_symbol_list("big = $10000").unwrap_err();
+    assert_eq!(err.kind, LoadErrorKind::AddressOutOfRange(0x10000));
+}
+
+#[test]
+fn test_parse_dbg() {
+    let table = parse_dbg(DBG).unwrap();
+
+    assert_eq!(table.get("main"), Some(0x0200));
+    assert_eq!(table.get("delay::loop"), Some(0x0206));
+    // Cheap locals and equates aren't labels.
+    assert_eq!(table.len(), 3);
+
+    let line = |address| table.source_line(address).map(|l| l.to_string());
+    assert_eq!(line(0x0200), Some("src/main.s:3".to_string()));
+    assert_eq!(line(0x0202), Some("src/main.s:3".to_string()));
+    // The line invoking the macro, not the macro's own line.
+    assert_eq!(line(0x0208), Some("src/main.s:9".to_string()));
+    assert_eq!(line(0x020A), None);
+
+    assert_eq!(
+        table.line_address("/home/user/project/src/main.s", 8),
+        Some(0x0206)
+    );
+    assert_eq!(table.line_address("main.s", 6), Some(0x0204));
+    assert_eq!(table.line_address("other.s", 6), None);
+    assert_eq!(table.line_address("src/main.s", 5), None);
+
+    let err = parse_dbg("version\tmajor=3,minor=0").unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::UnsupportedRecord(_)));
+
+    let err = parse_dbg("version\tmajor=2,minor=0\nline\tid=0,file=0,line=3,span=0").unwrap_err();
+    assert_eq!(err.position, Position::Line(2));
+
+    let err = parse_dbg("file\tid=0,name=\"main.s").unwrap_err();
+    assert!(matches!(err.kind, LoadErrorKind::Syntax(_)));
+}
+
+#[test]
+fn test_parse_symbols() {
+    assert_eq!(parse_symbols(DBG).unwrap(), parse_dbg(DBG).unwrap());
+    assert_eq!(
+        parse_symbols("\nal C:0200 .main").unwrap().get("main"),
+        Some(0x0200)
+    );
+    assert_eq!(
+        parse_symbols("main = $0200").unwrap().get("main"),
+        Some(0x0200)
+    );
+}
+
+#[test]
+fn test_labelled_disassembly_and_trace() {
+    let mut vm = common::vm(PROGRAM);
+    vm.symbols = parse_dbg(DBG).unwrap();
+    vm.symbols.insert("counter", 0x0010);
+
+    let listing = vm
+        .disassemble_count(0x0200, 5)
+        .iter()
+        .map(|i| i.labelled(&vm.symbols))
+        .collect::<Vec<String>>();
+    assert_eq!(
+        listing,
+        ["JSR delay", "BRK", "LDX #$03", "DEX", "BNE delay::loop"]
+    );
+
+    let lda = disassemble(&[0xA5, 0x10, 0xB5, 0x11, 0xB1, 0x10], 0x0300);
+    assert_eq!(lda[0].labelled(&vm.symbols), "LDA counter");
+    assert_eq!(lda[1].labelled(&vm.symbols), "LDA $11,X");
+    assert_eq!(lda[2].labelled(&vm.symbols), "LDA (counter),Y");
+    assert_eq!(
+        vm.disassemble_count(0x0200, 1)[0].labelled_listing(&vm.symbols),
+        "0200  20 04 02  JSR delay"
+    );
+
+    let trace = (0..4)
+        .map(|_| vm.step_traced().unwrap().text)
+        .collect::<Vec<String>>();
+    assert_eq!(trace, ["JSR delay", "LDX #$03", "DEX", "BNE delay::loop"]);
+}
+
+#[test]
+fn test_symbol_breakpoints() {
+    let path = temp("main.dbg");
+    std::fs::write(&path, DBG).unwrap();
+
+    let mut vm = common::vm(PROGRAM);
+    assert_eq!(vm.load_symbols(&path).unwrap(), 3);
+
+    assert_eq!(vm.add_symbol_breakpoint("nope"), None);
+    let id = vm.add_symbol_breakpoint("delay::loop+1").unwrap();
+    assert_eq!(
+        vm.execute().unwrap(),
+        StopReason::Breakpoint { id, pc: 0x0207 }
+    );
+    assert_eq!(vm.symbols.describe(vm.registers.pc), "delay::loop+1");
+
+    std::fs::write(&path, "main = $0200\nmain").unwrap();
+    let err = vm.load_symbols(&path).unwrap_err();
+    assert!(matches!(err, VmError::MalformedProgram { .. }));
+    assert!(err.to_string().contains("line 2: syntax error"));
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(vm.load_symbols(&path), Err(VmError::Io { .. })));
+}
+
+#[test]
+fn test_image_symbols() {
+    let mut image = ProgramImage::new();
+    image.add_segment(0x0200, vec![0xEA]);
+    image.symbols.insert("start".to_string(), 0x0200);
+
+    let mut vm = VirtualMachine::new();
+    vm.symbols.insert("data", 0x0300);
+    vm.load_image(&image).unwrap();
+    assert_eq!(vm.symbols.get("start"), Some(0x0200));
+    assert_eq!(vm.symbols.get("data"), Some(0x0300));
+}
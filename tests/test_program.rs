@@ -3,12 +3,12 @@ use vm6502::prelude::*;
 #[test]
 fn test_insert_bytes() {
     let mut vm = VirtualMachine::new();
-    vm.insert_bytes(0x0000, vec![0x69, 0x01]);
+    vm.insert_bytes(0x0000, vec![0x69, 0x01]).unwrap();
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 0x0000], 0x69);
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 0x0001], 0x01);
 
     let prog = vec![0x69, 0x01, 0x69, 0x02, 0x69, 0x03];
-    vm.insert_bytes(0x0F00, prog);
+    vm.insert_bytes(0x0F00, prog).unwrap();
 
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 0x0F00], 0x69);
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 0x0F01], 0x01);
@@ -37,10 +37,86 @@ fn test_set_interrupt_vectors() {
 fn test_load_program() {
     let mut vm = VirtualMachine::new();
     // Get the program from the file in binaries/square_ints.a65
-    vm.load_program(0x0000, "binaries/square_ints.a65");
+    vm.load_program(0x0000, "binaries/square_ints.a65").unwrap();
 
     assert_eq!(vm.flatmap[vm.heap_bounds.0], 0xA0);
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 1], 0x00);
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 2], 0x84);
     assert_eq!(vm.flatmap[vm.heap_bounds.0 + 3], 0x32);
 }
+
+#[test]
+fn test_load_odd_length_program() {
+    let mut vm = VirtualMachine::new();
+    let path = std::env::temp_dir().join(format!("vm6502_odd_{}.bin", std::process::id()));
+    std::fs::write(&path, [0xA9, 0x01, 0x00]).unwrap();
+
+    vm.load_program(0x0000, path.to_str().unwrap()).unwrap();
+    assert_eq!(vm.flatmap[vm.heap_bounds.0 + 2], 0x00);
+    assert_eq!(vm.flatmap[vm.heap_bounds.0 + 1], 0x01);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_load_program_to_end_of_memory() {
+    let mut vm = VirtualMachine::new();
+    let path = std::env::temp_dir().join(format!("vm6502_top_{}.bin", std::process::id()));
+    std::fs::write(&path, [0xEA, 0xEA, 0x00]).unwrap();
+    let path = path.to_str().unwrap();
+
+    // $FFFD-$FFFF is the last place three bytes fit.
+    vm.load_program(0xFDFD, path).unwrap();
+    assert_eq!(vm.flatmap[0xFFFF], 0x00);
+
+    assert!(matches!(
+        vm.load_program(0xFDFE, path),
+        Err(VmError::OutOfBounds { .. })
+    ));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_reset() {
+    let mut vm = VirtualMachine::new();
+    vm.insert_bytes(0x0000, vec![0xEA, 0xEA]).unwrap();
+    vm.set_interrupt_vectors(0xFFFA, 0x0200, 0xFFFE);
+    vm.registers.ac = 0x42;
+    vm.halted = true;
+
+    vm.reset();
+
+    assert_eq!(vm.registers.pc, 0x0200);
+    assert_eq!(vm.registers.sp, 0xFD);
+    assert_eq!(vm.registers.ac, 0x42);
+    assert!(vm.get_status(Status::Interrupt));
+    assert_eq!(vm.cycles, 7);
+    assert!(!vm.halted);
+
+    // Memory is left intact.
+    assert_eq!(vm.flatmap[vm.heap_bounds.0], 0xEA);
+    assert_eq!(vm.flatmap[0xFFFC], 0x00);
+    assert_eq!(vm.flatmap[0xFFFD], 0x02);
+
+    // Pushes land just below the reset SP.
+    vm.push(0x99).unwrap();
+    assert_eq!(vm.flatmap[0x01FD], 0x99);
+}
+
+#[test]
+fn test_power_on() {
+    let mut vm = VirtualMachine::new();
+    vm.insert_bytes(0x0000, vec![0xEA, 0xEA]).unwrap();
+    vm.set_interrupt_vectors(0xFFFA, 0x0200, 0xFFFE);
+    vm.registers.ac = 0x42;
+    vm.cycles = 100;
+
+    vm.power_on();
+
+    assert_eq!(vm.flatmap[vm.heap_bounds.0], 0x00);
+    assert_eq!(vm.flatmap[0xFFFD], 0x00);
+    assert_eq!(vm.registers.ac, 0x00);
+    assert_eq!(vm.registers.pc, 0x0000);
+    assert_eq!(vm.cycles, 0);
+}
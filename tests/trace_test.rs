@@ -0,0 +1,85 @@
+use vm6502::prelude::*;
+
+const NESTEST: &str = "\
+C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10
+C72D  04 A9    *NOP $A9 = 00                    A:AA X:97 Y:4E P:EF SP:F5 PPU: 78, 21 CYC:2603
+";
+
+#[test]
+fn test_parse_nestest() {
+    let trace = parse_trace(NESTEST);
+
+    assert_eq!(trace.len(), 3);
+    assert_eq!(trace[0].pc, 0xC000);
+    assert_eq!(trace[0].bytes, vec![0x4C, 0xF5, 0xC5]);
+    assert_eq!(trace[0].text, "JMP $C5F5");
+    assert_eq!(trace[0].p, 0x24);
+    assert_eq!(trace[0].sp, 0xFD);
+    assert_eq!(trace[0].cycles, 7);
+
+    assert_eq!(trace[2].bytes, vec![0x04, 0xA9]);
+    assert_eq!(trace[2].text, "*NOP $A9 = 00");
+    assert_eq!(trace[2].ac, 0xAA);
+    assert_eq!(trace[2].x, 0x97);
+    assert_eq!(trace[2].y, 0x4E);
+    assert_eq!(trace[2].cycles, 2603);
+
+    assert!(TraceLine::parse("not a trace line").is_none());
+    // A multi-byte char straddling the bytes column isn't a trace line either.
+    assert!(
+        TraceLine::parse("C000  4C F5 C5 éJMP $C5F5 A:00 X:00 Y:00 P:24 SP:FD CYC:7").is_none()
+    );
+}
+
+#[test]
+fn test_trace_round_trip() {
+    let mut vm = VirtualMachine::new();
+    // LDX #$05, DEX, BNE -3, BRK
+    vm.set_program(0x0000, "A205CAD0FD00").unwrap();
+
+    let mut trace = Vec::new();
+    while !vm.halted {
+        trace.push(vm.step_traced().unwrap());
+    }
+
+    assert_eq!(trace.len(), 1 + 5 * 2 + 1);
+    assert_eq!(
+        trace[2].to_string(),
+        "0203  D0 FD     BNE $0202                       A:00 X:04 Y:00 P:20 SP:FF CYC:4"
+    );
+
+    let text = trace
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+    assert_eq!(parse_trace(&text), trace);
+}
+
+#[test]
+fn test_diff_traces() {
+    let expected = parse_trace(NESTEST);
+    assert!(diff_traces(&expected, &expected).is_none());
+
+    let mut actual = expected.clone();
+    actual[1].x = 0x01;
+    actual[1].p |= 0x01;
+    // B and bit 5 are ignored.
+    actual[0].p &= !0x20;
+
+    let diff = diff_traces(&expected, &actual).unwrap();
+    assert_eq!(diff.index, 1);
+    assert_eq!(
+        diff.fields,
+        vec![TraceField::X, TraceField::Flag(Status::Carry)]
+    );
+    assert!(diff
+        .to_string()
+        .starts_with("traces diverge at instruction 1"));
+
+    let diff = diff_traces(&expected, &expected[..2]).unwrap();
+    assert_eq!(diff.index, 2);
+    assert_eq!(diff.fields, vec![TraceField::Length]);
+    assert!(diff.actual.is_none());
+}
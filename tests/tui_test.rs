@@ -0,0 +1,158 @@
+use vm6502::prelude::*;
+
+mod common;
+
+// 0x0200 LDA #$41
+// 0x0202 STA $10
+// 0x0204 JSR $0208
+// 0x0207 BRK
+// 0x0208 INX
+// 0x0209 RTS
+const PROGRAM: &str = "A941851020080200E860";
+
+fn screen(tui: &Tui, vm: &VirtualMachine) -> Vec<String> {
+    tui.render(vm, 100, 30)
+        .iter()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+/// The style of the first span on a line containing `text`.
+fn style_of(lines: &[Line], text: &str) -> Option<Style> {
+    lines
+        .iter()
+        .flat_map(|l| &l.0)
+        .find(|s| s.text.contains(text))
+        .map(|s| s.style)
+}
+
+#[test]
+fn test_layout() {
+    let vm = common::vm(PROGRAM);
+    let tui = Tui::new();
+
+    for (width, height) in [(100, 30), (80, 24), (20, 5)] {
+        let lines = tui.render(&vm, width, height);
+        assert_eq!(lines.len(), height as usize);
+        assert!(lines.iter().all(|l| l.width() == width as usize));
+    }
+
+    let screen = screen(&tui, &vm);
+    assert!(screen[0].starts_with(" Registers"));
+    assert!(screen[0].contains("│ Disassembly"));
+    assert!(screen[2].starts_with(" 0200 00 00 00 FF"));
+    assert!(screen[4].starts_with("       00000000"));
+    assert!(screen[5].starts_with(" Cycles 0"));
+    assert!(screen
+        .iter()
+        .any(|l| l.contains("> 0200  A9 41     LDA #$41")));
+    assert!(screen.iter().any(|l| l.starts_with(" 01F8 00")));
+    assert!(screen
+        .iter()
+        .any(|l| l.starts_with(" 0200  A9 41 85 10 20 08 02 00 E8 60")));
+}
+
+#[test]
+fn test_stepping() {
+    let mut vm = common::vm(PROGRAM);
+    let mut tui = Tui::new();
+    tui.memory = 0x0000;
+
+    tui.handle_key(&mut vm, Key::Char('s'));
+    tui.handle_key(&mut vm, Key::Char('s'));
+    assert_eq!(vm.registers.pc, 0x0204);
+
+    // The STA changed $10, which the memory view highlights until the next command.
+    let lines = tui.render(&vm, 100, 30);
+    let row = lines
+        .iter()
+        .find(|l| l.to_string().starts_with(" 0010 "))
+        .unwrap();
+    let changed = row.0.iter().find(|s| s.style == Style::Changed).unwrap();
+    assert_eq!(changed.text, "41");
+    assert_eq!(style_of(&lines, "> 0204"), Some(Style::Current));
+
+    tui.handle_key(&mut vm, Key::Char('n'));
+    assert_eq!(vm.registers.pc, 0x0207);
+    assert_eq!(vm.registers.x, 0x01);
+    let lines = tui.render(&vm, 100, 30);
+    assert!(!lines
+        .iter()
+        .flat_map(|l| &l.0)
+        .any(|s| s.style == Style::Changed && s.text == "41"));
+
+    tui.handle_key(&mut vm, Key::F(11));
+    assert!(screen(&tui, &vm).last().unwrap().starts_with("halted"));
+}
+
+#[test]
+fn test_breakpoints() {
+    let mut vm = common::vm(PROGRAM);
+    let mut tui = Tui::new();
+
+    // Select the JSR and toggle a breakpoint on it.
+    tui.handle_key(&mut vm, Key::Down);
+    tui.handle_key(&mut vm, Key::Down);
+    tui.handle_key(&mut vm, Key::Char('b'));
+    assert_eq!(vm.breakpoints(), &[(0, Breakpoint::Pc(0x0204))]);
+    assert_eq!(
+        style_of(&tui.render(&vm, 100, 30), " *0204"),
+        Some(Style::Selected)
+    );
+
+    tui.handle_key(&mut vm, Key::Up);
+    tui.handle_key(&mut vm, Key::Esc);
+    assert_eq!(
+        style_of(&tui.render(&vm, 100, 30), " *0204"),
+        Some(Style::Breakpoint)
+    );
+
+    tui.handle_key(&mut vm, Key::Char('c'));
+    assert!(tui.running());
+    tui.run_slice(&mut vm, 100);
+    assert!(!tui.running());
+    assert_eq!(vm.registers.pc, 0x0204);
+    assert!(screen(&tui, &vm)
+        .last()
+        .unwrap()
+        .starts_with("breakpoint 0"));
+    assert!(screen(&tui, &vm).iter().any(|l| l.contains(">*0204")));
+
+    tui.handle_key(&mut vm, Key::F(9));
+    assert!(vm.breakpoints().is_empty());
+}
+
+#[test]
+fn test_pause() {
+    // 0x0200 JMP $0200
+    let mut vm = common::vm("4C0002");
+    let mut tui = Tui::new();
+
+    tui.handle_key(&mut vm, Key::F(5));
+    tui.run_slice(&mut vm, 1000);
+    assert!(tui.running());
+
+    // Any key pauses rather than acting.
+    assert_eq!(tui.handle_key(&mut vm, Key::Char('q')), Action::Redraw);
+    assert!(!tui.running());
+    assert!(screen(&tui, &vm).last().unwrap().starts_with("paused"));
+    assert_eq!(tui.handle_key(&mut vm, Key::Char('q')), Action::Quit);
+}
+
+#[test]
+fn test_memory_scroll() {
+    let mut vm = common::vm(PROGRAM);
+    let mut tui = Tui::new();
+
+    tui.handle_key(&mut vm, Key::PageDown);
+    tui.handle_key(&mut vm, Key::Char('+'));
+    assert_eq!(tui.memory, 0x0310);
+    tui.handle_key(&mut vm, Key::Char('-'));
+    tui.handle_key(&mut vm, Key::PageUp);
+    tui.handle_key(&mut vm, Key::PageUp);
+    tui.handle_key(&mut vm, Key::PageUp);
+    assert_eq!(tui.memory, 0x0000);
+    tui.handle_key(&mut vm, Key::Char('-'));
+    assert_eq!(tui.memory, 0xFFF0);
+    assert!(screen(&tui, &vm).iter().any(|l| l.starts_with(" FFF0 ")));
+}
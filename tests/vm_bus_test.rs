@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use vm6502::prelude::*;
+
+/// A write only output port, recording everything written to it.
+struct Port(Rc<RefCell<Vec<u8>>>);
+
+impl Bus for Port {
+    fn read(&mut self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _address: u16, value: u8) {
+        self.0.borrow_mut().push(value);
+    }
+
+    fn peek(&self, _address: u16) -> u8 {
+        0xFF
+    }
+}
+
+#[test]
+fn test_memory_mapped_io() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut bus = MemoryMap::new();
+    bus.map(0xD000, 0xD0FF, Port(output.clone()));
+
+    let mut vm = VirtualMachine::with_bus(bus);
+    let prog = assemble("LDA #$42\nSTA $D000\nLDX #$01\nSTX $D010\nLDA $D000").unwrap();
+    vm.insert_bytes(0x0000, prog).unwrap();
+    vm.registers.pc = vm.heap_bounds.0 as u16;
+
+    for _ in 0..5 {
+        vm.step().unwrap();
+    }
+
+    assert_eq!(*output.borrow(), vec![0x42, 0x01]);
+    assert_eq!(vm.registers.ac, 0xFF);
+}
+
+#[test]
+fn test_rom_and_mirror() {
+    let mut bus = MemoryMap::new();
+    bus.map_rom(0xE000, vec![0xEA; 0x100]);
+    bus.mirror(0x0800, 0x1FFF, 0x0000, 0x0800);
+
+    let mut vm = VirtualMachine::with_bus(bus);
+    vm.set_program(0x0000, "A9018D0018A9028D00E0").unwrap();
+    for _ in 0..4 {
+        vm.step().unwrap();
+    }
+
+    // 0x1800 mirrors 0x0000, and the ROM ignores the write.
+    assert_eq!(vm.flatmap.peek(0x0000), 0x01);
+    assert_eq!(vm.flatmap.peek(0x0800), 0x01);
+    assert_eq!(vm.flatmap.peek(0xE000), 0xEA);
+    assert_eq!(vm.flatmap.ram[0xE000], 0x00);
+}
+
+#[test]
+fn test_default_bus_addressing() {
+    let mut vm = VirtualMachine::new();
+    let prog = assemble(
+        "
+        .org $0200
+                LDX #$04
+                LDA #$34
+                STA $10
+                LDA #$12
+                STA $11
+                LDA #$99
+                STA ($0C,X)
+                STA $0300,X
+                JMP done
+                .byte $00
+        done:   INC $0304
+    ",
+    )
+    .unwrap();
+    vm.set_program(0x0000, &hex::encode(prog)).unwrap();
+
+    for _ in 0..10 {
+        vm.step().unwrap();
+    }
+
+    assert_eq!(vm.flatmap[0x1234], 0x99);
+    assert_eq!(vm.flatmap[0x0304], 0x9A);
+}
+
+#[test]
+fn test_load_image_into_rom() {
+    let mut bus = MemoryMap::new();
+    bus.map_rom(0xE000, vec![0xEA; 0x100]);
+
+    let mut vm = VirtualMachine::with_bus(bus);
+    let mut image = ProgramImage::new();
+    image.add_segment(0x0300, vec![0xA9, 0x01]);
+    image.add_segment(0xE0FE, vec![0x01, 0x02, 0x03]);
+    image.entry = Some(0x0300);
+
+    assert!(matches!(
+        vm.load_image(&image),
+        Err(VmError::MalformedProgram { reason, .. }) if reason.contains("$E0FE")
+    ));
+    assert_eq!(vm.flatmap.peek(0xE0FE), 0xEA);
+    assert_eq!(vm.registers.pc, 0x0000);
+
+    // The part past the ROM loads fine on its own.
+    let mut image = ProgramImage::new();
+    image.add_segment(0xE100, vec![0x03]);
+    vm.load_image(&image).unwrap();
+    assert_eq!(vm.flatmap.peek(0xE100), 0x03);
+}
+
+#[test]
+fn test_map_rom_sizes() {
+    let mut bus = MemoryMap::new();
+    let rom = (0..0x10000).map(|i| (i >> 8) as u8).collect::<Vec<u8>>();
+    bus.map_rom(0x0000, rom);
+    assert_eq!(bus.peek(0x0000), 0x00);
+    assert_eq!(bus.peek(0x1234), 0x12);
+    assert_eq!(bus.peek(0xFFFF), 0xFF);
+
+    // An image running past 0xFFFF is cut off at the end of memory.
+    let mut bus = MemoryMap::new();
+    bus.map_rom(0xFFFE, vec![0x01, 0x02, 0x03]);
+    assert_eq!((bus.peek(0xFFFE), bus.peek(0xFFFF)), (0x01, 0x02));
+    assert_eq!(bus.peek(0x0000), 0x00);
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use vm6502::prelude::*;
+
+mod common;
+
+// 0x0200 LDX #$00
+// 0x0202 INX
+// 0x0203 STX $10
+// 0x0205 LDA $10
+// 0x0207 BRK
+const PROGRAM: &str = "A200E88610A51000";
+
+#[test]
+fn test_pc_breakpoint() {
+    let mut vm = common::vm(PROGRAM);
+    let id = vm.add_breakpoint(Breakpoint::Pc(0x0203));
+
+    assert_eq!(
+        vm.execute().unwrap(),
+        StopReason::Breakpoint { id, pc: 0x0203 }
+    );
+    assert_eq!(vm.registers.pc, 0x0203);
+    assert_eq!(vm.registers.x, 0x01);
+
+    // Resuming steps over the breakpoint it stopped at.
+    assert_eq!(vm.execute().unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn test_watchpoints() {
+    let mut vm = common::vm(PROGRAM);
+    let write = vm.add_breakpoint(Breakpoint::Watch {
+        start: 0x0010,
+        end: 0x001F,
+        access: Access::Write,
+    });
+    let read = vm.add_breakpoint(Breakpoint::Watch {
+        start: 0x0010,
+        end: 0x0010,
+        access: Access::Read,
+    });
+
+    assert_eq!(
+        vm.execute().unwrap(),
+        StopReason::Watchpoint {
+            id: write,
+            address: 0x0010,
+            access: Access::Write
+        }
+    );
+    // The access completes before stopping.
+    assert_eq!(vm.registers.pc, 0x0205);
+    assert_eq!(vm.flatmap[0x0010], 0x01);
+
+    assert_eq!(
+        vm.execute().unwrap(),
+        StopReason::Watchpoint {
+            id: read,
+            address: 0x0010,
+            access: Access::Read
+        }
+    );
+    assert_eq!(vm.registers.ac, 0x01);
+}
+
+#[test]
+fn test_operand_fetches_dont_watch() {
+    // 0x0200 LDA #$01
+    // 0x0202 LDX $10
+    // 0x0204 BNE $0206
+    // 0x0206 LDA $0204
+    // 0x0209 BRK
+    let mut vm = common::vm("A901A610D000AD040200");
+    let read = vm.add_breakpoint(Breakpoint::Watch {
+        start: 0x0200,
+        end: 0x0210,
+        access: Access::Read,
+    });
+
+    // Only the data read of the code bytes stops, not the immediate or branch operands.
+    assert_eq!(
+        vm.execute().unwrap(),
+        StopReason::Watchpoint {
+            id: read,
+            address: 0x0204,
+            access: Access::Read
+        }
+    );
+    assert_eq!(vm.registers.pc, 0x0209);
+    assert_eq!(vm.registers.ac, 0xD0);
+}
+
+#[test]
+fn test_conditions() {
+    let mut vm = common::vm(PROGRAM);
+    let x = vm.add_breakpoint(Breakpoint::Register {
+        register: Register::X,
+        value: 0x01,
+    });
+
+    assert_eq!(vm.execute().unwrap(), StopReason::Condition { id: x });
+    assert_eq!(vm.registers.pc, 0x0203);
+
+    assert!(vm.remove_breakpoint(x));
+    assert!(!vm.remove_breakpoint(x));
+
+    let zero = vm.add_breakpoint(Breakpoint::Flag {
+        flag: Status::Interrupt,
+        set: true,
+    });
+    assert_ne!(zero, x);
+    assert_eq!(vm.breakpoints().len(), 1);
+
+    // BRK sets I.
+    assert_eq!(vm.execute().unwrap(), StopReason::Halted);
+    vm.halted = false;
+    assert_eq!(vm.execute().unwrap(), StopReason::Condition { id: zero });
+
+    vm.clear_breakpoints();
+    assert!(vm.breakpoints().is_empty());
+}
+
+#[test]
+fn test_run_stop_reason() {
+    let mut vm = common::vm(PROGRAM);
+    let id = vm.add_breakpoint(Breakpoint::Pc(0x0205));
+
+    let (cycles, _, reason) = vm.run(Duration::from_secs(1)).unwrap();
+    assert_eq!(reason, StopReason::Breakpoint { id, pc: 0x0205 });
+    // LDX, INX, STX
+    assert_eq!(cycles, 2 + 2 + 3);
+
+    let (_, _, reason) = vm.run(Duration::ZERO).unwrap();
+    assert_eq!(reason, StopReason::TimedOut);
+}
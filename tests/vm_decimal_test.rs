@@ -0,0 +1,100 @@
+use vm6502::prelude::*;
+
+mod common;
+use common::flags;
+
+/// Run `op #operand` with `ac` and the given carry and decimal flags.
+fn arithmetic(op: u8, ac: u8, operand: u8, carry: bool, decimal: bool) -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, &format!("{:02X}{:02X}", op, operand))
+        .unwrap();
+    vm.registers.ac = ac;
+    vm.set_status(Status::Carry, carry);
+    vm.set_status(Status::Decimal, decimal);
+
+    vm.step().unwrap();
+    vm
+}
+
+#[test]
+fn test_binary_adc() {
+    let vm = arithmetic(0x69, 0x50, 0x50, false, false);
+    assert_eq!(vm.registers.ac, 0xA0);
+    assert_eq!(flags(&vm), (false, false, true, true));
+
+    let vm = arithmetic(0x69, 0xFF, 0x00, true, false);
+    assert_eq!(vm.registers.ac, 0x00);
+    assert_eq!(flags(&vm), (true, true, false, false));
+
+    let vm = arithmetic(0x69, 0xD0, 0x90, false, false);
+    assert_eq!(vm.registers.ac, 0x60);
+    assert_eq!(flags(&vm), (true, false, true, false));
+}
+
+#[test]
+fn test_binary_sbc() {
+    let vm = arithmetic(0xE9, 0x05, 0x03, true, false);
+    assert_eq!(vm.registers.ac, 0x02);
+    assert_eq!(flags(&vm), (true, false, false, false));
+
+    let vm = arithmetic(0xE9, 0x50, 0xB0, true, false);
+    assert_eq!(vm.registers.ac, 0xA0);
+    assert_eq!(flags(&vm), (false, false, true, true));
+
+    let vm = arithmetic(0xE9, 0x00, 0x00, false, false);
+    assert_eq!(vm.registers.ac, 0xFF);
+    assert_eq!(flags(&vm), (false, false, false, true));
+}
+
+#[test]
+fn test_decimal_adc() {
+    let vm = arithmetic(0x69, 0x12, 0x34, false, true);
+    assert_eq!(vm.registers.ac, 0x46);
+    assert!(!vm.get_status(Status::Carry));
+
+    let vm = arithmetic(0x69, 0x58, 0x46, true, true);
+    assert_eq!(vm.registers.ac, 0x05);
+    assert!(vm.get_status(Status::Carry));
+
+    let vm = arithmetic(0x69, 0x81, 0x92, false, true);
+    assert_eq!(vm.registers.ac, 0x73);
+    assert!(vm.get_status(Status::Carry));
+    assert!(vm.get_status(Status::Overflow));
+}
+
+#[test]
+fn test_decimal_sbc() {
+    let vm = arithmetic(0xE9, 0x46, 0x12, true, true);
+    assert_eq!(vm.registers.ac, 0x34);
+    assert!(vm.get_status(Status::Carry));
+
+    let vm = arithmetic(0xE9, 0x40, 0x13, true, true);
+    assert_eq!(vm.registers.ac, 0x27);
+
+    let vm = arithmetic(0xE9, 0x32, 0x02, false, true);
+    assert_eq!(vm.registers.ac, 0x29);
+    assert!(vm.get_status(Status::Carry));
+
+    let vm = arithmetic(0xE9, 0x12, 0x21, true, true);
+    assert_eq!(vm.registers.ac, 0x91);
+    assert!(!vm.get_status(Status::Carry));
+}
+
+#[cfg(not(feature = "cmos"))]
+#[test]
+fn test_nmos_decimal_flags() {
+    // 99 + 1 = 100, but N and Z come from the binary sum 0x9A.
+    let vm = arithmetic(0x69, 0x99, 0x01, false, true);
+    assert_eq!(vm.registers.ac, 0x00);
+    assert_eq!(flags(&vm), (true, false, false, true));
+    assert_eq!(vm.cycles, 2);
+}
+
+#[cfg(feature = "cmos")]
+#[test]
+fn test_cmos_decimal_flags() {
+    let vm = arithmetic(0x69, 0x99, 0x01, false, true);
+    assert_eq!(vm.registers.ac, 0x00);
+    assert_eq!(flags(&vm), (true, true, false, false));
+    assert_eq!(vm.cycles, 3);
+}
@@ -0,0 +1,76 @@
+use vm6502::prelude::*;
+
+#[test]
+fn test_illegal_opcode() {
+    let mut vm = VirtualMachine::new();
+    vm.undocumented = false;
+    vm.set_program(0x0010, "6901FF").unwrap();
+
+    vm.step().unwrap();
+    let err = vm.step().unwrap_err();
+
+    assert_eq!(
+        err,
+        VmError::IllegalOpcode {
+            pc: 0x0212,
+            opcode: 0xFF
+        }
+    );
+    assert_eq!(err.pc(), 0x0212);
+    assert_eq!(err.opcode(), 0xFF);
+}
+
+#[test]
+fn test_out_of_bounds() {
+    let mut vm = VirtualMachine::new();
+
+    let err = vm.get_heap(0xFF00).unwrap_err();
+    assert!(matches!(
+        err,
+        VmError::OutOfBounds {
+            address: 0x10100,
+            ..
+        }
+    ));
+
+    let err = vm.insert_bytes(0xFDFF, vec![0xEA, 0xEA]).unwrap_err();
+    assert!(matches!(
+        err,
+        VmError::OutOfBounds {
+            address: 0x10000,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_stack_errors() {
+    let mut vm = VirtualMachine::new();
+
+    assert!(matches!(
+        vm.pop().unwrap_err(),
+        VmError::StackUnderflow { .. }
+    ));
+
+    vm.fill_stack(vec![0xEA; 0xFF]).unwrap();
+    assert_eq!(vm.registers.sp, 0x00);
+    assert!(matches!(
+        vm.push(0x42).unwrap_err(),
+        VmError::StackOverflow { .. }
+    ));
+    // The push still lands at 0x0100 before SP wraps around.
+    assert_eq!(vm.flatmap[0x0100], 0x42);
+    assert_eq!(vm.registers.sp, 0xFF);
+}
+
+#[test]
+fn test_malformed_program() {
+    let mut vm = VirtualMachine::new();
+
+    let err = vm.insert_program(0x0000, "69F").unwrap_err();
+    assert!(matches!(err, VmError::MalformedProgram { .. }));
+
+    let err = vm.load_program(0x0000, "binaries/missing.bin").unwrap_err();
+    assert!(matches!(err, VmError::Io { .. }));
+    assert!(err.to_string().starts_with("io error: failed to read"));
+}
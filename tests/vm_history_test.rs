@@ -0,0 +1,80 @@
+use vm6502::prelude::*;
+
+mod common;
+
+// 0x0200 LDX #$03
+// 0x0202 TXA
+// 0x0203 PHA
+// 0x0204 STA $10,X
+// 0x0206 INC $10
+// 0x0208 DEX
+// 0x0209 BNE $0202
+// 0x020B BRK
+const PROGRAM: &str = "A2038A489510E610CAD0F700";
+
+#[test]
+fn test_step_back_restores_everything() {
+    let mut vm = common::vm(PROGRAM);
+    vm.enable_history(1024);
+    let before = vm.snapshot();
+
+    vm.execute().unwrap();
+    assert_eq!(vm.flatmap[0x10], 0x03);
+    assert_eq!(vm.flatmap[0x13], 0x03);
+    assert_eq!(vm.registers.sp, 0xF9);
+
+    while vm.step_back() {}
+
+    assert_eq!(vm.history_len(), 0);
+    assert_eq!(vm.snapshot(), before);
+}
+
+#[test]
+fn test_run_back_to_pc() {
+    let mut vm = common::vm(PROGRAM);
+    vm.enable_history(1024);
+    vm.execute().unwrap();
+
+    // Back to the last pass through the loop body.
+    assert!(vm.run_back_to_pc(0x0204));
+    assert_eq!(vm.registers.x, 0x01);
+    assert_eq!(vm.flatmap[0x11], 0x00);
+
+    assert!(vm.run_back_to_pc(0x0204));
+    assert_eq!(vm.registers.x, 0x02);
+
+    assert!(!vm.run_back_to_pc(0x1234));
+    assert_eq!(vm.registers.pc, 0x0200);
+}
+
+#[test]
+fn test_rewind_cycles() {
+    let mut vm = common::vm(PROGRAM);
+    vm.enable_history(1024);
+    vm.run_for_instructions(4).unwrap();
+    let cycles = vm.cycles;
+
+    // STA $10,X takes 4 cycles, PHA 3.
+    assert_eq!(vm.rewind_cycles(5), 7);
+    assert_eq!(vm.cycles, cycles - 7);
+    assert_eq!(vm.registers.pc, 0x0203);
+
+    assert_eq!(vm.rewind_cycles(1000), cycles - 7);
+    assert_eq!(vm.cycles, 0);
+}
+
+#[test]
+fn test_history_is_bounded() {
+    let mut vm = common::vm(PROGRAM);
+    vm.enable_history(3);
+    vm.run_for_instructions(6).unwrap();
+
+    assert_eq!(vm.history_len(), 3);
+    // Only STA, INC and DEX are kept.
+    assert!(vm.run_back_to_pc(0x0204));
+    assert!(!vm.step_back());
+
+    vm.disable_history();
+    vm.step().unwrap();
+    assert!(!vm.step_back());
+}
@@ -6,13 +6,13 @@ use vm6502::utils::machine_arrays::VALID_CYCLE_COUNTS;
 #[test]
 fn adc_imd() {
     let mut vm = VirtualMachine::new();
-    vm.set_program(0x0000, "69F06901");
+    vm.set_program(0x0000, "69F06901").unwrap();
 
     vm.registers.ac = 0x0F;
-    vm.step();
+    vm.step().unwrap();
     assert_eq!(vm.registers.ac, 0xFF);
 
-    vm.step();
+    vm.step().unwrap();
     assert_eq!(vm.registers.sr & status!(Status::Carry), 1);
     assert_eq!(vm.registers.ac, 0x00);
 }
@@ -21,22 +21,22 @@ fn adc_imd() {
 #[test]
 fn and_imd() {
     let mut vm = VirtualMachine::new();
-    vm.set_program(0x0000, "29FF29FF2900");
+    vm.set_program(0x0000, "29FF29FF2900").unwrap();
     eprintln!("Program: {:?}...", &vm.flatmap[0x0200..0x0203]);
     vm.registers.ac = 0x00;
 
-    vm.step();
+    vm.step().unwrap();
     assert_eq!(vm.registers.ac, 0x00);
     assert_eq!(vm.get_status(Status::Zero), true);
 
     vm.registers.ac = 0xFF;
-    eprintln!("PC byte 0: {}", vm.get_heap(vm.registers.pc));
-    eprintln!("PC byte 1: {}", vm.get_heap(vm.registers.pc + 1));
-    vm.step();
+    eprintln!("PC byte 0: {}", vm.get_heap(vm.registers.pc).unwrap());
+    eprintln!("PC byte 1: {}", vm.get_heap(vm.registers.pc + 1).unwrap());
+    vm.step().unwrap();
     assert_eq!(vm.registers.ac, 0xFF & 0xFF);
     assert_eq!(vm.get_status(Status::Zero), false);
 
-    vm.step();
+    vm.step().unwrap();
     assert_eq!(vm.registers.ac, 0x00);
     assert_eq!(vm.get_status(Status::Zero), true);
 }
@@ -45,23 +45,21 @@ fn and_imd() {
 fn asl_cover() {
     let mut vm = VirtualMachine::new();
     let prog = "0A0A0A0A0A0A0A0A0A";
-    vm.set_program(0x0000, prog);
+    vm.set_program(0x0000, prog).unwrap();
     vm.registers.ac = 0x01;
 
     for i in 1..8 {
-        vm.step();
+        vm.step().unwrap();
         eprintln!("i: {}, ac: {}", 1 << i, vm.registers.ac);
 
         assert_eq!(vm.registers.ac, 1 << i);
     }
 }
 
-#[ignore]
 #[test]
 fn check_cycles_used() {
     let mut vm = VirtualMachine::new();
 
-    let last_cycles: u64 = 0;
     for (i, valid_op) in VALID_OPCODES.iter().enumerate() {
         eprintln!(
             "i: {}, op: 0x{:02X}, {:?}",
@@ -69,11 +67,62 @@ fn check_cycles_used() {
             valid_op,
             opcode_name!(*valid_op)
         );
-        vm.registers.pc = 0x0000;
+        vm.registers = Registers::new();
+        vm.registers.pc = vm.heap_bounds.0 as u16;
+        vm.registers.sp = 0x80;
         vm.flatmap[vm.heap_bounds.0] = *valid_op;
 
-        vm.step();
+        let cycles = vm.step().unwrap();
 
-        assert_eq!((vm.cycles - last_cycles) as u8, VALID_CYCLE_COUNTS[i]);
+        // With a clear status register, BPL, BVC, BCC and BNE are taken.
+        let taken = matches!(*valid_op, 0x10 | 0x50 | 0x90 | 0xD0) as u64;
+        assert_eq!(cycles, VALID_CYCLE_COUNTS[i] as u64 + taken);
     }
 }
+
+#[test]
+fn page_cross_cycles() {
+    let mut vm = VirtualMachine::new();
+    let prog = assemble(
+        "
+        .org $0200
+        LDX #$20
+        LDA $02F0,X
+        LDA $0200,X
+        STA $02F0,X
+        LDA ($10),Y
+    ",
+    )
+    .unwrap();
+    vm.insert_bytes(0x0000, prog).unwrap();
+    vm.registers.pc = 0x0200;
+    vm.flatmap[0x10] = 0xFF;
+    vm.registers.y = 0x01;
+
+    let cycles = (0..5).map(|_| vm.step().unwrap()).collect::<Vec<u64>>();
+
+    // Reads take a cycle when indexing crosses a page, stores always pay it.
+    assert_eq!(cycles, vec![2, 5, 4, 5, 6]);
+    assert_eq!(vm.cycles, 22);
+}
+
+#[test]
+fn branch_cycles() {
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x00F0, "D0FE").unwrap();
+
+    // Taken, backwards onto itself.
+    assert_eq!(vm.step().unwrap(), 3);
+    assert_eq!(vm.registers.pc, 0x02F0);
+
+    // Taken, crossing into the next page.
+    vm.set_program(0x00F0, "D020").unwrap();
+    assert_eq!(vm.step().unwrap(), 4);
+    assert_eq!(vm.registers.pc, 0x0312);
+
+    // Not taken.
+    vm.set_status(Status::Zero, true);
+    vm.set_program(0x00F0, "D020").unwrap();
+    assert_eq!(vm.step().unwrap(), 2);
+    assert_eq!(vm.registers.pc, 0x02F2);
+}
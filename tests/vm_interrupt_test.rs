@@ -0,0 +1,82 @@
+use vm6502::prelude::*;
+
+fn vm_with_vectors() -> VirtualMachine {
+    let mut vm = VirtualMachine::new();
+    vm.set_interrupt_vectors(0x3000, 0x0200, 0x4000);
+    // NOP, NOP
+    vm.set_program(0x0000, "EAEA").unwrap();
+    vm
+}
+
+#[test]
+fn test_irq_masked() {
+    let mut vm = vm_with_vectors();
+    vm.set_status(Status::Interrupt, true);
+    vm.assert_irq();
+
+    assert_eq!(vm.step().unwrap(), 2);
+    assert_eq!(vm.registers.pc, 0x0201);
+}
+
+#[test]
+fn test_irq_taken() {
+    let mut vm = vm_with_vectors();
+    vm.set_status(Status::Interrupt, false);
+    vm.step().unwrap();
+    vm.assert_irq();
+
+    assert_eq!(vm.step().unwrap(), 7);
+    assert_eq!(vm.registers.pc, 0x4000);
+    assert!(vm.get_status(Status::Interrupt));
+
+    // B clear and bit 5 set on the pushed SR, followed by the return address.
+    assert_eq!(vm.pop().unwrap() & 0x30, 0x20);
+    assert_eq!(vm.pop().unwrap(), 0x01);
+    assert_eq!(vm.pop().unwrap(), 0x02);
+
+    // The line is level triggered but now masked by I.
+    vm.flatmap[0x4000] = 0xEA;
+    assert_eq!(vm.step().unwrap(), 2);
+}
+
+#[test]
+fn test_nmi_edge() {
+    let mut vm = vm_with_vectors();
+    vm.flatmap[0x3000] = 0xEA;
+    vm.flatmap[0x3001] = 0xEA;
+    vm.set_status(Status::Interrupt, true);
+    vm.assert_nmi();
+
+    assert_eq!(vm.step().unwrap(), 7);
+    assert_eq!(vm.registers.pc, 0x3000);
+
+    // Holding the line doesn't retrigger.
+    vm.assert_nmi();
+    assert_eq!(vm.step().unwrap(), 2);
+    assert_eq!(vm.registers.pc, 0x3001);
+
+    vm.release_nmi();
+    vm.assert_nmi();
+    assert_eq!(vm.step().unwrap(), 7);
+    assert_eq!(vm.registers.pc, 0x3000);
+}
+
+#[test]
+fn test_rti_returns() {
+    let mut vm = vm_with_vectors();
+    // RTI
+    vm.flatmap[0x4000] = 0x40;
+    vm.set_status(Status::Interrupt, false);
+    vm.set_status(Status::Negative, true);
+    vm.set_status(Status::Overflow, true);
+    vm.step().unwrap();
+    vm.assert_irq();
+    vm.step().unwrap();
+    vm.release_irq();
+
+    vm.step().unwrap();
+    assert_eq!(vm.registers.pc, 0x0201);
+    assert!(!vm.get_status(Status::Interrupt));
+    assert!(vm.get_status(Status::Negative));
+    assert!(vm.get_status(Status::Overflow));
+}
@@ -12,7 +12,7 @@ fn test_vm_stack() {
 
     // Push backwards because the stack grows from 0x01FF to 0x100.
     for i in 0x0FF..0 {
-        vm.push(test[i]);
+        vm.push(test[i]).unwrap();
     }
 
     eprintln!("Stack: {:?}", vm);
@@ -21,7 +21,7 @@ fn test_vm_stack() {
         assert_ne!(test[0x0FF - i - 1], 0);
         assert_ne!(vm.flatmap[0x0100 + i], 0);
         assert_eq!(vm.flatmap[0x0100 + i], test[0x0FF - i - 1]);
-        vm.pop();
+        vm.pop().unwrap();
         assert_eq!(vm.registers.ac, test[0x0FF - i - 1]);
         assert_ne!(vm.registers.ac, 0);
     }
@@ -36,7 +36,7 @@ fn test_vm_write_stack() {
     let test: [u8; 0x0FF] = test_vec.into_inner().unwrap();
 
     for i in 0..0x0FF {
-        vm.push(test[i]);
+        vm.push(test[i]).unwrap();
     }
 
     // TODO: For some reason stack isn't being written properly.
@@ -44,7 +44,7 @@ fn test_vm_write_stack() {
     eprintln!("Test: {:?}", test);
 
     for i in 0x0ff..=0 {
-        vm.pop();
+        vm.pop().unwrap();
         if test[i] != 0 {
             assert_ne!(vm.registers.ac, 0);
         }
@@ -62,7 +62,7 @@ fn test_vm_stack_contig() {
 
     // Push backwards because the stack grows from 0x01FF to 0x100.
     for i in 0..0x0FF {
-        vm.push(test[i]);
+        vm.push(test[i]).unwrap();
     }
 
     eprintln!("vm {:?}", vm);
@@ -114,7 +114,7 @@ fn test_vm_insert_program() {
     let decoded = decode(prog).unwrap();
 
     let offset = 0x0000;
-    vm.insert_program(offset, prog);
+    vm.insert_program(offset, prog).unwrap();
 
     for (i, byte) in decoded.iter().enumerate() {
         eprintln!("i: {}, byte: 0x{:02X}", i, byte);
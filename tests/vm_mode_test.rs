@@ -7,7 +7,7 @@ fn test_mode() {
     let mut vm = VirtualMachine::new();
 
     for (i, op) in VALID_OPCODES.iter().enumerate() {
-        let mode = vm.mode(*op);
+        let mode = vm.mode(*op).unwrap();
 
         assert_eq!(mode, OP_MODES[i]);
     }
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use vm6502::prelude::*;
+
+#[derive(Default)]
+struct Events(Vec<String>);
+
+impl Observer for Events {
+    fn on_fetch(&mut self, pc: u16, opcode: u8) {
+        self.0.push(format!("fetch {:04X} {:02X}", pc, opcode));
+    }
+
+    fn on_decode(&mut self, _pc: u16, _opcode: u8, mode: Mode) {
+        self.0.push(format!("decode {:?}", mode));
+    }
+
+    fn on_read(&mut self, address: u16, value: u8) {
+        self.0.push(format!("read {:04X} {:02X}", address, value));
+    }
+
+    fn on_write(&mut self, address: u16, value: u8) {
+        self.0.push(format!("write {:04X} {:02X}", address, value));
+    }
+
+    fn on_status(&mut self, old: u8, new: u8) {
+        self.0.push(format!("status {:02X} {:02X}", old, new));
+    }
+
+    fn on_push(&mut self, address: u16, value: u8) {
+        self.0.push(format!("push {:04X} {:02X}", address, value));
+    }
+
+    fn on_pop(&mut self, address: u16, value: u8) {
+        self.0.push(format!("pop {:04X} {:02X}", address, value));
+    }
+
+    fn on_interrupt(&mut self, kind: InterruptKind, vector: u16) {
+        self.0.push(format!("interrupt {:?} {:04X}", kind, vector));
+    }
+}
+
+fn observed(prog: &str) -> (VirtualMachine, Rc<RefCell<Events>>) {
+    let events = Rc::new(RefCell::new(Events::default()));
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, prog).unwrap();
+    vm.set_observer(events.clone());
+
+    (vm, events)
+}
+
+#[test]
+fn test_observer_events() {
+    // LDA #$00, PHA, PLA
+    let (mut vm, events) = observed("A9004868");
+    vm.registers.sr = 0x80;
+
+    for _ in 0..3 {
+        vm.step().unwrap();
+    }
+
+    assert_eq!(
+        events.borrow().0,
+        vec![
+            "fetch 0200 A9",
+            "decode Immediate",
+            "status 80 02",
+            "fetch 0202 48",
+            "decode Implied",
+            "write 01FF 00",
+            "push 01FF 00",
+            "fetch 0203 68",
+            "decode Implied",
+            "read 01FF 00",
+            "pop 01FF 00",
+        ]
+    );
+}
+
+#[test]
+fn test_observer_interrupts() {
+    let (mut vm, events) = observed("EA");
+    vm.set_interrupt_vectors(0x3000, 0x0200, 0x4000);
+
+    vm.reset();
+    vm.release_nmi();
+    vm.assert_nmi();
+    vm.step().unwrap();
+
+    let events = &events.borrow().0;
+    assert_eq!(events[0], "interrupt Reset 0200");
+    // I is already set by the reset, so the NMI doesn't change the status.
+    assert_eq!(events.last().unwrap(), "interrupt Nmi 3000");
+    assert!(!events.iter().any(|e| e.starts_with("status")));
+}
+
+#[test]
+fn test_observer_detach() {
+    let (mut vm, events) = observed("EAEA");
+
+    vm.step().unwrap();
+    assert!(vm.take_observer().is_some());
+    vm.step().unwrap();
+
+    assert_eq!(events.borrow().0.len(), 2);
+    assert!(vm.take_observer().is_none());
+}
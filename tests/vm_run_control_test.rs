@@ -0,0 +1,100 @@
+use vm6502::prelude::*;
+
+mod common;
+
+// 0x0200 JSR $0208
+// 0x0203 LDA #$02
+// 0x0205 BRK
+// 0x0206 NOP, NOP
+// 0x0208 LDX #$01
+// 0x020A JSR $020E
+// 0x020D RTS
+// 0x020E INY
+// 0x020F RTS
+const PROGRAM: &str = "200802A90200EAEAA201200E0260C860";
+
+#[test]
+fn test_step_over() {
+    let mut vm = common::vm(PROGRAM);
+
+    assert_eq!(vm.step_over().unwrap(), StopReason::StepComplete);
+    assert_eq!(vm.registers.pc, 0x0203);
+    assert_eq!(vm.registers.x, 0x01);
+    assert_eq!(vm.registers.y, 0x01);
+    assert_eq!(vm.registers.sp, 0xFF);
+
+    // Anything but JSR is a single step.
+    assert_eq!(vm.step_over().unwrap(), StopReason::StepComplete);
+    assert_eq!(vm.registers.pc, 0x0205);
+}
+
+#[test]
+fn test_step_over_breakpoint() {
+    let mut vm = common::vm(PROGRAM);
+    let id = vm.add_breakpoint(Breakpoint::Pc(0x020E));
+
+    assert_eq!(
+        vm.step_over().unwrap(),
+        StopReason::Breakpoint { id, pc: 0x020E }
+    );
+}
+
+#[test]
+fn test_step_out() {
+    let mut vm = common::vm(PROGRAM);
+    vm.run_until_pc(0x020E).unwrap();
+
+    assert_eq!(vm.step_out().unwrap(), StopReason::StepComplete);
+    assert_eq!(vm.registers.pc, 0x020D);
+
+    assert_eq!(vm.step_out().unwrap(), StopReason::StepComplete);
+    assert_eq!(vm.registers.pc, 0x0203);
+    assert_eq!(vm.registers.sp, 0xFF);
+}
+
+#[test]
+fn test_run_until_pc() {
+    let mut vm = common::vm(PROGRAM);
+
+    assert_eq!(
+        vm.run_until_pc(0x020A).unwrap(),
+        StopReason::ReachedPc(0x020A)
+    );
+    assert_eq!(vm.registers.x, 0x01);
+
+    assert_eq!(vm.run_until_pc(0x1234).unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn test_run_for_limits() {
+    let mut vm = common::vm(PROGRAM);
+
+    assert_eq!(
+        vm.run_for_instructions(3).unwrap(),
+        StopReason::InstructionLimit
+    );
+    assert_eq!(vm.registers.pc, 0x020E);
+    assert_eq!(
+        vm.run_for_instructions(0).unwrap(),
+        StopReason::InstructionLimit
+    );
+    assert_eq!(vm.registers.pc, 0x020E);
+
+    let start = vm.cycles;
+    // INY takes 2, RTS 6.
+    assert_eq!(vm.run_for_cycles(3).unwrap(), StopReason::CycleLimit);
+    assert_eq!(vm.cycles - start, 8);
+}
+
+#[test]
+fn test_run_until_predicate() {
+    let mut vm = common::vm(PROGRAM);
+
+    let reason = vm.run_until(|vm| vm.registers.y == 1).unwrap();
+    assert_eq!(reason, StopReason::Predicate);
+    assert_eq!(vm.registers.pc, 0x020F);
+
+    let reason = vm.run_until(|_| false).unwrap();
+    assert_eq!(reason, StopReason::Halted);
+    assert_eq!(vm.registers.ac, 0x02);
+}
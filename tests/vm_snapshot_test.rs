@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use vm6502::prelude::*;
+
+/// A write only output port, recording everything written to it.
+struct Port(Rc<RefCell<Vec<u8>>>);
+
+impl Bus for Port {
+    fn read(&mut self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _address: u16, value: u8) {
+        self.0.borrow_mut().push(value);
+    }
+
+    fn peek(&self, _address: u16) -> u8 {
+        0xFF
+    }
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, "A9428D0003E8").unwrap();
+    vm.set_interrupt_vectors(0x1234, 0x0200, 0x5678);
+    vm.step().unwrap();
+    vm.assert_nmi();
+    vm.halted = true;
+    vm.undocumented = !vm.undocumented;
+
+    let snapshot = vm.snapshot();
+
+    let mut other = VirtualMachine::new();
+    other.restore(&snapshot).unwrap();
+
+    assert_eq!(other.snapshot(), snapshot);
+    assert_eq!(other.registers.pc, vm.registers.pc);
+    assert_eq!(other.registers.ac, 0x42);
+    assert_eq!(other.cycles, vm.cycles);
+    assert_eq!(other.addr_mode, vm.addr_mode);
+    assert_eq!(other.opcode, 0xA9);
+    assert!(other.halted && other.nmi && other.nmi_pending);
+    assert_eq!(other.undocumented, vm.undocumented);
+    assert_eq!(&other.flatmap[..], &vm.flatmap[..]);
+
+    // Both machines continue identically.
+    vm.halted = false;
+    other.halted = false;
+    for _ in 0..2 {
+        assert_eq!(vm.step().unwrap(), other.step().unwrap());
+    }
+    assert_eq!(other.snapshot(), vm.snapshot());
+}
+
+#[test]
+fn test_snapshot_rejects_invalid() {
+    let mut vm = VirtualMachine::new();
+    let mut snapshot = vm.snapshot();
+
+    snapshot[4] = 0xFF;
+    let err = vm.restore(&snapshot).unwrap_err();
+    assert!(matches!(err, VmError::InvalidSnapshot { .. }));
+    assert!(err.to_string().contains("unsupported version"));
+
+    // Version 1 snapshots had no room for the jammed and undocumented state.
+    snapshot[4] = 0x01;
+    let err = vm.restore(&snapshot).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("unsupported version 1, expected 2"));
+
+    let snapshot = vm.snapshot();
+    let err = vm.restore(&snapshot[..snapshot.len() - 1]).unwrap_err();
+    assert!(matches!(err, VmError::InvalidSnapshot { .. }));
+
+    let err = vm.restore(b"nope").unwrap_err();
+    assert!(matches!(err, VmError::InvalidSnapshot { .. }));
+}
+
+#[test]
+fn test_snapshot_file() {
+    let path = std::env::temp_dir().join("vm6502_snapshot_test.bin");
+    let path = path.to_str().unwrap();
+
+    let mut vm = VirtualMachine::new();
+    vm.set_program(0x0000, "6901").unwrap();
+    vm.step().unwrap();
+    vm.save_snapshot(path).unwrap();
+
+    let mut other = VirtualMachine::new();
+    other.load_snapshot(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(other.snapshot(), vm.snapshot());
+    assert!(matches!(
+        other.load_snapshot(path).unwrap_err(),
+        VmError::Io { .. }
+    ));
+}
+
+#[test]
+fn test_snapshot_memory_map() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut bus = MemoryMap::new();
+    bus.map(0xD000, 0xD0FF, Port(output.clone()));
+    bus.map_rom(0xE000, vec![0xEA; 0x2000]);
+
+    let mut vm = VirtualMachine::with_bus(bus);
+    vm.set_program(0x0000, "A9428D0003").unwrap();
+    let snapshot = vm.snapshot();
+
+    vm.flatmap.write(0x0200, 0x00);
+    vm.restore(&snapshot).unwrap();
+
+    // RAM comes back, while the port sees no writes and the ROM is unchanged.
+    assert_eq!(vm.flatmap.peek(0x0200), 0xA9);
+    assert!(output.borrow().is_empty());
+    assert_eq!(vm.flatmap.peek(0xE000), 0xEA);
+    assert_eq!(vm.snapshot(), snapshot);
+}
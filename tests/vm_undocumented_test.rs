@@ -0,0 +1,210 @@
+use vm6502::opcode_name;
+use vm6502::prelude::*;
+
+mod common;
+use common::flags;
+
+/// A machine with the undocumented opcodes enabled and `program` at $0200.
+fn machine(program: &str) -> VirtualMachine {
+    let mut vm = common::vm(program);
+    vm.undocumented = true;
+    vm
+}
+
+#[test]
+fn test_undocumented_are_illegal_when_disabled() {
+    let mut vm = machine("A710");
+    vm.undocumented = false;
+
+    assert_eq!(
+        vm.step().unwrap_err(),
+        VmError::IllegalOpcode {
+            pc: 0x0200,
+            opcode: 0xA7
+        }
+    );
+
+    // The unstable opcodes stay illegal either way.
+    let mut vm = machine("8B10");
+    assert!(matches!(
+        vm.step(),
+        Err(VmError::IllegalOpcode { opcode: 0x8B, .. })
+    ));
+}
+
+#[test]
+fn test_every_undocumented_opcode_steps() {
+    for op in UNDOCUMENTED_OPCODES {
+        let mut vm = machine(&format!("{:02X}0000", op));
+
+        let cycles = vm.step().unwrap();
+        let mode = undocumented_mode(op).unwrap();
+        assert_eq!(cycles, OPCODE_CYCLES[op as usize] as u64, "{:02X}", op);
+        assert_eq!(vm.jammed, opcode_name!(op) == "JAM", "{:02X}", op);
+        if !vm.jammed {
+            assert_eq!(vm.registers.pc, 0x0201 + mode.operand_len(), "{:02X}", op);
+        }
+    }
+}
+
+#[test]
+fn test_lax_sax() {
+    // LAX $10, SAX $11
+    let mut vm = machine("A7108711");
+    vm.flatmap[0x0010] = 0xF3;
+
+    vm.step().unwrap();
+    assert_eq!((vm.registers.ac, vm.registers.x), (0xF3, 0xF3));
+    assert_eq!(flags(&vm), (false, false, false, true));
+
+    vm.registers.x = 0x0F;
+    vm.step().unwrap();
+    assert_eq!(vm.flatmap[0x0011], 0x03);
+}
+
+#[test]
+fn test_read_modify_write() {
+    // SLO $10, RLA $11, SRE $12, RRA $13, DCP $14, ISC $15
+    let mut vm = machine("0710271147126713C714E715");
+    vm.flatmap[0x0010..=0x0015].copy_from_slice(&[0x81, 0x81, 0x03, 0x02, 0x43, 0x0F]);
+
+    vm.registers.ac = 0x10;
+    vm.step().unwrap();
+    assert_eq!((vm.flatmap[0x0010], vm.registers.ac), (0x02, 0x12));
+    assert_eq!(flags(&vm), (true, false, false, false));
+
+    vm.registers.ac = 0xFF;
+    vm.step().unwrap();
+    assert_eq!((vm.flatmap[0x0011], vm.registers.ac), (0x03, 0x03));
+    assert!(vm.get_status(Status::Carry));
+
+    vm.registers.ac = 0x11;
+    vm.step().unwrap();
+    assert_eq!((vm.flatmap[0x0012], vm.registers.ac), (0x01, 0x10));
+    assert!(vm.get_status(Status::Carry));
+
+    vm.registers.ac = 0x01;
+    vm.step().unwrap();
+    assert_eq!((vm.flatmap[0x0013], vm.registers.ac), (0x81, 0x82));
+    assert_eq!(flags(&vm), (false, false, false, true));
+
+    vm.registers.ac = 0x42;
+    vm.step().unwrap();
+    assert_eq!(vm.flatmap[0x0014], 0x42);
+    assert_eq!(flags(&vm), (true, true, false, false));
+
+    vm.registers.ac = 0x30;
+    vm.set_status(Status::Carry, true);
+    vm.step().unwrap();
+    assert_eq!((vm.flatmap[0x0015], vm.registers.ac), (0x10, 0x20));
+    assert_eq!(flags(&vm), (true, false, false, false));
+}
+
+#[test]
+fn test_immediate() {
+    // ANC #$80, ALR #$03, ARR #$FF, SBX #$01, SBC #$01
+    let mut vm = machine("0B804B036BFFCB01EB01");
+
+    vm.registers.ac = 0xFF;
+    vm.step().unwrap();
+    assert_eq!(vm.registers.ac, 0x80);
+    assert_eq!(flags(&vm), (true, false, false, true));
+
+    vm.registers.ac = 0xFF;
+    vm.step().unwrap();
+    assert_eq!(vm.registers.ac, 0x01);
+    assert_eq!(flags(&vm), (true, false, false, false));
+
+    vm.registers.ac = 0xC0;
+    vm.step().unwrap();
+    assert_eq!(vm.registers.ac, 0xE0);
+    assert_eq!(flags(&vm), (true, false, false, true));
+
+    vm.registers.ac = 0x0F;
+    vm.registers.x = 0xF3;
+    vm.step().unwrap();
+    assert_eq!(vm.registers.x, 0x02);
+    assert_eq!(flags(&vm), (true, false, false, false));
+
+    vm.registers.ac = 0x10;
+    vm.step().unwrap();
+    assert_eq!(vm.registers.ac, 0x0F);
+    assert_eq!(flags(&vm), (true, false, false, false));
+}
+
+#[test]
+fn test_decimal_arr() {
+    // ARR #$55
+    let mut vm = machine("6B55");
+    vm.registers.ac = 0xFF;
+    vm.set_status(Status::Decimal, true);
+
+    vm.step().unwrap();
+    assert_eq!(vm.registers.ac, 0x80);
+    assert_eq!(flags(&vm), (true, false, true, false));
+}
+
+#[test]
+fn test_page_cross_cycles() {
+    // NOP $10FF,X, SLO $10FF,X, LAX $10FF,Y
+    let mut vm = machine("1CFF101FFF10BFFF10");
+    vm.registers.x = 0x01;
+    vm.registers.y = 0x01;
+
+    assert_eq!(vm.step().unwrap(), 5);
+    // Read-modify-write ops always take the extra cycle.
+    assert_eq!(vm.step().unwrap(), 7);
+    assert_eq!(vm.step().unwrap(), 5);
+}
+
+#[test]
+fn test_jam() {
+    // INX, JAM
+    let mut vm = machine("E802");
+    vm.flatmap[0xFFFC] = 0x00;
+    vm.flatmap[0xFFFD] = 0x02;
+
+    assert_eq!(vm.execute(), Ok(StopReason::Jammed { pc: 0x0201 }));
+    assert!(vm.jammed && vm.halted);
+    assert_eq!(vm.registers.x, 1);
+
+    // Only a reset gets it going again.
+    vm.halted = false;
+    assert_eq!(vm.execute(), Ok(StopReason::Jammed { pc: 0x0201 }));
+
+    let mut other = VirtualMachine::new();
+    other.restore(&vm.snapshot()).unwrap();
+    assert!(other.jammed);
+
+    vm.reset();
+    assert!(!vm.jammed);
+    assert_eq!(vm.run_for_instructions(1), Ok(StopReason::InstructionLimit));
+    assert_eq!(vm.registers.x, 2);
+}
+
+#[test]
+fn test_disassemble_undocumented() {
+    let mut vm = machine("A710B3208B02");
+
+    let text = vm
+        .disassemble_count(0x0200, 4)
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(text, vec!["*LAX $10", "*LAX ($20),Y", ".byte $8B", "*JAM"]);
+
+    let line = vm.trace_line();
+    assert_eq!(
+        line.to_string(),
+        "0200  A7 10    *LAX $10                         A:00 X:00 Y:00 P:20 SP:FF CYC:0"
+    );
+    assert_eq!(parse_trace(&line.to_string()), vec![line]);
+
+    // Without them, and outside of a machine, they're data.
+    vm.undocumented = false;
+    assert_eq!(vm.disassemble_count(0x0200, 1)[0].to_string(), ".byte $A7");
+    assert_eq!(
+        disassemble(&[0xA7, 0x10], 0x0200)[0].to_string(),
+        ".byte $A7"
+    );
+}